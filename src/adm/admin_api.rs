@@ -0,0 +1,109 @@
+//! Authenticated admin API for hot-reloading [AdmFilter] at runtime
+//!
+//! Unlike the read-only `/__dump__/*` introspection endpoints (see
+//! [crate::web::admin]), these endpoints mutate the live [AdmFilter] --
+//! adding or removing an advertiser's URL filters, or reloading the whole
+//! filter from [Settings] -- without a redeploy or waiting on the next
+//! bucket poll. Guarded by [Settings::admin_token_hash] via an
+//! `X-Api-Token` header, verified with constant-time bcrypt comparison;
+//! every failure mode (unset hash, missing header, mismatched token) is
+//! rejected with 401, since (unlike `/__dump__/*`) these endpoints are
+//! mutating and their existence doesn't need to be hidden from a 404.
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::{
+    adm::{settings::AdvertiserUrlFilter, AdmFilter},
+    error::HandlerResult,
+    server::ServerState,
+    settings::Settings,
+};
+
+/// Handles the authenticated admin mutation endpoints
+pub fn service(config: &mut web::ServiceConfig) {
+    config
+        .service(web::resource("/admin/advertisers").route(web::post().to(put_advertiser)))
+        .service(
+            web::resource("/admin/advertisers/{name}").route(web::delete().to(remove_advertiser)),
+        )
+        .service(web::resource("/admin/reload").route(web::post().to(reload)));
+}
+
+/// Whether `req` presents a valid `X-Api-Token` for `settings`. `false` for
+/// every failure mode: unset hash, missing/unreadable header, or mismatch.
+fn authorized(req: &HttpRequest, settings: &Settings) -> bool {
+    let Some(hash) = &settings.admin_token_hash else {
+        return false;
+    };
+    let Some(token) = req
+        .headers()
+        .get("X-Api-Token")
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    bcrypt::verify(token, hash).unwrap_or(false)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutAdvertiserBody {
+    pub name: String,
+    pub filters: HashMap<String, Vec<AdvertiserUrlFilter>>,
+}
+
+/// `POST /admin/advertisers` -- insert or replace an advertiser's
+/// per-country URL filters on the live filter (see
+/// [AdmFilter::put_advertiser]), effective on the very next request.
+async fn put_advertiser(
+    req: HttpRequest,
+    state: web::Data<ServerState>,
+    body: web::Json<PutAdvertiserBody>,
+) -> HttpResponse {
+    if !authorized(&req, &state.settings) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let body = body.into_inner();
+    let mut filter = state.filter.write().unwrap();
+    filter.put_advertiser(body.name, body.filters);
+    HttpResponse::Ok().finish()
+}
+
+/// `DELETE /admin/advertisers/{name}` -- remove an advertiser from the live
+/// filter entirely (see [AdmFilter::remove_advertiser]). 404 if no
+/// advertiser by that name was present.
+async fn remove_advertiser(
+    req: HttpRequest,
+    state: web::Data<ServerState>,
+    name: web::Path<String>,
+) -> HttpResponse {
+    if !authorized(&req, &state.settings) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let mut filter = state.filter.write().unwrap();
+    if filter.remove_advertiser(&name) {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NotFound().finish()
+    }
+}
+
+/// `POST /admin/reload` -- rebuild [AdmFilter] from the current [Settings]
+/// (the same construction path [crate::server::Server::with_settings] runs
+/// once at boot) and swap it into the live filter, picking up changes to
+/// the advertiser settings source without a restart.
+async fn reload(req: HttpRequest, state: web::Data<ServerState>) -> HttpResponse {
+    if !authorized(&req, &state.settings) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let mut settings = state.settings.clone();
+    match HandlerResult::<AdmFilter>::from(&mut settings) {
+        Ok(fresh) => {
+            *state.filter.write().unwrap() = fresh;
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
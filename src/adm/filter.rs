@@ -1,12 +1,16 @@
 use std::{
-    borrow::Cow, collections::HashSet, fmt::Debug, iter::FromIterator, sync::Arc, time::Duration,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
 };
 
 use actix_web::{http::Uri, rt};
 use actix_web_location::Location;
 use cadence::{CountedExt, StatsdClient};
-use google_cloud_storage::http::objects::{download::Range, get::GetObjectRequest};
 use lazy_static::lazy_static;
+use regex::Regex;
 use time::OffsetDateTime;
 use tokio::sync::RwLock;
 use url::Url;
@@ -16,22 +20,20 @@ use super::{
     tiles::{AdmTile, Tile},
 };
 use crate::{
-    adm::settings::{AdmDefaults, AdvertiserUrlFilter, PathFilter, PathMatching},
+    adm::settings::{
+        break_hosts, canonicalize_path, glob_match, settings_source, AdmDefaults,
+        AdvertiserUrlFilter, HostFilter, HostMatchMode, HostMatching, NameMatchSet, PathFilter,
+        PathMatching, ProxyConfig, RejectionAction,
+    },
     error::{HandlerError, HandlerErrorKind, HandlerResult},
     metrics::Metrics,
+    settings::Settings,
     tags::Tags,
     web::middleware::sentry as l_sentry,
     web::DeviceInfo,
 };
 
 lazy_static! {
-    static ref REQ_CLICK_PARAMS: Vec<&'static str> = vec!["ci", "ctag", "key", "version"];
-    static ref ALL_CLICK_PARAMS: HashSet<&'static str> = {
-        let opt_click_params = vec!["click-status"];
-        let mut all = HashSet::from_iter(REQ_CLICK_PARAMS.clone());
-        all.extend(opt_click_params);
-        all
-    };
     static ref DEFAULT_PATH_FILTER: Vec<PathFilter> = vec![PathFilter::default()];
 }
 
@@ -46,18 +48,29 @@ lazy_static! {
 pub struct AdmFilter {
     /// Filter settings by Advertiser name
     pub advertiser_filters: AdmAdvertiserSettings,
-    /// Ignored (not included but also not reported to Sentry) Advertiser names
-    pub ignore_list: HashSet<String>,
+    /// Ignored (not included but also not reported to Sentry) Advertiser
+    /// names, exact or glob (`*`/`?`) patterns -- see [NameMatchSet].
+    pub ignore_list: NameMatchSet,
     /// Temporary list of advertisers with legacy images built into firefox
-    /// for pre 91 tile support.
-    pub legacy_list: HashSet<String>,
+    /// for pre 91 tile support. Exact or glob (`*`/`?`) patterns -- see
+    /// [NameMatchSet].
+    pub legacy_list: NameMatchSet,
     pub all_include_regions: HashSet<String>,
     pub source: Option<String>,
     pub source_url: Option<url::Url>,
     pub last_updated: Option<OffsetDateTime>,
+    /// The settings source's fingerprint (S3 ETag, GCS generation, or file
+    /// mtime) as of `last_updated` -- see
+    /// [super::settings::SettingsSource::fetch_if_modified].
+    pub last_fingerprint: Option<String>,
     pub refresh_rate: Duration,
     pub defaults: AdmDefaults,
     pub excluded_countries_200: bool,
+    /// Outbound proxy to use for ADM partner requests, parsed from
+    /// `Settings::adm_proxy` -- see [ProxyConfig]. Not consulted for the
+    /// settings bucket poll in [spawn_updater], which builds its own
+    /// [super::settings::SettingsSource] per backend.
+    pub proxy_config: ProxyConfig,
 }
 
 /// Parse &str into a `Url`
@@ -85,19 +98,144 @@ fn get_host(url: &Url, species: &'static str) -> HandlerResult<String> {
 
 /// Check that a given URL is valid according to it's corresponding filter.
 ///
-/// Allows a partial match: a filter setting for "example.com" (["example",
-/// "com"]) allows "foo.example.com" and "quux.bar.example.com" (["quux",
-/// "bar", "example", "com"])
-fn check_url(url: Url, species: &'static str, filter: &[Vec<String>]) -> HandlerResult<bool> {
+/// A partial (subdomain) match is allowed for a given `filter` entry when
+/// either that entry's own `include_subdomains` is `Some(true)`, or it's
+/// `None` and the blanket `host_match` is [HostMatchMode::Suffix]: e.g. a
+/// filter setting for "example.com" (["example", "com"]) then allows
+/// "foo.example.com" and "quux.bar.example.com" (["quux", "bar", "example",
+/// "com"]). Subdomain matches are only allowed once "example.com" is
+/// confirmed to be at least a registrable domain per the Public Suffix List
+/// (see [super::psl]), so a bare public suffix like "co.uk" can never act
+/// as a wildcard root for every co.uk registrant. An entry with
+/// `include_subdomains: Some(false)`, or `None` under the (default)
+/// [HostMatchMode::Strict], never does this backwards-expanding search:
+/// only an exact host match is accepted.
+///
+/// `patterns` is an additional, precompiled set of regexes checked against
+/// the host alongside `filter`'s literal entries (e.g. for rotating
+/// subdomains that are impractical to enumerate literally) -- consulted
+/// regardless of `host_match`.
+fn check_url(
+    url: Url,
+    species: &'static str,
+    filter: &[HostFilter],
+    patterns: &[Regex],
+    host_match: HostMatchMode,
+) -> HandlerResult<bool> {
     let host = get_host(&url, species)?;
+    if host_matches_filter(&host, filter, patterns, host_match) {
+        return Ok(true);
+    }
+    Err(HandlerErrorKind::UnexpectedHost(species, host).into())
+}
+
+/// The host-matching core of [check_url], usable on a bare `host` string
+/// (e.g. a redirect hop's host, which has no `species` to blame an error
+/// on) -- see [check_url] for what `filter`/`patterns`/`host_match` mean.
+fn host_matches_filter(
+    host: &str,
+    filter: &[HostFilter],
+    patterns: &[Regex],
+    host_match: HostMatchMode,
+) -> bool {
     let domains: Vec<_> = host.split('.').collect();
     for allowed in filter {
-        let begin = domains.len() - allowed.len().min(domains.len());
-        if &domains[begin..] == allowed {
-            return Ok(true);
+        let labels = &allowed.labels;
+        let begin = domains.len() - labels.len().min(domains.len());
+        if domains[begin..] != labels[..] {
+            continue;
+        }
+        let is_subdomain_match = begin > 0;
+        if is_subdomain_match {
+            let allow_subdomains = allowed
+                .include_subdomains
+                .unwrap_or(host_match == HostMatchMode::Suffix);
+            if !allow_subdomains {
+                continue;
+            }
+            let allowed_host = labels.join(".");
+            if super::psl::registrable_domain(&allowed_host).as_deref()
+                != Some(allowed_host.as_str())
+            {
+                continue;
+            }
+        }
+        return true;
+    }
+    patterns.iter().any(|pattern| pattern.is_match(host))
+}
+
+/// Whether `host` matches a filter entry's `host_matching` (see
+/// [HostMatching]): an `Exact` host matches itself, or -- when
+/// `allow_subdomains` is set -- any subdomain of it; a `Suffix` host
+/// (written `*.foo.com`) matches any subdomain but never the host itself,
+/// regardless of `allow_subdomains`.
+fn matches_host(host_matching: &HostMatching, host: &str, allow_subdomains: bool) -> bool {
+    match host_matching {
+        HostMatching::Exact(filter_host) => {
+            host == filter_host || (allow_subdomains && is_subdomain_of(host, filter_host))
+        }
+        HostMatching::Suffix(filter_host) => is_subdomain_of(host, filter_host),
+    }
+}
+
+/// Whether `host` is a strict subdomain of `filter_host`, compared
+/// label-by-label (via [break_hosts], never a substring check) and only
+/// once `filter_host` is confirmed to be at least a registrable domain (see
+/// [super::psl]), so a bare public suffix can never act as a wildcard root.
+fn is_subdomain_of(host: &str, filter_host: &str) -> bool {
+    let host_labels = break_hosts(host.to_owned());
+    let filter_labels = break_hosts(filter_host.to_owned());
+    if host_labels.len() <= filter_labels.len() {
+        return false;
+    }
+    let begin = host_labels.len() - filter_labels.len();
+    host_labels[begin..] == filter_labels[..]
+        && super::psl::registrable_domain(filter_host).is_some()
+}
+
+impl ProxyConfig {
+    /// Build the `reqwest::Proxy` that outbound partner requests should be
+    /// sent through, if any. `ByDomain` is realized as a single
+    /// `Proxy::custom` so one `reqwest::Client` can serve every configured
+    /// host: at request time it looks up the target host the same way
+    /// [AdvertiserUrlFilter::host] is matched (exact, or subdomain-only for
+    /// a leading `*.`), falling back to a direct connection if nothing
+    /// matches.
+    pub fn into_reqwest_proxy(&self) -> HandlerResult<Option<reqwest::Proxy>> {
+        match self {
+            ProxyConfig::None => Ok(None),
+            ProxyConfig::Global { url } => {
+                let proxy = reqwest::Proxy::all(url.as_str()).map_err(|e| {
+                    HandlerError::internal(&format!("Invalid adm_proxy url {:?}: {:?}", url, e))
+                })?;
+                Ok(Some(proxy))
+            }
+            ProxyConfig::ByDomain(domains) => {
+                let resolved = domains
+                    .iter()
+                    .map(|d| {
+                        Url::parse(&d.url)
+                            .map(|url| (d.host_matching.clone(), url))
+                            .map_err(|e| {
+                                HandlerError::internal(&format!(
+                                    "Invalid adm_proxy url {:?}: {:?}",
+                                    d.url, e
+                                ))
+                            })
+                    })
+                    .collect::<HandlerResult<Vec<_>>>()?;
+                let proxy = reqwest::Proxy::custom(move |url| {
+                    let host = url.host_str()?;
+                    resolved
+                        .iter()
+                        .find(|(host_matching, _)| matches_host(host_matching, host, false))
+                        .map(|(_, proxy_url)| proxy_url.clone())
+                });
+                Ok(Some(proxy))
+            }
         }
     }
-    Err(HandlerErrorKind::UnexpectedHost(species, host).into())
 }
 
 /// Background updater.
@@ -106,7 +244,7 @@ pub fn spawn_updater(
     is_cloud: bool,
     refresh_rate: Duration,
     filter: &Arc<RwLock<AdmFilter>>,
-    storage_client: Arc<google_cloud_storage::client::Client>,
+    settings: Arc<Settings>,
     metrics: Arc<StatsdClient>,
 ) -> HandlerResult<()> {
     {
@@ -117,29 +255,43 @@ pub fn spawn_updater(
     let mfilter = Arc::clone(filter);
     rt::spawn(async move {
         loop {
-            updater(&mfilter, &storage_client, &metrics).await;
+            updater(&mfilter, &settings, &metrics).await;
             rt::time::sleep(refresh_rate).await;
         }
     });
     Ok(())
 }
 
-/// Update `AdmFilter` from the Cloud Storage settings if they've been updated
-async fn updater(
-    filter: &Arc<RwLock<AdmFilter>>,
-    storage_client: &google_cloud_storage::client::Client,
-    metrics: &Arc<StatsdClient>,
-) {
+/// Update `AdmFilter` from the settings source if it's been modified
+async fn updater(filter: &Arc<RwLock<AdmFilter>>, settings: &Settings, metrics: &Arc<StatsdClient>) {
     // Do the check before matching so that the read lock can be released right away.
-    let result = filter.read().await.fetch_new_settings(storage_client).await;
+    let result = filter.read().await.fetch_new_settings(settings).await;
     match result {
-        Ok(Some((new_settings, last_updated))) => {
-            filter.write().await.update(new_settings, last_updated);
-            trace!("AdmFilter updated from cloud storage");
-            metrics.incr("filter.adm.update.ok").ok();
+        Ok(Some((new_settings, last_updated, fingerprint))) => {
+            let updated = filter
+                .write()
+                .await
+                .update(new_settings, last_updated, fingerprint);
+            match updated {
+                Ok(()) => {
+                    trace!("AdmFilter updated from cloud storage");
+                    metrics.incr("filter.adm.update.ok").ok();
+                }
+                Err(reason) => {
+                    trace!("Rejected new ADM settings: {}", reason);
+                    metrics.incr("filter.adm.update.rejected").ok();
+                    let err = HandlerError::internal(&format!(
+                        "Rejected new ADM settings: {}",
+                        reason
+                    ));
+                    l_sentry::report(&err, &err.tags);
+                }
+            }
         }
         Ok(None) => {
-            metrics.incr("filter.adm.update.check.skip").ok();
+            // The source's fingerprint (ETag/generation/mtime) matched what
+            // we already have -- the 304-equivalent path.
+            metrics.incr("filter.adm.update.not_modified").ok();
         }
         Err(e) => {
             trace!("AdmFilter update failed: {:?}", e);
@@ -154,76 +306,150 @@ impl AdmFilter {
     /// convenience function to determine if settings are cloud ready.
     pub fn is_cloud(&self) -> bool {
         if let Some(source) = &self.source_url {
-            return source.scheme() == "gs";
+            return matches!(source.scheme(), "gs" | "s3" | "file");
         }
         false
     }
 
+    /// Insert or replace `name`'s per-country URL filters, e.g. from the
+    /// `POST /admin/advertisers` admin endpoint (see
+    /// [crate::adm::admin_api]) -- takes effect on the very next request,
+    /// without a redeploy or waiting on the next bucket poll.
+    pub fn put_advertiser(
+        &mut self,
+        name: String,
+        country_filters: HashMap<String, Vec<AdvertiserUrlFilter>>,
+    ) {
+        let name = name.to_lowercase();
+        self.advertiser_filters.deleted.remove(&name);
+        self.advertiser_filters
+            .adm_advertisers
+            .insert(name, country_filters);
+    }
+
+    /// Remove `name` entirely, e.g. from the
+    /// `DELETE /admin/advertisers/{name}` admin endpoint. Returns whether an
+    /// advertiser by that name was present.
+    pub fn remove_advertiser(&mut self, name: &str) -> bool {
+        self.advertiser_filters
+            .adm_advertisers
+            .remove(&name.to_lowercase())
+            .is_some()
+    }
+
+    /// Whether `host` is an acceptable redirect target for an outbound ADM
+    /// or image fetch -- see [crate::server::redirect::safe_policy]. A
+    /// redirect's species (click/impression/image) isn't known at the
+    /// `reqwest::redirect::Policy` layer, so `host` is accepted if it
+    /// matches any one of `click_hosts`, `impression_hosts`, or
+    /// `image_hosts` (or their pattern counterparts).
+    pub fn allows_redirect_host(&self, host: &str) -> bool {
+        let defaults = &self.defaults;
+        host_matches_filter(
+            host,
+            &defaults.click_hosts,
+            &defaults.click_host_patterns,
+            defaults.host_match,
+        ) || host_matches_filter(
+            host,
+            &defaults.impression_hosts,
+            &defaults.impression_host_patterns,
+            defaults.host_match,
+        ) || host_matches_filter(
+            host,
+            &defaults.image_hosts,
+            &defaults.image_host_patterns,
+            defaults.host_match,
+        )
+    }
+
+    /// Check if `advertiser` is in the ignore list (exact name or glob
+    /// pattern, e.g. `test-*`), case-insensitively.
+    pub fn is_ignored(&self, advertiser: &str) -> bool {
+        self.ignore_list.matches(advertiser)
+    }
+
     /// Report the error directly to sentry
     fn report(&self, error: &HandlerError, tags: &mut Tags) {
-        // trace!(&error, &tags);
-        // TODO: if not error.is_reportable, just add to metrics.
         let mut merged_tags = error.tags.clone();
         merged_tags.extend(tags.clone());
         l_sentry::report(error, &merged_tags);
     }
 
-    /// Check if the bucket has been modified since the last time we updated,
-    /// returning new `AdmAdvertiserSettings` if so.
+    /// Reject a tile for `error`, honoring `self.defaults.rejection_actions`'
+    /// configured [RejectionAction] for `action` (`Metric`/`Silent` skip the
+    /// Sentry report that `Report` -- the default -- would otherwise send;
+    /// `Silent` additionally skips the metric).
+    fn reject(
+        &self,
+        metric: &'static str,
+        action: RejectionAction,
+        error: HandlerError,
+        tags: &mut Tags,
+        metrics: &Metrics,
+    ) {
+        if action == RejectionAction::Silent {
+            return;
+        }
+        metrics.incr_with_tags(metric, Some(tags));
+        if action == RejectionAction::Report {
+            self.report(&error, tags);
+        }
+    }
+
+    /// Check if the bucket (or `file://` path) has changed since the last
+    /// time we updated -- via a single conditional request where the
+    /// backend supports it (see
+    /// [super::settings::SettingsSource::fetch_if_modified]) -- returning
+    /// new `AdmAdvertiserSettings` if so.
     pub async fn fetch_new_settings(
         &self,
-        storage_client: &google_cloud_storage::client::Client,
-    ) -> HandlerResult<Option<(AdmAdvertiserSettings, OffsetDateTime)>> {
+        settings: &Settings,
+    ) -> HandlerResult<Option<(AdmAdvertiserSettings, OffsetDateTime, String)>> {
         // don't update non-bucket versions (for now)
         if !self.is_cloud() {
             return Ok(None);
         }
-        if let Some(bucket) = &self.source_url {
-            let host = bucket
-                .host()
-                .ok_or_else(|| {
-                    HandlerError::internal(&format!("Missing bucket Host {:?}", self.source))
-                })?
-                .to_string();
-            let path = bucket.path().trim_start_matches('/');
-            let request = GetObjectRequest {
-                bucket: host,
-                object: path.into(),
-                ..Default::default()
-            };
-            let obj = storage_client.get_object(&request).await?;
-            let Some(obj_updated) = obj.updated else {
-                Err(HandlerErrorKind::General(format!("ADM Settings missing last updated timestamp")))?
-            };
-            if let Some(last_updated) = self.last_updated {
-                // if the remote object is not newer than the local object, do nothing
-                if obj_updated <= last_updated {
-                    return Ok(None);
-                }
-            };
-
-            let bytes = storage_client
-                .download_object(&request, &Range::default())
-                .await?;
-            let contents = String::from_utf8(bytes).map_err(|e| {
-                HandlerErrorKind::General(format!("Could not read ADM Settings: {:?}", e))
-            })?;
-            let new_settings = serde_json::from_str(&contents).map_err(|e| {
-                HandlerErrorKind::General(format!("Could not read ADM Settings: {:?}", e))
-            })?;
-            return Ok(Some((new_settings, obj_updated)));
-        }
-        Ok(None)
+        let Some(bucket) = &self.source_url else {
+            return Ok(None);
+        };
+        let source = settings_source(settings, bucket)
+            .await
+            .map_err(|e| HandlerErrorKind::General(format!("{:?}", e)))?;
+        let Some((contents, modified, fingerprint)) = source
+            .fetch_if_modified(self.last_fingerprint.as_ref())
+            .await
+            .map_err(|e| HandlerErrorKind::General(format!("{:?}", e)))?
+        else {
+            return Ok(None);
+        };
+        let contents = String::from_utf8(contents).map_err(|e| {
+            HandlerErrorKind::General(format!("Could not read ADM Settings: {:?}", e))
+        })?;
+        let new_settings = serde_json::from_str(&contents).map_err(|e| {
+            HandlerErrorKind::General(format!("Could not read ADM Settings: {:?}", e))
+        })?;
+        Ok(Some((new_settings, modified, fingerprint)))
     }
 
-    /// Clear and update the ADM filter data from new `AdmAdvertiserSettings`
+    /// Clear and update the ADM filter data from new `AdmAdvertiserSettings`,
+    /// first running it through [AdmAdvertiserSettings::validate]. On
+    /// failure, the previous good settings (and `last_updated`/
+    /// `last_fingerprint`) are left untouched and `Err` describes the
+    /// rejected settings -- a poisoned config push can't silently corrupt
+    /// live filtering until the next good one.
     pub fn update(
         &mut self,
         settings: AdmAdvertiserSettings,
         last_updated: OffsetDateTime,
-    ) {
+        fingerprint: String,
+    ) -> Result<(), String> {
+        settings.validate()?;
         self.all_include_regions.clear();
         self.advertiser_filters.adm_advertisers.clear();
+        self.advertiser_filters.aliases = settings.aliases.clone();
+        self.advertiser_filters.host_denylist = settings.host_denylist.clone();
+        self.advertiser_filters.host_allowlist = settings.host_allowlist.clone();
         for (adv, setting) in settings.adm_advertisers {
             for country in setting.keys() {
                 self.all_include_regions.insert(country.clone());
@@ -232,7 +458,60 @@ impl AdmFilter {
                 .adm_advertisers
                 .insert(adv.to_lowercase(), setting);
         }
+        self.advertiser_filters.expand_aliases();
+        self.advertiser_filters.reject_overbroad_hosts();
         self.last_updated = Some(last_updated);
+        self.last_fingerprint = Some(fingerprint);
+        Ok(())
+    }
+
+    /// Check the request's location against the advertiser's
+    /// `include_regions`.
+    ///
+    /// `filters` are the `AdvertiserUrlFilter`s for the tile's matched
+    /// country. Entries are checked at both country level (`"US"`) and
+    /// region level (`"US/TX"`). If none of `filters` declare any
+    /// `include_regions` of their own, falls back to `self.defaults`'
+    /// `include_regions` (mirroring the `paths`/`DEFAULT_PATH_FILTER`
+    /// fallback in `check_advertiser`). An empty resulting list (the
+    /// common case, since this is opt-in) means unrestricted.
+    fn check_region(
+        &self,
+        filters: &[AdvertiserUrlFilter],
+        location: &Location,
+        tile: &AdmTile,
+        tags: &mut Tags,
+    ) -> HandlerResult<()> {
+        let own_regions: Vec<&str> = filters
+            .iter()
+            .flat_map(|filter| filter.include_regions.iter().map(String::as_str))
+            .collect();
+        let regions: Vec<&str> = if own_regions.is_empty() {
+            self.defaults
+                .include_regions
+                .iter()
+                .map(String::as_str)
+                .collect()
+        } else {
+            own_regions
+        };
+        if regions.is_empty() {
+            return Ok(());
+        }
+
+        let country = location.country();
+        let region = location.region();
+        let qualified = (!region.is_empty()).then(|| format!("{country}/{region}"));
+        if regions.iter().any(|allowed| {
+            *allowed == country.as_str() || Some(*allowed) == qualified.as_deref()
+        }) {
+            return Ok(());
+        }
+
+        tags.add_tag("type", "Region");
+        tags.add_extra("tile", &tile.name);
+        tags.add_extra("country", &country);
+        Err(HandlerErrorKind::InvalidRegion(tile.name.clone(), country).into())
     }
 
     /// Check the advertiser URL
@@ -254,19 +533,49 @@ impl AdmFilter {
         }
 
         // do a quick string comparison between the supplied host and the provided filter.
-        let mut path = Cow::from(parsed.path());
+        //
+        // The path is canonicalized (percent-decoded and dot-segment
+        // resolved -- see `canonicalize_path`) before comparison, just as
+        // `PathFilter::value` is at parse time, so e.g. `/%63a/`, `/./ca/`,
+        // and `//ca/` all compare equal to a configured `/ca/`.
+        let canonical_path = canonicalize_path(parsed.path());
+        let mut path = Cow::from(canonical_path.as_str());
         if !path.ends_with('/') {
             path.to_mut().push('/');
         }
 
         for filter in filters {
-            if host == filter.host {
+            if matches_host(&filter.host_matching, &host, filter.allow_subdomains) {
                 let paths = filter.paths.as_ref().unwrap_or(&DEFAULT_PATH_FILTER);
                 for rule in paths {
                     match rule.matching {
-                        // Note that the original path is used for exact matching
-                        PathMatching::Exact if rule.value == parsed.path() => return Ok(()),
+                        // Note that the original (un-padded) canonical path is
+                        // used for exact matching
+                        PathMatching::Exact if rule.value == canonical_path => return Ok(()),
                         PathMatching::Prefix if path.starts_with(&rule.value) => return Ok(()),
+                        // Also uses the original (un-padded) canonical path:
+                        // unlike `prefix`, a glob isn't required to span to
+                        // the end of a segment unless it says so with a
+                        // trailing `*`/`**`.
+                        PathMatching::Glob
+                            if rule
+                                .glob
+                                .as_deref()
+                                .is_some_and(|glob| glob_match(glob, canonical_path.as_bytes())) =>
+                        {
+                            return Ok(())
+                        }
+                        // Matched against the original (un-padded) canonical
+                        // path, same as `exact`/`glob` -- the pattern itself
+                        // is responsible for anchoring.
+                        PathMatching::Regex
+                            if rule
+                                .regex
+                                .as_ref()
+                                .is_some_and(|re| re.is_match(&canonical_path)) =>
+                        {
+                            return Ok(())
+                        }
 
                         _ => continue,
                     }
@@ -281,8 +590,10 @@ impl AdmFilter {
 
     /// Check the click URL
     ///
-    /// Internally, this will use the hard-coded `req_keys` and `opt_keys` to specify
-    /// the required and optional query parameter keys that can appear in the click_url
+    /// Internally, this uses `defaults.click_req_params`/`click_opt_params`
+    /// (data, configurable alongside the rest of `AdmDefaults`) to specify
+    /// the required and optional query parameter keys that can appear in the
+    /// click_url.
     fn check_click(
         &self,
         defaults: &AdmDefaults,
@@ -302,7 +613,13 @@ impl AdmFilter {
             .collect::<HashSet<String>>();
         // run the gauntlet of checks.
 
-        if !check_url(parsed, "Click", &defaults.click_hosts)? {
+        if !check_url(
+            parsed,
+            "Click",
+            &defaults.click_hosts,
+            &defaults.click_host_patterns,
+            defaults.host_match,
+        )? {
             trace!("bad url: url={:?}", url);
             tags.add_tag("type", species);
             tags.add_extra("tile", &tile.name);
@@ -312,8 +629,8 @@ impl AdmFilter {
             return Err(HandlerErrorKind::InvalidHost(species, host).into());
         }
 
-        for key in &*REQ_CLICK_PARAMS {
-            if !query_keys.contains(*key) {
+        for key in &defaults.click_req_params {
+            if !query_keys.contains(key) {
                 trace!("missing param: key={:?} url={:?}", &key, url);
                 tags.add_tag("type", species);
                 tags.add_extra("tile", &tile.name);
@@ -324,8 +641,14 @@ impl AdmFilter {
                 return Err(HandlerErrorKind::InvalidHost(species, host).into());
             }
         }
+        let all_click_params: HashSet<&str> = defaults
+            .click_req_params
+            .iter()
+            .chain(&defaults.click_opt_params)
+            .map(String::as_str)
+            .collect();
         for key in query_keys {
-            if !ALL_CLICK_PARAMS.contains(key.as_str()) {
+            if !all_click_params.contains(key.as_str()) {
                 trace!("invalid param key={:?} url={:?}", &key, url);
                 tags.add_tag("type", species);
                 tags.add_extra("tile", &tile.name);
@@ -366,7 +689,13 @@ impl AdmFilter {
             let host = get_host(&parsed, species)?;
             return Err(HandlerErrorKind::InvalidHost(species, host).into());
         }
-        check_url(parsed, species, &defaults.impression_hosts)?;
+        check_url(
+            parsed,
+            species,
+            &defaults.impression_hosts,
+            &defaults.impression_host_patterns,
+            defaults.host_match,
+        )?;
         Ok(())
     }
 
@@ -381,13 +710,99 @@ impl AdmFilter {
     ) -> HandlerResult<()> {
         // if no hosts are defined, then accept all (this allows
         // for backward compatibility)
-        if defaults.image_hosts.is_empty() {
+        if defaults.image_hosts.is_empty() && defaults.image_host_patterns.is_empty() {
             return Ok(());
         }
         let url = &tile.image_url;
         let species = "Image";
         let parsed = parse_url(url, species, &tile.name, tags)?;
-        check_url(parsed, species, &defaults.image_hosts)?;
+        check_url(
+            parsed,
+            species,
+            &defaults.image_hosts,
+            &defaults.image_host_patterns,
+            defaults.host_match,
+        )?;
+        Ok(())
+    }
+
+    /// Crate-wide kill-switch, checked before any per-advertiser filter:
+    /// reject the tile if its advertiser, click, or image URL host matches
+    /// `host_denylist`, or -- when `host_allowlist` is non-empty -- if none
+    /// of them matches it. Unlike the per-advertiser `*_hosts` fields, this
+    /// applies regardless of which advertiser the tile claims to be, so a
+    /// domain that turns malicious can be cut off crate-wide without
+    /// waiting on every advertiser's own filter to be updated. A host that
+    /// fails to parse is treated as a non-match rather than an error here;
+    /// `check_advertiser`/`check_click`/`check_image_hosts` are what
+    /// actually validate each URL.
+    fn check_blocklist(&self, tile: &AdmTile, tags: &mut Tags) -> HandlerResult<()> {
+        let denylist = &self.advertiser_filters.host_denylist;
+        let allowlist = &self.advertiser_filters.host_allowlist;
+        if denylist.is_empty() && allowlist.is_empty() {
+            return Ok(());
+        }
+        let hosts: Vec<String> = [
+            tile.advertiser_url.as_str(),
+            tile.click_url.as_str(),
+            tile.image_url.as_str(),
+        ]
+        .into_iter()
+        .filter_map(|url| Url::parse(url).ok())
+        .filter_map(|url| url.host_str().map(str::to_owned))
+        .collect();
+        let blocked = hosts
+            .iter()
+            .any(|host| host_matches_filter(host, denylist, &[], HostMatchMode::Strict))
+            || (!allowlist.is_empty()
+                && !hosts
+                    .iter()
+                    .any(|host| host_matches_filter(host, allowlist, &[], HostMatchMode::Strict)));
+        if blocked {
+            tags.add_tag("type", "Blocklist");
+            tags.add_extra("tile", &tile.name);
+            return Err(HandlerErrorKind::UnexpectedHost("Blocklist", tile.name.clone()).into());
+        }
+        Ok(())
+    }
+
+    /// Reject a tile whose advertiser or image URL host is a bare IPv4/IPv6
+    /// literal rather than a registered domain -- a legitimate sponsored
+    /// destination is always one of the latter. Classifies the host via
+    /// [url::Host] before it collapses to a string the way [get_host] does,
+    /// since an `Ipv6` literal's `:`-delimited groups would defeat the
+    /// `.`-split that [host_matches_filter]/[is_subdomain_of] use for
+    /// ordinary domain hosts; the reported host is the literal's plain,
+    /// canonical (bracket-stripped) form rather than `url::Host`'s bracketed
+    /// `Display`. Skipped entirely when `defaults.allow_ip_hosts` opts a
+    /// deployment (e.g. a test/staging environment) into bare-IP
+    /// destinations.
+    fn check_ip_hosts(
+        &self,
+        defaults: &AdmDefaults,
+        tile: &AdmTile,
+        tags: &mut Tags,
+    ) -> HandlerResult<()> {
+        if defaults.allow_ip_hosts {
+            return Ok(());
+        }
+        for (url, species) in [
+            (&tile.advertiser_url, "Advertiser"),
+            (&tile.image_url, "Image"),
+        ] {
+            let Ok(parsed) = Url::parse(url) else {
+                continue;
+            };
+            let host = match parsed.host() {
+                Some(url::Host::Ipv4(addr)) => addr.to_string(),
+                Some(url::Host::Ipv6(addr)) => addr.to_string(),
+                _ => continue,
+            };
+            tags.add_tag("type", "IpHost");
+            tags.add_extra("tile", &tile.name);
+            tags.add_extra("url", url);
+            return Err(HandlerErrorKind::UnexpectedHost(species, host).into());
+        }
         Ok(())
     }
 
@@ -403,8 +818,28 @@ impl AdmFilter {
         tags: &mut Tags,
         metrics: &Metrics,
     ) -> HandlerResult<Option<Tile>> {
-        // Use strict matching for now, eventually, we may want to use backwards expanding domain
-        // searches, (.e.g "xyz.example.com" would match "example.com")
+        if let Err(e) = self.check_blocklist(&tile, tags) {
+            trace!("Rejecting tile: blocklisted");
+            self.reject(
+                "filter.adm.blocklist.reject",
+                self.defaults.rejection_actions.blocklisted,
+                e,
+                tags,
+                metrics,
+            );
+            return Ok(None);
+        }
+        if let Err(e) = self.check_ip_hosts(&self.defaults, &tile, tags) {
+            trace!("Rejecting tile: IP-literal host");
+            self.reject(
+                "filter.adm.err.ip_host",
+                self.defaults.rejection_actions.ip_host,
+                e,
+                tags,
+                metrics,
+            );
+            return Ok(None);
+        }
         match self
             .advertiser_filters
             .adm_advertisers
@@ -424,45 +859,77 @@ impl AdmFilter {
                 // match to the version that we switched over from built in image management
                 // to CDN image fetch.
 
-                if device_info.legacy_only()
-                    && !self.legacy_list.contains(&tile.name.to_lowercase())
-                {
+                if device_info.legacy_only() && !self.legacy_list.matches(&tile.name) {
                     trace!("Rejecting tile: Not a legacy advertiser {:?}", &tile.name);
                     metrics.incr_with_tags("filter.adm.err.non_legacy", Some(tags));
                     return Ok(None);
                 }
 
                 let adv_filter = filter.get(&location.country()).unwrap();
+                let actions = &self.defaults.rejection_actions;
+                if let Err(e) = self.check_region(adv_filter, location, &tile, tags) {
+                    trace!("Rejecting tile: region not included");
+                    self.reject(
+                        "filter.adm.err.invalid_region",
+                        actions.invalid_region,
+                        e,
+                        tags,
+                        metrics,
+                    );
+                    return Ok(None);
+                }
                 if let Err(e) = self.check_advertiser(adv_filter, &mut tile, tags) {
                     trace!("Rejecting tile: bad adv");
-                    metrics.incr_with_tags("filter.adm.err.invalid_advertiser", Some(tags));
-                    self.report(&e, tags);
+                    self.reject(
+                        "filter.adm.err.invalid_advertiser",
+                        actions.invalid_advertiser,
+                        e,
+                        tags,
+                        metrics,
+                    );
                     return Ok(None);
                 }
                 if let Err(e) = self.check_click(&self.defaults, &mut tile, tags) {
                     trace!("Rejecting tile: bad click");
-                    metrics.incr_with_tags("filter.adm.err.invalid_click", Some(tags));
-                    self.report(&e, tags);
+                    self.reject(
+                        "filter.adm.err.invalid_click",
+                        actions.invalid_click,
+                        e,
+                        tags,
+                        metrics,
+                    );
                     return Ok(None);
                 }
                 if let Err(e) = self.check_impression(&self.defaults, &mut tile, tags) {
                     trace!("Rejecting tile: bad imp");
-                    metrics.incr_with_tags("filter.adm.err.invalid_impression", Some(tags));
-                    self.report(&e, tags);
+                    self.reject(
+                        "filter.adm.err.invalid_impression",
+                        actions.invalid_impression,
+                        e,
+                        tags,
+                        metrics,
+                    );
                     return Ok(None);
                 }
                 if let Err(e) = self.check_image_hosts(&self.defaults, &mut tile, tags) {
                     trace!("Rejecting tile: bad image");
-                    metrics.incr_with_tags("filter.adm.err.invalid_image_host", Some(tags));
-                    self.report(&e, tags);
+                    self.reject(
+                        "filter.adm.err.invalid_image_host",
+                        actions.invalid_image_host,
+                        e,
+                        tags,
+                        metrics,
+                    );
                     return Ok(None);
                 }
                 if let Err(e) = tile.image_url.parse::<Uri>() {
                     trace!("Rejecting tile: bad image: {:?}", e);
-                    metrics.incr_with_tags("filter.adm.err.invalid_image", Some(tags));
-                    self.report(
-                        &HandlerErrorKind::InvalidHost("Image", tile.image_url).into(),
+                    self.reject(
+                        "filter.adm.err.invalid_image",
+                        actions.invalid_image,
+                        HandlerErrorKind::InvalidHost("Image", tile.image_url).into(),
                         tags,
+                        metrics,
                     );
                     return Ok(None);
                 }
@@ -470,11 +937,13 @@ impl AdmFilter {
                 Ok(Some(Tile::from_adm_tile(tile)))
             }
             None => {
-                if !self.ignore_list.contains(&tile.name.to_lowercase()) {
-                    metrics.incr_with_tags("filter.adm.err.unexpected_advertiser", Some(tags));
-                    self.report(
-                        &HandlerErrorKind::UnexpectedAdvertiser(tile.name).into(),
+                if !self.is_ignored(&tile.name) {
+                    self.reject(
+                        "filter.adm.err.unexpected_advertiser",
+                        self.defaults.rejection_actions.unexpected_advertiser,
+                        HandlerErrorKind::UnexpectedAdvertiser(tile.name).into(),
                         tags,
+                        metrics,
                     );
                 }
                 Ok(None)
@@ -486,15 +955,21 @@ impl AdmFilter {
 #[cfg(test)]
 mod tests {
     use super::{check_url, AdmFilter};
-    use crate::adm::settings::AdmAdvertiserSettings;
+    use crate::adm::settings::{
+        AdmAdvertiserSettings, HostFilter, HostMatchMode, PathFilter, PathMatching,
+    };
     use crate::adm::{settings::AdvertiserUrlFilter, tiles::AdmTile};
     use crate::adm::{spawn_updater, AdmDefaults};
+    use crate::settings::Settings;
     use crate::tags::Tags;
-    use crate::web::test::{find_metrics, MockTokenSourceProvider};
+    use crate::web::test::find_metrics;
     use actix_web::rt;
+    use actix_web_location::Location;
     use cadence::{SpyMetricSink, StatsdClient};
+    use regex::Regex;
     use std::sync::Arc;
     use std::time::Duration;
+    use time::OffsetDateTime;
     use tokio::sync::RwLock;
     use url::Url;
 
@@ -504,43 +979,65 @@ mod tests {
         assert!(check_url(
             "https://example.com".parse().unwrap(),
             species,
-            &[vec!["example".to_owned(), "com".to_owned()]]
+            &[HostFilter::new("example.com")],
+            &[],
+            HostMatchMode::Strict
         )
         .unwrap());
 
+        // Subdomain matches require opting into `Suffix`.
         assert!(check_url(
             "https://foo.bridge.example.com/?quux=baz".parse().unwrap(),
             species,
-            &[vec!["example".to_owned(), "com".to_owned()]]
+            &[HostFilter::new("example.com")],
+            &[],
+            HostMatchMode::Suffix
         )
         .unwrap());
     }
 
+    #[test]
+    fn check_url_strict_rejects_subdomains() {
+        // The default `Strict` mode never does the backwards-expanding
+        // search `Suffix` does: only an exact host match is accepted.
+        let species = "Click";
+        assert!(check_url(
+            "https://foo.bridge.example.com/?quux=baz".parse().unwrap(),
+            species,
+            &[HostFilter::new("example.com")],
+            &[],
+            HostMatchMode::Strict
+        )
+        .is_err());
+    }
+
     #[test]
     fn check_url_failed() {
         let species = "Click";
         assert!(check_url(
             "https://foo.com".parse().unwrap(),
             species,
-            &[vec!["example".to_owned(), "com".to_owned()]]
+            &[HostFilter::new("example.com")],
+            &[],
+            HostMatchMode::Suffix
         )
         .is_err());
 
         assert!(check_url(
             "https://foo.com".parse().unwrap(),
             species,
-            &[vec![
-                "bar".to_owned(),
-                "example".to_owned(),
-                "com".to_owned()
-            ]]
+            &[HostFilter::new("bar.example.com")],
+            &[],
+            HostMatchMode::Suffix
         )
         .is_err());
 
         assert!(check_url(
             "https://badexample.com".parse().unwrap(),
             species,
-            &[vec!["example".to_owned(), "com".to_owned()]]
+            &[HostFilter::new("example.com")],
+            &[],
+            HostMatchMode::Suffix
         )
         .is_err());
     }
@@ -552,14 +1049,41 @@ mod tests {
             "https://foo.co.mx".parse().unwrap(),
             "Click",
             &[
-                vec!["bar".to_owned(), "co".to_owned(), "mx".to_owned()],
-                vec!["bar".to_owned(), "com".to_owned()],
-                vec!["foo".to_owned(), "co".to_owned(), "uk".to_owned()],
-            ]
+                HostFilter::new("bar.co.mx"),
+                HostFilter::new("bar.com"),
+                HostFilter::new("foo.co.uk"),
+            ],
+            &[],
+            HostMatchMode::Suffix
         )
         .is_err());
     }
 
+    #[test]
+    fn check_url_rejects_bare_public_suffix_as_wildcard() {
+        let species = "Click";
+        // A configured filter entry of just "co.uk" must never act as a
+        // wildcard root for every co.uk registrant.
+        assert!(check_url(
+            "https://evil.co.uk".parse().unwrap(),
+            species,
+            &[HostFilter::new("co.uk")],
+            &[],
+            HostMatchMode::Suffix
+        )
+        .is_err());
+
+        // But an exact match against the bare suffix is still fine.
+        assert!(check_url(
+            "https://co.uk".parse().unwrap(),
+            species,
+            &[HostFilter::new("co.uk")],
+            &[],
+            HostMatchMode::Suffix
+        )
+        .unwrap());
+    }
+
     #[test]
     fn check_advertiser() {
         let s = r#"{"adm_advertisers":{
@@ -593,13 +1117,9 @@ mod tests {
         let filter = AdmFilter {
             advertiser_filters: advertiser_filters.clone(),
             defaults: AdmDefaults {
-                click_hosts: [crate::adm::settings::break_hosts("example.com".to_owned())].to_vec(),
-                image_hosts: [crate::adm::settings::break_hosts(
-                    "cdn.example.org".to_owned(),
-                )]
-                .to_vec(),
-                impression_hosts: [crate::adm::settings::break_hosts("example.net".to_owned())]
-                    .to_vec(),
+                click_hosts: vec![HostFilter::new("example.com")],
+                image_hosts: vec![HostFilter::new("cdn.example.org")],
+                impression_hosts: vec![HostFilter::new("example.net")],
                 ..Default::default()
             },
             ..Default::default()
@@ -733,15 +1253,9 @@ mod tests {
             .check_advertiser(&settings, &mut tile, &mut tags)
             .is_ok());
 
-        // replicate settings breaking hosts into component bits.
-        let host_bits: Vec<String> = "example.org"
-            .to_owned()
-            .split('.')
-            .map(String::from)
-            .collect();
-
         let defaults = AdmDefaults {
-            image_hosts: vec![host_bits],
+            image_hosts: vec![HostFilter::new("example.org")],
+            host_match: HostMatchMode::Suffix,
             ..Default::default()
         };
         tile.image_url = "https://example.biz".to_owned();
@@ -758,6 +1272,564 @@ mod tests {
             .check_image_hosts(&defaults, &mut tile, &mut tags)
             .is_ok());
     }
+
+    #[test]
+    fn check_advertiser_path_canonicalization() {
+        let s = r#"{"adm_advertisers":{"Acme": {"US": [
+            { "host": "acme.biz", "paths": [{ "value": "/ca/", "matching": "exact" }] }
+        ]}}}"#;
+        let advertiser_filters: AdmAdvertiserSettings = serde_json::from_str(s).unwrap();
+        let filter = AdmFilter {
+            advertiser_filters: advertiser_filters.clone(),
+            ..Default::default()
+        };
+        let settings = &advertiser_filters.adm_advertisers["Acme"]["US"];
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: String::new(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+
+        // Good, percent-encoded unreserved bytes decode to the configured path.
+        tile.advertiser_url = "https://acme.biz/%63a/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_ok());
+        // Good, a `.` segment resolves away.
+        tile.advertiser_url = "https://acme.biz/./ca/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_ok());
+        // Good, duplicate slashes collapse.
+        tile.advertiser_url = "https://acme.biz//ca/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_ok());
+        // Good, a `..` segment resolves away.
+        tile.advertiser_url = "https://acme.biz/foo/../ca/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_ok());
+        // Bad, a reserved byte's percent-encoding isn't decoded (would
+        // otherwise turn one path segment into two).
+        tile.advertiser_url = "https://acme.biz/ca%2f".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn check_advertiser_regex_path() {
+        let s = r#"{"adm_advertisers":{"Acme": {"US": [
+            { "host": "acme.biz", "paths": [{ "value": "^/[a-z]{2}/campaign-[0-9]+/$", "matching": "regex" }] }
+        ]}}}"#;
+        let advertiser_filters: AdmAdvertiserSettings = serde_json::from_str(s).unwrap();
+        let filter = AdmFilter {
+            advertiser_filters: advertiser_filters.clone(),
+            ..Default::default()
+        };
+        let settings = &advertiser_filters.adm_advertisers["Acme"]["US"];
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: String::new(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+
+        tile.advertiser_url = "https://acme.biz/ca/campaign-42/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_ok());
+        // The pattern is anchored, so trailing garbage doesn't match.
+        tile.advertiser_url = "https://acme.biz/ca/campaign-42/extra".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_err());
+        // Nor does a non-numeric campaign id.
+        tile.advertiser_url = "https://acme.biz/ca/campaign-abc/".to_owned();
+        assert!(filter
+            .check_advertiser(settings, &mut tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn allows_redirect_host_checks_click_impression_and_image_hosts() {
+        let filter = AdmFilter {
+            defaults: AdmDefaults {
+                click_hosts: vec![HostFilter::new("example.com")],
+                impression_hosts: vec![HostFilter::new("example.net")],
+                image_hosts: vec![HostFilter::new("example.org")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // A host from any one of the three lists is an acceptable redirect
+        // target -- the species following the redirect isn't known here.
+        assert!(filter.allows_redirect_host("example.com"));
+        assert!(filter.allows_redirect_host("example.net"));
+        assert!(filter.allows_redirect_host("example.org"));
+        assert!(!filter.allows_redirect_host("evil.example"));
+    }
+
+    #[test]
+    fn check_advertiser_allow_subdomains() {
+        let filter = AdmFilter::default();
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://sub.acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+
+        // Strict (default): a subdomain isn't allowed even though the base
+        // host matches.
+        let strict: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "acme.biz", "paths": [{"value": "/", "matching": "exact"}]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_advertiser(&strict, &mut tile, &mut tags)
+            .is_err());
+
+        // With `allow_subdomains`, the subdomain is allowed...
+        let permissive: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "acme.biz", "allow_subdomains": true, "paths": [{"value": "/", "matching": "exact"}]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_advertiser(&permissive, &mut tile, &mut tags)
+            .is_ok());
+        // ... but the bare host itself still matches too.
+        tile.advertiser_url = "https://acme.biz/".to_owned();
+        assert!(filter
+            .check_advertiser(&permissive, &mut tile, &mut tags)
+            .is_ok());
+
+        // A bare public suffix can never be used as a wildcard root, even
+        // with `allow_subdomains` set.
+        tile.advertiser_url = "https://evil.co.uk/".to_owned();
+        let unsafe_filter: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "co.uk", "allow_subdomains": true, "paths": [{"value": "/", "matching": "exact"}]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_advertiser(&unsafe_filter, &mut tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn check_image_hosts_per_entry_include_subdomains() {
+        let filter = AdmFilter::default();
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://cdn.example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+
+        // `host_match` is the (default) `Strict`, but this one entry opts
+        // into subdomain acceptance on its own.
+        let defaults: AdmDefaults = serde_json::from_str(
+            r#"{"image_hosts": [{"host": "example.org", "include_subdomains": true}]}"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_image_hosts(&defaults, &mut tile, &mut tags)
+            .is_ok());
+        // ... the bare host still matches too.
+        tile.image_url = "https://example.org/i/cat.jpg".to_owned();
+        assert!(filter
+            .check_image_hosts(&defaults, &mut tile, &mut tags)
+            .is_ok());
+
+        // A sibling bare-string entry (no override) falls back to the
+        // blanket `host_match`, so it still rejects a subdomain.
+        tile.image_url = "https://cdn.example.net/i/cat.jpg".to_owned();
+        let strict: AdmDefaults =
+            serde_json::from_str(r#"{"image_hosts": ["example.net"]}"#).unwrap();
+        assert!(filter
+            .check_image_hosts(&strict, &mut tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn check_blocklist_rejects_denylisted_host() {
+        let advertiser_filters = AdmAdvertiserSettings {
+            host_denylist: vec![HostFilter::new("evil.example")],
+            ..Default::default()
+        };
+        let filter = AdmFilter {
+            advertiser_filters,
+            ..Default::default()
+        };
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+        // None of the tile's hosts are denylisted.
+        assert!(filter.check_blocklist(&tile, &mut tags).is_ok());
+        // A denylisted click host is rejected regardless of which
+        // advertiser the tile claims to be.
+        tile.click_url = "https://evil.example/foo".to_owned();
+        assert!(filter.check_blocklist(&tile, &mut tags).is_err());
+    }
+
+    #[test]
+    fn check_blocklist_allowlist_rejects_unlisted_host() {
+        let advertiser_filters = AdmAdvertiserSettings {
+            host_allowlist: vec![HostFilter::new("acme.biz")],
+            ..Default::default()
+        };
+        let filter = AdmFilter {
+            advertiser_filters,
+            ..Default::default()
+        };
+        let mut tags = Tags::default();
+        let tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+        // The advertiser_url host matches the allowlist, so it's fine even
+        // though click/image hosts don't.
+        assert!(filter.check_blocklist(&tile, &mut tags).is_ok());
+
+        // Nothing matches the allowlist -- rejected.
+        let filter = AdmFilter {
+            advertiser_filters: AdmAdvertiserSettings {
+                host_allowlist: vec![HostFilter::new("only-this.example")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(filter.check_blocklist(&tile, &mut tags).is_err());
+    }
+
+    #[test]
+    fn check_ip_hosts_rejects_ipv4_and_ipv6_literals() {
+        let filter = AdmFilter::default();
+        let defaults = AdmDefaults::default();
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+        // An ordinary domain host on both URLs passes.
+        assert!(filter
+            .check_ip_hosts(&defaults, &tile, &mut tags)
+            .is_ok());
+
+        // A bare IPv4 literal advertiser_url is rejected.
+        tile.advertiser_url = "https://203.0.113.5/ca/".to_owned();
+        assert!(filter
+            .check_ip_hosts(&defaults, &tile, &mut tags)
+            .is_err());
+
+        // A bracketed IPv6 literal image_url is rejected too.
+        tile.advertiser_url = "https://acme.biz/".to_owned();
+        tile.image_url = "https://[2001:db8::1]/i/cat.jpg".to_owned();
+        assert!(filter
+            .check_ip_hosts(&defaults, &tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn check_ip_hosts_allow_ip_hosts_opts_out() {
+        let filter = AdmFilter::default();
+        let defaults: AdmDefaults =
+            serde_json::from_str(r#"{"allow_ip_hosts": true}"#).unwrap();
+        let mut tags = Tags::default();
+        let tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://203.0.113.5/ca/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://[2001:db8::1]/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+        assert!(filter
+            .check_ip_hosts(&defaults, &tile, &mut tags)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_advertiser_wildcard_host() {
+        let filter = AdmFilter::default();
+        let mut tags = Tags::default();
+        let mut tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+        let wildcard: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "*.acme.biz", "paths": [{"value": "/", "matching": "exact"}]}]"#,
+        )
+        .unwrap();
+
+        // Unlike `allow_subdomains`, `*.acme.biz` doesn't also match the
+        // apex host.
+        assert!(filter
+            .check_advertiser(&wildcard, &mut tile, &mut tags)
+            .is_err());
+
+        // A single-label subdomain matches...
+        tile.advertiser_url = "https://sub.acme.biz/".to_owned();
+        assert!(filter
+            .check_advertiser(&wildcard, &mut tile, &mut tags)
+            .is_ok());
+
+        // ...as does a multi-label one.
+        tile.advertiser_url = "https://a.b.acme.biz/".to_owned();
+        assert!(filter
+            .check_advertiser(&wildcard, &mut tile, &mut tags)
+            .is_ok());
+
+        // A host that merely ends in the same letters isn't a subdomain
+        // match (never a substring check).
+        tile.advertiser_url = "https://evilacme.biz/".to_owned();
+        assert!(filter
+            .check_advertiser(&wildcard, &mut tile, &mut tags)
+            .is_err());
+
+        // A bare public suffix can never be used as a wildcard root.
+        tile.advertiser_url = "https://evil.co.uk/".to_owned();
+        let unsafe_filter: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "*.co.uk", "paths": [{"value": "/", "matching": "exact"}]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_advertiser(&unsafe_filter, &mut tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn check_region() {
+        let filter = AdmFilter::default();
+        let mut tags = Tags::default();
+        let tile = AdmTile {
+            id: 0,
+            name: "test".to_owned(),
+            advertiser_url: "https://acme.biz/".to_owned(),
+            click_url: "https://example.com/foo".to_owned(),
+            image_url: "https://example.org/i/cat.jpg".to_owned(),
+            impression_url: "https://example.net".to_owned(),
+            position: None,
+        };
+
+        // No `include_regions` configured anywhere: unrestricted.
+        let unrestricted: Vec<AdvertiserUrlFilter> =
+            serde_json::from_str(r#"[{"host": "acme.biz"}]"#).unwrap();
+        let us_tx = Location::build()
+            .country("US".to_owned())
+            .region("TX".to_owned())
+            .finish()
+            .unwrap();
+        assert!(filter
+            .check_region(&unrestricted, &us_tx, &tile, &mut tags)
+            .is_ok());
+
+        // Country-level entry matches any region in that country.
+        let us_only: Vec<AdvertiserUrlFilter> =
+            serde_json::from_str(r#"[{"host": "acme.biz", "include_regions": ["US"]}]"#).unwrap();
+        assert!(filter
+            .check_region(&us_only, &us_tx, &tile, &mut tags)
+            .is_ok());
+        let mx = Location::build().country("MX".to_owned()).finish().unwrap();
+        assert!(filter.check_region(&us_only, &mx, &tile, &mut tags).is_err());
+
+        // Region-level entry only matches that specific region.
+        let us_tx_only: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "acme.biz", "include_regions": ["US/TX"]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_region(&us_tx_only, &us_tx, &tile, &mut tags)
+            .is_ok());
+        let us_ca = Location::build()
+            .country("US".to_owned())
+            .region("CA".to_owned())
+            .finish()
+            .unwrap();
+        assert!(filter
+            .check_region(&us_tx_only, &us_ca, &tile, &mut tags)
+            .is_err());
+
+        // Advertiser entry declares none: falls back to the defaults'
+        // `include_regions`.
+        let filter_with_defaults = AdmFilter {
+            defaults: AdmDefaults {
+                include_regions: vec!["US".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(filter_with_defaults
+            .check_region(&unrestricted, &us_tx, &tile, &mut tags)
+            .is_ok());
+        assert!(filter_with_defaults
+            .check_region(&unrestricted, &mx, &tile, &mut tags)
+            .is_err());
+
+        // Multiple entries for the same country: their `include_regions`
+        // are combined, so a region listed on *any* entry is allowed.
+        let mixed: Vec<AdvertiserUrlFilter> = serde_json::from_str(
+            r#"[{"host": "acme.biz", "include_regions": ["US/TX"]},
+                {"host": "acme.biz", "include_regions": ["US/CA"]}]"#,
+        )
+        .unwrap();
+        assert!(filter
+            .check_region(&mixed, &us_tx, &tile, &mut tags)
+            .is_ok());
+        assert!(filter
+            .check_region(&mixed, &us_ca, &tile, &mut tags)
+            .is_ok());
+        let us_ny = Location::build()
+            .country("US".to_owned())
+            .region("NY".to_owned())
+            .finish()
+            .unwrap();
+        assert!(filter
+            .check_region(&mixed, &us_ny, &tile, &mut tags)
+            .is_err());
+    }
+
+    #[test]
+    fn advertiser_aliases_share_filters() {
+        let s = r#"{
+            "adm_advertisers": {
+                "acme": {
+                    "US": [{"host": "acme.biz"}]
+                }
+            },
+            "aliases": {
+                "acme": ["Acme Inc", "acme-co"]
+            }
+        }"#;
+        let mut advertiser_filters: AdmAdvertiserSettings = serde_json::from_str(s).unwrap();
+        advertiser_filters.expand_aliases();
+
+        let canonical_host = &advertiser_filters
+            .adm_advertisers
+            .get("acme")
+            .unwrap()
+            .get("US")
+            .unwrap()[0]
+            .host;
+        for alias in ["acme inc", "acme-co"] {
+            let aliased_host = &advertiser_filters
+                .adm_advertisers
+                .get(alias)
+                .unwrap()
+                .get("US")
+                .unwrap()[0]
+                .host;
+            assert_eq!(canonical_host, aliased_host);
+        }
+    }
+
+    #[test]
+    fn check_url_matches_host_pattern() {
+        let species = "Click";
+        let patterns = [Regex::new(r"^[a-z]+\.ads\.example\.com$").unwrap()];
+
+        // No literal host entries match, but the pattern does -- patterns
+        // are consulted regardless of `host_match`.
+        assert!(check_url(
+            "https://foo.ads.example.com".parse().unwrap(),
+            species,
+            &[],
+            &patterns,
+            HostMatchMode::Strict
+        )
+        .unwrap());
+
+        // Doesn't match the pattern (extra subdomain level) and there's no
+        // literal entry either.
+        assert!(check_url(
+            "https://foo.bar.ads.example.com".parse().unwrap(),
+            species,
+            &[],
+            &patterns,
+            HostMatchMode::Strict
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn update_rejects_invalid_settings_and_keeps_previous() {
+        let good = r#"{"adm_advertisers":{"Acme": {"US": [{"host": "acme.biz"}]}}}"#;
+        let good: AdmAdvertiserSettings = serde_json::from_str(good).unwrap();
+        let mut filter = AdmFilter {
+            advertiser_filters: good.clone(),
+            last_updated: Some(OffsetDateTime::UNIX_EPOCH),
+            last_fingerprint: Some("1".to_owned()),
+            ..Default::default()
+        };
+
+        // A path filter that can never match a real URL path. Built directly,
+        // since deserializing would canonicalize the value to start with `/`.
+        let mut bad = good.clone();
+        bad.adm_advertisers.get_mut("Acme").unwrap().get_mut("US").unwrap()[0].paths =
+            Some(vec![PathFilter {
+                value: "no-leading-slash".to_owned(),
+                matching: PathMatching::Glob,
+                glob: None,
+                regex: None,
+            }]);
+        assert!(filter
+            .update(bad, OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1), "2".to_owned())
+            .is_err());
+
+        // The previous good settings and provenance are untouched.
+        assert_eq!(
+            filter
+                .advertiser_filters
+                .adm_advertisers
+                .get("Acme")
+                .unwrap()["US"][0]
+                .host,
+            "acme.biz"
+        );
+        assert_eq!(filter.last_fingerprint, Some("1".to_owned()));
+    }
+
     #[actix_web::test]
     async fn check_advertiser_metrics() {
         let s = r#"{"adm_advertisers":{
@@ -791,18 +1863,13 @@ mod tests {
             true,
             refresh_rate,
             &adm_filter,
-            Arc::new(google_cloud_storage::client::Client::new(
-                google_cloud_storage::client::ClientConfig {
-                    token_source_provider: Box::new(MockTokenSourceProvider),
-                    ..Default::default()
-                },
-            )),
+            Arc::new(Settings::default()),
             Arc::new(StatsdClient::builder("contile", sink).build()),
         )
         .unwrap();
         rt::time::sleep(Duration::from_secs(1)).await;
 
-        let prefixes = &["contile.filter.adm.update.check.skip"];
+        let prefixes = &["contile.filter.adm.update.not_modified"];
         let metrics = find_metrics(&rx, prefixes);
         assert_eq!(metrics.len(), 1);
     }
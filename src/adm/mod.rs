@@ -6,10 +6,14 @@
 //! We only allow a known set of partners, and validate that the tile info
 //! offered matches expected values.
 
+pub mod admin_api;
 mod filter;
+mod psl;
 mod settings;
 mod tiles;
 
-pub use filter::AdmFilter;
+pub use filter::{spawn_updater, AdmFilter};
 pub(crate) use settings::{AdmAdvertiserFilterSettings, AdmSettings, DEFAULT};
-pub use tiles::{get_tiles, TileResponse};
+pub use tiles::{
+    get_tiles, AdmRequester, AdmRevalidation, GetTilesOutcome, ReqwestAdmRequester, TileResponse,
+};
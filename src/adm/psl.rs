@@ -0,0 +1,218 @@
+//! A small, embedded subset of the Public Suffix List
+//! (<https://publicsuffix.org/list/>), used to make subdomain-matching
+//! advertiser filters (see [super::settings::AdvertiserUrlFilter]) safe
+//! against overly broad matches: a filter entry of "co.uk" must never be
+//! allowed to act as a wildcard root for every co.uk registrant, and a tile
+//! host like "evil.example.com.attacker.net" must never be mistaken for a
+//! subdomain of "example.com".
+//!
+//! Rules are loaded into a trie keyed by reversed label (TLD-first), same as
+//! the real PSL algorithm, and support the PSL's `*.` wildcard and `!`
+//! exception rule syntax. This isn't exhaustive -- just enough to recognize
+//! the suffixes actual tile advertiser hosts fall under, plus a couple of
+//! wildcard/exception examples to exercise that machinery. Add entries here
+//! as they come up, erring on the side of including a suffix (treating it as
+//! non-registrable) rather than omitting one.
+//!
+//! Unlike the real PSL algorithm's implicit `*` rule, a host under a TLD we
+//! don't recognize at all resolves to `None` (no registrable domain) rather
+//! than treating its last label as a standalone public suffix -- we'd rather
+//! an operator explicitly add the suffix than silently treat an unknown TLD
+//! as safe to wildcard-match.
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+const PUBLIC_SUFFIX_RULES: &[&str] = &[
+    // gTLDs
+    "com", "net", "org", "biz", "info", "name", "pro", "mobi", "app", "dev", "io", "co", "me",
+    "tv", "cc", "xyz", "online", "site", "shop", "store", "tech", "club",
+    // ccTLDs
+    "us", "uk", "ca", "de", "fr", "es", "it", "nl", "se", "no", "fi", "dk", "pl", "ru", "ch",
+    "at", "be", "pt", "gr", "ie", "nz", "au", "jp", "kr", "cn", "in", "br", "mx", "za", "sg",
+    "hk", "tw", "il", "ae", "sa", "tr", "eu", "ck", "bd",
+    // common ccTLD second-level suffixes
+    "co.uk", "org.uk", "me.uk", "ac.uk", "gov.uk", "net.uk", "sch.uk", "co.jp", "or.jp",
+    "ne.jp", "ac.jp", "co.nz", "org.nz", "net.nz", "govt.nz", "com.au", "net.au", "org.au",
+    "gov.au", "edu.au", "co.za", "org.za", "net.za", "co.in", "net.in", "org.in", "gov.in",
+    "co.kr", "or.kr", "ne.kr", "com.br", "net.br", "org.br", "com.mx", "org.mx", "net.mx",
+    "com.cn", "net.cn", "org.cn", "com.tw", "org.tw", "net.tw", "com.sg", "com.hk", "org.hk",
+    "co.il", "org.il", "net.il",
+    // wildcard + exception examples (mirroring the real PSL's ".ck"/".bd" entries):
+    // every direct label under "ck"/"bd" is itself a public suffix, EXCEPT
+    // the excepted names below, which are registrable domains in their own
+    // right.
+    "*.ck", "!www.ck", "*.bd", "!www.bd",
+];
+
+#[derive(Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    wildcard: Option<Box<Node>>,
+    /// A plain (non-`!`) rule terminates exactly here.
+    is_suffix: bool,
+    /// A `!` exception rule terminates exactly here.
+    is_exception: bool,
+}
+
+/// A Public Suffix List loaded into a trie of reversed (TLD-first) labels,
+/// supporting `*.` wildcard and `!` exception rules per the PSL algorithm.
+struct PublicSuffixList {
+    root: Node,
+}
+
+impl PublicSuffixList {
+    fn new(rules: &[&str]) -> Self {
+        let mut root = Node::default();
+        for &rule in rules {
+            let (is_exception, pattern) = match rule.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, rule),
+            };
+            let labels: Vec<&str> = pattern.split('.').rev().collect();
+            let mut node = &mut root;
+            for (i, label) in labels.iter().enumerate() {
+                node = if *label == "*" {
+                    node.wildcard.get_or_insert_with(Box::default)
+                } else {
+                    node.children.entry((*label).to_owned()).or_default()
+                };
+                if i == labels.len() - 1 {
+                    if is_exception {
+                        node.is_exception = true;
+                    } else {
+                        node.is_suffix = true;
+                    }
+                }
+            }
+        }
+        Self { root }
+    }
+
+    /// Walk `labels_tld_first` (a host's labels, reversed so the TLD comes
+    /// first) down the trie, preferring a literal label match over a
+    /// wildcard one at each step, tracking the longest (deepest) matching
+    /// rule seen along the way. Returns `(labels_matched, was_exception)`,
+    /// or `(0, false)` if no rule matched at all.
+    fn longest_match(&self, labels_tld_first: &[&str]) -> (usize, bool) {
+        let mut node = &self.root;
+        let mut best = (0, false);
+        for (i, label) in labels_tld_first.iter().enumerate() {
+            let next = node.children.get(*label).or(node.wildcard.as_deref());
+            match next {
+                Some(next_node) => {
+                    node = next_node;
+                    if node.is_suffix {
+                        best = (i + 1, false);
+                    }
+                    if node.is_exception {
+                        best = (i + 1, true);
+                    }
+                }
+                None => break,
+            }
+        }
+        best
+    }
+
+    /// The "registrable domain" for `host`: its public suffix plus one
+    /// additional label. `None` if `host` doesn't resolve under any known
+    /// rule, IS itself a public suffix, or otherwise has no label beyond
+    /// one.
+    fn registrable_domain(&self, host: &str) -> Option<String> {
+        let host = host.to_lowercase();
+        let labels: Vec<&str> = host.split('.').collect();
+        let labels_tld_first: Vec<&str> = labels.iter().rev().copied().collect();
+        let (matched, was_exception) = self.longest_match(&labels_tld_first);
+        if matched == 0 {
+            return None;
+        }
+        // An exception rule "!a.b" means "a.b" is registrable in its own
+        // right, even though the wildcard that matched it would otherwise
+        // make "a.b" itself a public suffix -- so its *public* suffix is one
+        // label shorter than the match.
+        let suffix_label_count = if was_exception { matched - 1 } else { matched };
+        if labels.len() <= suffix_label_count {
+            return None;
+        }
+        Some(labels[labels.len() - suffix_label_count - 1..].join("."))
+    }
+}
+
+lazy_static! {
+    static ref PSL: PublicSuffixList = PublicSuffixList::new(PUBLIC_SUFFIX_RULES);
+}
+
+/// The "registrable domain" for `host` per the embedded [PUBLIC_SUFFIX_RULES].
+pub fn registrable_domain(host: &str) -> Option<String> {
+    PSL.registrable_domain(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::registrable_domain;
+
+    #[test]
+    fn simple_suffix() {
+        assert_eq!(
+            registrable_domain("example.com"),
+            Some("example.com".to_owned())
+        );
+        assert_eq!(
+            registrable_domain("foo.example.com"),
+            Some("example.com".to_owned())
+        );
+    }
+
+    #[test]
+    fn compound_suffix() {
+        assert_eq!(
+            registrable_domain("example.co.uk"),
+            Some("example.co.uk".to_owned())
+        );
+        assert_eq!(
+            registrable_domain("foo.bar.example.co.uk"),
+            Some("example.co.uk".to_owned())
+        );
+    }
+
+    #[test]
+    fn bare_suffix_is_not_registrable() {
+        assert_eq!(registrable_domain("com"), None);
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("uk"), None);
+    }
+
+    #[test]
+    fn unknown_tld_has_no_suffix() {
+        assert_eq!(registrable_domain("example.notareal"), None);
+    }
+
+    #[test]
+    fn wildcard_rule_treats_any_label_as_a_suffix() {
+        assert_eq!(registrable_domain("ck"), None);
+        assert_eq!(registrable_domain("example.ck"), None);
+        assert_eq!(
+            registrable_domain("foo.example.ck"),
+            Some("foo.example.ck".to_owned())
+        );
+    }
+
+    #[test]
+    fn exception_rule_overrides_the_wildcard() {
+        // "www.ck" is carved out of "*.ck": it's registrable in its own
+        // right, rather than itself being a public suffix.
+        assert_eq!(registrable_domain("www.ck"), Some("www.ck".to_owned()));
+        assert_eq!(registrable_domain("foo.www.ck"), Some("www.ck".to_owned()));
+    }
+
+    #[test]
+    fn evil_subdomain_bypass_does_not_resolve_to_the_target_domain() {
+        // A naive suffix check might see "example.com" as a suffix of this
+        // host; the registrable domain is actually "attacker.net".
+        assert_eq!(
+            registrable_domain("evil.example.com.attacker.net"),
+            Some("attacker.net".to_owned())
+        );
+    }
+}
@@ -6,8 +6,11 @@ use std::{
     path::Path,
 };
 
+use async_trait::async_trait;
 use config::ConfigError;
+use regex::Regex;
 use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+use time::OffsetDateTime;
 
 use super::AdmFilter;
 use crate::{
@@ -39,11 +42,20 @@ use crate::{
 ///   value in this filter.  If not, this URL is rejected by this filter.
 ///   For example `https://foo.com` would match, however `https://www.foo.com`
 ///   would *not* match and would be rejected. If you wish to include both
-///   hosts, you will need to duplicate the `"paths"`.
+///   hosts, you will need to duplicate the `"paths"`. Set `"allow_subdomains"`
+///   to match any subdomain of `"host"` as well (e.g. `"host": "foo.com"`
+///   with `"allow_subdomains": true` matches `"www.foo.com"`); this is gated
+///   behind a flag (rather than always allowed, as with paths) because
+///   `"host"` must be at least a registrable domain per the Public Suffix
+///   List for it to take effect -- a bare public suffix like `"co.uk"` can
+///   never be used as a wildcard root. Alternatively, write `"host"` with a
+///   leading `"*."` (e.g. `"*.foo.com"`) to match any subdomain of `foo.com`
+///   *without* matching `foo.com` itself -- unlike `"allow_subdomains"`,
+///   this doesn't require duplicating the rule to also cover the apex host.
 /// * If the host matches, and there is no `"paths"` specified in this filter,
 ///   then the URL is accepted by this filter.
 /// * If the `"paths"` filter list is present, then proceed with path filtering.
-///   There are two matching strategies:
+///   There are three matching strategies:
 ///   * `"exact"` for exact path matching, which compares the `"path"`
 ///     character-by-character with the `"value"` filed of this path filter.
 ///   * "prefix" for prefix path matching, which checks if the `value` is a
@@ -52,11 +64,248 @@ use crate::{
 ///     matches. In particular, when loading filters from the settings file,
 ///     Contile will panic if it detects that a prefix filter doesn't have
 ///     the trailing '/' in the `"value"`.
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+///   * `"glob"` for wildcard path matching (`*`/`**`/`?`), see
+///     [PathMatching::Glob].
+///   * `"regex"` for matching `"value"` as an anchored regular expression
+///     against the path, see [PathMatching::Regex].
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct AdvertiserUrlFilter {
     pub(crate) host: String,
     #[serde(skip_serializing_if = "check_paths")]
     pub(crate) paths: Option<Vec<PathFilter>>,
+    /// Match subdomains of `host` too, not just `host` itself. Safe against
+    /// over-broad matches: only takes effect if `host` is at least a
+    /// registrable domain (see [crate::adm::psl]).
+    #[serde(default)]
+    pub(crate) allow_subdomains: bool,
+    /// Restrict this entry to specific request locations, e.g.
+    /// `["US", "US/TX"]` for the whole US plus specifically Texas. Empty
+    /// (the default) means unrestricted -- see
+    /// [crate::adm::AdmFilter::check_region].
+    #[serde(default)]
+    pub(crate) include_regions: Vec<String>,
+    /// `host`, pre-parsed into exact/wildcard-suffix form -- see
+    /// [HostMatching]. Cached here so matching a request's host never has
+    /// to re-inspect `host` for a `*.` prefix.
+    #[serde(skip)]
+    pub(crate) host_matching: HostMatching,
+}
+
+/// Parsed form of `AdvertiserUrlFilter::host`. A plain host (`foo.com`)
+/// matches exactly (or, with `allow_subdomains`, as a suffix of it too); a
+/// leading-wildcard host (`*.foo.com`) matches any subdomain of `foo.com`,
+/// but never `foo.com` itself, regardless of `allow_subdomains`.
+#[derive(Clone, Debug)]
+pub(crate) enum HostMatching {
+    Exact(String),
+    Suffix(String),
+}
+
+impl Default for HostMatching {
+    fn default() -> Self {
+        HostMatching::Exact(String::new())
+    }
+}
+
+impl From<&str> for HostMatching {
+    fn from(host: &str) -> Self {
+        match host.strip_prefix("*.") {
+            Some(suffix) => HostMatching::Suffix(suffix.to_owned()),
+            None => HostMatching::Exact(host.to_owned()),
+        }
+    }
+}
+
+/// Matching mode for [AdmDefaults]' `click_hosts`/`impression_hosts`/
+/// `image_hosts`: whether a configured host also backwards-expands to
+/// match its subdomains (`example.com` accepting `xyz.example.com`), the
+/// same PSL-guarded search [AdvertiserUrlFilter] already offers per-entry
+/// via `allow_subdomains`/a `*.` prefix. Defaults to `Strict` so a newly
+/// configured host list is exact-match-only until an operator opts in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum HostMatchMode {
+    #[default]
+    Strict,
+    Suffix,
+}
+
+/// How a tile filtering rejection reason should be surfaced, see
+/// [FilterActions]. Modeled on Sentry's own `relay_filter` configuration,
+/// where each filter category carries its own action rather than every
+/// rejection being handled the same way.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RejectionAction {
+    /// Report to Sentry and count the metric -- the historical behavior,
+    /// and the default for any reason left unconfigured.
+    #[default]
+    Report,
+    /// Count the metric only; don't send a Sentry event. Use this for a
+    /// known-noisy advertiser that shouldn't page anyone.
+    Metric,
+    /// Neither report nor count a metric. Use sparingly: a silently
+    /// dropped reason leaves no trace it happened at all.
+    Silent,
+}
+
+/// Per-rejection-reason behavior for [AdmFilter]'s tile filtering, see
+/// [RejectionAction]. Lets operators quiet a noisy advertiser's rejections
+/// without recompiling; any reason left unset keeps reporting to Sentry.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct FilterActions {
+    #[serde(default)]
+    pub(crate) invalid_region: RejectionAction,
+    #[serde(default)]
+    pub(crate) invalid_advertiser: RejectionAction,
+    #[serde(default)]
+    pub(crate) invalid_click: RejectionAction,
+    #[serde(default)]
+    pub(crate) invalid_impression: RejectionAction,
+    #[serde(default)]
+    pub(crate) invalid_image_host: RejectionAction,
+    #[serde(default)]
+    pub(crate) invalid_image: RejectionAction,
+    #[serde(default)]
+    pub(crate) unexpected_advertiser: RejectionAction,
+    /// A tile rejected by the crate-wide [AdmAdvertiserSettings::host_denylist]/
+    /// [AdmAdvertiserSettings::host_allowlist] subsystem.
+    #[serde(default)]
+    pub(crate) blocklisted: RejectionAction,
+    /// A tile whose advertiser or image URL host is a bare IP literal, see
+    /// [AdmDefaults::allow_ip_hosts].
+    #[serde(default)]
+    pub(crate) ip_host: RejectionAction,
+}
+
+/// Outbound proxy configuration for ADM partner requests, parsed from
+/// `Settings::adm_proxy` (JSON). Modeled on the equivalent setting in
+/// Conduit: either no proxy, a single proxy for every host, or a
+/// per-host list that falls back to a direct connection for anything
+/// that doesn't match.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyConfig {
+    /// Connect directly, bypassing any proxy (the default).
+    #[default]
+    None,
+    /// Route every outbound request through this proxy.
+    Global { url: String },
+    /// Route requests to a matching `for_host` through its proxy, falling
+    /// back to a direct connection if nothing matches.
+    ByDomain(Vec<DomainProxy>),
+}
+
+/// One entry of [ProxyConfig::ByDomain]. `for_host` is parsed the same way
+/// as [AdvertiserUrlFilter::host]: a bare host (`foo.com`) matches itself
+/// only, a leading-wildcard host (`*.foo.com`) matches any subdomain only.
+#[derive(Clone, Debug, Serialize)]
+pub struct DomainProxy {
+    pub for_host: String,
+    pub url: String,
+    /// `for_host`, pre-parsed into exact/wildcard-suffix form -- see
+    /// [HostMatching].
+    #[serde(skip)]
+    pub(crate) host_matching: HostMatching,
+}
+
+impl AdvertiserUrlFilter {
+    /// Whether this filter relies on subdomain matching (`allow_subdomains`,
+    /// or a `Suffix` `host_matching` written `*.host`) over a `host` (minus
+    /// any `*.` prefix) that isn't at least a registrable domain per the
+    /// Public Suffix List -- e.g.
+    /// `"co.uk"` with `allow_subdomains: true` would otherwise act as a
+    /// wildcard root for every co.uk registrant. An exact-only filter (no
+    /// `*.` prefix, `allow_subdomains: false`) is never too broad, no matter
+    /// what `host` is, since it only ever matches that one literal host. See
+    /// [super::psl].
+    pub(crate) fn is_too_broad(&self) -> bool {
+        let relies_on_subdomain_match =
+            matches!(self.host_matching, HostMatching::Suffix(_)) || self.allow_subdomains;
+        let bare_host = self.host.strip_prefix("*.").unwrap_or(&self.host);
+        relies_on_subdomain_match && super::psl::registrable_domain(bare_host).is_none()
+    }
+
+    /// Structural sanity check for a config push -- see
+    /// [AdmAdvertiserSettings::validate]. `host` must be a syntactically
+    /// valid hostname, and every `paths` entry must be satisfiable by some
+    /// real (parsed) URL path, which always begins with `/`.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let bare_host = self.host.strip_prefix("*.").unwrap_or(&self.host);
+        url::Url::parse(&format!("https://{}/", bare_host))
+            .map_err(|e| format!("invalid host {:?}: {:?}", self.host, e))?;
+        for path in self.paths.as_deref().unwrap_or(&[]) {
+            // A `regex` pattern anchors itself (e.g. with `^`/`$`) rather
+            // than being a literal path, so it isn't required to start with
+            // `/` the way `prefix`/`exact`/`glob` values are.
+            if !matches!(path.matching, PathMatching::Regex) && !path.value.starts_with('/') {
+                return Err(format!(
+                    "path filter {:?} for host {:?} can never match a real URL path",
+                    path.value, self.host
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for DomainProxy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct InnerDomainProxy {
+            for_host: String,
+            url: String,
+        }
+
+        let inner = InnerDomainProxy::deserialize(deserializer)?;
+        let host_matching = HostMatching::from(inner.for_host.as_str());
+
+        Ok(DomainProxy {
+            for_host: inner.for_host,
+            url: inner.url,
+            host_matching,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for AdvertiserUrlFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct InnerAdvertiserUrlFilter {
+            host: String,
+            #[serde(default)]
+            paths: Option<Vec<PathFilter>>,
+            #[serde(default)]
+            allow_subdomains: bool,
+            #[serde(default)]
+            include_regions: Vec<String>,
+        }
+
+        let inner = InnerAdvertiserUrlFilter::deserialize(deserializer)?;
+        let (prefix, bare_host) = match inner.host.strip_prefix("*.") {
+            Some(suffix) => ("*.", suffix),
+            None => ("", inner.host.as_str()),
+        };
+        let normalized = normalize_host(bare_host).ok_or_else(|| {
+            de::Error::custom(format!("invalid advertiser host: {:?}", inner.host))
+        })?;
+        let host = format!("{prefix}{normalized}");
+        let host_matching = HostMatching::from(host.as_str());
+
+        Ok(AdvertiserUrlFilter {
+            host,
+            paths: inner.paths,
+            allow_subdomains: inner.allow_subdomains,
+            include_regions: inner.include_regions,
+            host_matching,
+        })
+    }
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Serialize)]
@@ -64,6 +313,17 @@ pub struct AdvertiserUrlFilter {
 pub enum PathMatching {
     Prefix,
     Exact,
+    /// `*` matches a run of characters within one path segment, `**` spans
+    /// segments (including `/`), and `?` matches a single non-`/` character.
+    /// Unlike `prefix`, a glob doesn't need a trailing `/` -- use a trailing
+    /// `**` if it should match anything below a given path.
+    Glob,
+    /// `value` is an anchored regular expression (see
+    /// [PathFilter::regex]) matched against the canonicalized path, for
+    /// advertisers with structured-but-variable paths (locale segments,
+    /// campaign IDs) that would otherwise need many near-identical
+    /// `prefix`/`glob` rules.
+    Regex,
 }
 
 fn check_paths(paths: &Option<Vec<PathFilter>>) -> bool {
@@ -81,6 +341,8 @@ impl TryFrom<&str> for PathMatching {
         match string.to_lowercase().as_str() {
             "prefix" => Ok(Self::Prefix),
             "exact" => Ok(Self::Exact),
+            "glob" => Ok(Self::Glob),
+            "regex" => Ok(Self::Regex),
             _ => Err(ConfigError::Message(format!(
                 "Invalid Path Filter Type {}",
                 string
@@ -94,16 +356,196 @@ impl From<PathMatching> for &'static str {
         match pm {
             PathMatching::Prefix => "prefix",
             PathMatching::Exact => "exact",
+            PathMatching::Glob => "glob",
+            PathMatching::Regex => "regex",
+        }
+    }
+}
+
+/// Match `path` against a glob `pattern`, both as raw bytes. `*` matches any
+/// run of characters except `/` (within one path segment), `**` matches any
+/// run including `/` (spanning segments), `?` matches a single non-`/`
+/// character, and every other byte matches literally. Backtracks on
+/// `*`/`**` by trying each possible match length in turn.
+pub(crate) fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            (0..=path.len()).any(|i| glob_match(rest, &path[i..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            let segment_end = path.iter().position(|&b| b == b'/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| glob_match(rest, &path[i..]))
+        }
+        Some(b'?') => {
+            matches!(path.first(), Some(&b) if b != b'/') && glob_match(&pattern[1..], &path[1..])
+        }
+        Some(&c) => {
+            matches!(path.first(), Some(&b) if b == c) && glob_match(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+/// Percent-decode `path`'s unreserved-set encodings -- `%XX` where the
+/// decoded byte is an ASCII letter, digit, or one of `-._~` (RFC 3986 §2.3)
+/// -- leaving every other `%XX` as-is, since those aren't equivalent to
+/// their literal byte (e.g. a literal `/` would change the path's
+/// structure).
+fn percent_decode_unreserved(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                    out.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Canonicalize an absolute URL `path` so `PathMatching::Exact`/`Prefix`
+/// can't be bypassed by an equivalent but differently-spelled path: decode
+/// unreserved-set percent-encodings (see [percent_decode_unreserved]),
+/// then resolve it segment-by-segment like [RFC 3986 §5.2.4's
+/// remove_dot_segments](https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4)
+/// -- dropping empty and `.` segments (which also collapses duplicate
+/// `/`s) and popping the previous segment on `..`. `/%63a/`, `/./ca/`, and
+/// `//ca/` all canonicalize to `/ca/`, matching how the `url` crate's own
+/// path parser treats dot segments. The configured `PathFilter::value` is
+/// canonicalized the same way at parse time (see its `Deserialize` impl)
+/// so both sides of the comparison are in the same normal form.
+pub(crate) fn canonicalize_path(path: &str) -> String {
+    let decoded = percent_decode_unreserved(path);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut canonical = format!("/{}", segments.join("/"));
+    if decoded.ends_with('/') && !canonical.ends_with('/') {
+        canonical.push('/');
+    }
+    canonical
+}
+
+/// Parse `data` as either JSON or YAML. The format is detected from `hint`
+/// (a file path or URL, checked for a `.yaml`/`.yml`/`.json` extension)
+/// falling back to sniffing `data` itself -- a YAML document is assumed
+/// unless it starts with `{` or `[`.
+pub(crate) fn parse_document<T: de::DeserializeOwned>(
+    data: &str,
+    hint: &str,
+) -> Result<T, ConfigError> {
+    let is_yaml = if hint.ends_with(".yaml") || hint.ends_with(".yml") {
+        true
+    } else if hint.ends_with(".json") {
+        false
+    } else {
+        !matches!(data.trim_start().as_bytes().first(), Some(b'{' | b'['))
+    };
+    if is_yaml {
+        serde_yaml::from_str(data)
+            .map_err(|e| ConfigError::Message(format!("Invalid YAML document: {:?}", e)))
+    } else {
+        serde_json::from_str(data)
+            .map_err(|e| ConfigError::Message(format!("Invalid JSON document: {:?}", e)))
+    }
+}
+
+/// A set of advertiser names to match against, e.g. for
+/// `CONTILE_ADM_IGNORE_ADVERTISERS`/`adm_has_legacy_image`. Plain entries are
+/// matched exactly (via a fast `HashSet` lookup); entries containing `*` or
+/// `?` are matched as glob patterns (see [glob_match]), so operators can
+/// write e.g. `"test-*"` or `"*-staging"` to cover a whole family of
+/// advertisers instead of listing each one. Matching is always
+/// case-insensitive.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NameMatchSet {
+    exact: HashSet<String>,
+    globs: Vec<Box<[u8]>>,
+}
+
+impl NameMatchSet {
+    /// Check whether `name` matches this set, case-insensitively.
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.exact.contains(&name)
+            || self
+                .globs
+                .iter()
+                .any(|pattern| glob_match(pattern, name.as_bytes()))
+    }
+}
+
+impl FromIterator<String> for NameMatchSet {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut exact = HashSet::new();
+        let mut globs = Vec::new();
+        for name in iter {
+            let name = name.to_lowercase();
+            if name.contains('*') || name.contains('?') {
+                globs.push(name.into_bytes().into_boxed_slice());
+            } else {
+                exact.insert(name);
+            }
         }
+        Self { exact, globs }
     }
 }
 
+/// An anchored `PathMatching::Regex` pattern is capped at this many bytes
+/// before compilation, bounding both compile time and the backing automaton
+/// size regardless of the `size_limit` below -- an operator typo (e.g. a
+/// pattern built from unsanitized input) can't blow either up.
+const MAX_PATH_REGEX_LEN: usize = 512;
+
+/// Compile a `PathMatching::Regex` pattern with a deliberately small engine:
+/// Unicode tables are disabled (canonicalized paths are always ASCII --
+/// [percent_decode_unreserved] only decodes the ASCII unreserved set, and
+/// every other byte stays percent-encoded) and the compiled-program size is
+/// capped well below the crate's 10MiB default, so a pathological pattern
+/// fails fast at load time rather than costing memory/time on every match.
+fn compile_path_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern)
+        .unicode(false)
+        .size_limit(1 << 20)
+        .build()
+}
+
 /// PathFilter describes how path filtering is conducted. See more details in
 /// AdvertiserUrlFilter.
 #[derive(Clone, Debug, Serialize)]
 pub struct PathFilter {
     pub(crate) value: String,
     pub(crate) matching: PathMatching,
+    /// `value` pre-split into bytes for `PathMatching::Glob`, so matching a
+    /// request doesn't need to re-derive it from the `String` each time.
+    /// `None` for every other `matching`.
+    #[serde(skip)]
+    pub(crate) glob: Option<Box<[u8]>>,
+    /// `value` compiled once for `PathMatching::Regex` (see
+    /// [compile_path_regex]), so `check_advertiser` never recompiles it.
+    /// `None` for every other `matching`.
+    #[serde(skip)]
+    pub(crate) regex: Option<Regex>,
 }
 
 impl Default for PathFilter {
@@ -111,6 +553,8 @@ impl Default for PathFilter {
         Self {
             value: "/".to_owned(),
             matching: PathMatching::Exact,
+            glob: None,
+            regex: None,
         }
     }
 }
@@ -127,18 +571,43 @@ impl<'de> Deserialize<'de> for PathFilter {
         }
 
         let inner = InnerPathFiler::deserialize(deserializer)?;
+        if let PathMatching::Regex = inner.matching {
+            if inner.value.len() > MAX_PATH_REGEX_LEN {
+                return Err(de::Error::custom(format!(
+                    "advertiser_urls regex PathFilter exceeds {} bytes",
+                    MAX_PATH_REGEX_LEN
+                )));
+            }
+            let regex = compile_path_regex(&inner.value).map_err(|e| {
+                de::Error::custom(format!(
+                    "advertiser_urls contain invalid regex PathFilter {:?}: {}",
+                    inner.value, e
+                ))
+            })?;
+            return Ok(PathFilter {
+                value: inner.value,
+                matching: inner.matching,
+                glob: None,
+                regex: Some(regex),
+            });
+        }
+
+        let value = canonicalize_path(&inner.value);
         if let PathMatching::Prefix = inner.matching {
-            if !inner.value.ends_with('/') {
+            if !value.ends_with('/') {
                 return Err(de::Error::custom(
                     "advertiser_urls contain invalid prefix PathFilter (missing trailing '/')"
                         .to_string(),
                 ));
             }
         }
+        let glob = matches!(inner.matching, PathMatching::Glob).then(|| value.as_bytes().into());
 
         Ok(PathFilter {
-            value: inner.value,
+            value,
             matching: inner.matching,
+            glob,
+            regex: None,
         })
     }
 }
@@ -153,6 +622,18 @@ pub struct AdmAdvertiserFilterSettings {
     pub(crate) countries: HashMap<String, Vec<AdvertiserUrlFilter>>,
 }
 
+/// Normalize `host` to the canonical ASCII form that [url::Url::host] also
+/// produces for an incoming tile URL: lowercased, NFC-normalized, and with
+/// each Unicode label IDNA-encoded to its `xn--` punycode form. `None` if
+/// `host` isn't a syntactically valid hostname. Configured hosts are
+/// normalized once at load time (here, and in [AdvertiserUrlFilter]'s
+/// `Deserialize` impl) so every later comparison is ASCII-vs-ASCII --
+/// otherwise a Unicode homograph domain (e.g. Cyrillic `а` for Latin `a`)
+/// could slip past a filter written in plain ASCII.
+fn normalize_host(host: &str) -> Option<String> {
+    Some(url::Host::parse(host).ok()?.to_string())
+}
+
 pub fn break_hosts(host: String) -> Vec<String> {
     host.split('.').map(ToOwned::to_owned).collect()
 }
@@ -161,30 +642,125 @@ fn make_host(split_host: &[String]) -> String {
     split_host.join(".")
 }
 
+/// One allowed host for click/impression/image URL filtering: a host
+/// (pre-split into labels, see [break_hosts]) plus an optional per-host
+/// override of [AdmDefaults::host_match] -- `Some(true)`/`Some(false)`
+/// forces subdomain acceptance on or off for just this entry regardless of
+/// the blanket `host_match`; `None` defers to it. This gives per-host
+/// granularity, mirroring [AdvertiserUrlFilter::allow_subdomains], instead
+/// of relying solely on the one global on/off switch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct HostFilter {
+    pub(crate) labels: Vec<String>,
+    pub(crate) include_subdomains: Option<bool>,
+}
+
+impl HostFilter {
+    /// Build a `HostFilter` for `host` with no per-entry override -- the
+    /// common case, and what a bare JSON string entry also produces.
+    pub(crate) fn new(host: &str) -> Self {
+        HostFilter {
+            labels: break_hosts(host.to_owned()),
+            include_subdomains: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HostFilterEntry {
+    Bare(String),
+    Detailed {
+        host: String,
+        #[serde(default)]
+        include_subdomains: Option<bool>,
+    },
+}
+
 /// Parse JSON:
-/// ["example.com", "foo.net"]
-/// into:
-/// [["example", "com"], ["foo", "net"]]
-fn deserialize_hosts<'de, D>(d: D) -> Result<Vec<Vec<String>>, D::Error>
+/// ["example.com", {"host": "foo.net", "include_subdomains": true}]
+/// into the equivalent [HostFilter]s, each normalized (see [normalize_host])
+/// and pre-split into labels (see [break_hosts]). Most entries are the bare
+/// string form; the object form is only needed to set `include_subdomains`
+/// for that one host.
+fn deserialize_hosts<'de, D>(d: D) -> Result<Vec<HostFilter>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    Deserialize::deserialize(d)
-        .map(|hosts: Vec<String>| hosts.into_iter().map(break_hosts).collect())
+    let entries: Vec<HostFilterEntry> = Deserialize::deserialize(d)?;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let (host, include_subdomains) = match entry {
+                HostFilterEntry::Bare(host) => (host, None),
+                HostFilterEntry::Detailed {
+                    host,
+                    include_subdomains,
+                } => (host, include_subdomains),
+            };
+            let normalized = normalize_host(&host)
+                .ok_or_else(|| de::Error::custom(format!("invalid host: {:?}", host)))?;
+            Ok(HostFilter {
+                labels: break_hosts(normalized),
+                include_subdomains,
+            })
+        })
+        .collect()
 }
 
-/// Serialize:
-/// [["example", "com"], ["foo", "net"]]
-/// into:
-/// ["example.com", "foo.net"]
-fn serialize_hosts<S>(hosts: &[Vec<String>], s: S) -> Result<S::Ok, S::Error>
+/// Serialize [HostFilter]s back to their JSON form -- a bare string when
+/// `include_subdomains` is unset, an object otherwise.
+fn serialize_hosts<S>(hosts: &[HostFilter], s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let hosts: Vec<_> = hosts.iter().map(|v| make_host(v)).collect();
+    #[derive(Serialize)]
+    struct Detailed<'a> {
+        host: &'a str,
+        include_subdomains: bool,
+    }
+
     let mut seq = s.serialize_seq(Some(hosts.len()))?;
-    for host in hosts {
-        seq.serialize_element(&host)?;
+    for entry in hosts {
+        let host = make_host(&entry.labels);
+        match entry.include_subdomains {
+            None => seq.serialize_element(&host)?,
+            Some(include_subdomains) => seq.serialize_element(&Detailed {
+                host: &host,
+                include_subdomains,
+            })?,
+        }
+    }
+    seq.end()
+}
+
+/// Parse a JSON list of regex strings (e.g. `["^[a-z]+\\.example\\.com$"]`)
+/// into compiled patterns, so hosts can be matched by pattern in addition to
+/// the literal `*_hosts` sets. Compiling here means this only happens once,
+/// at settings-load time, rather than on every request.
+fn deserialize_patterns<'de, D>(d: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns: Vec<String> = Deserialize::deserialize(d)?;
+    patterns
+        .into_iter()
+        .map(|pattern| {
+            Regex::new(&pattern).map_err(|e| {
+                de::Error::custom(format!("Invalid host pattern {:?}: {}", pattern, e))
+            })
+        })
+        .collect()
+}
+
+/// Serialize compiled host patterns back into their source strings.
+fn serialize_patterns<S>(patterns: &[Regex], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(patterns.len()))?;
+    for pattern in patterns {
+        seq.serialize_element(pattern.as_str())?;
     }
     seq.end()
 }
@@ -237,7 +813,18 @@ impl AdmPse {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Default, Serialize)]
+/// Overlay a per-environment override document on top of a base one, so
+/// operators can keep a shared baseline plus small country- or
+/// stage-specific overlays instead of one monolithic blob. See
+/// `Settings::adm_defaults`/`adm_defaults_override` and
+/// `Settings::adm_settings`/`adm_settings_override`.
+pub(crate) trait Merge {
+    /// Overlay `override_doc` on top of `self` (the base), returning the
+    /// merged result.
+    fn merge(self, override_doc: Self) -> Self;
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AdmDefaults {
     /// Required set of valid hosts and paths for the `advertiser_url`
     #[serde(default)]
@@ -248,32 +835,315 @@ pub struct AdmDefaults {
         serialize_with = "serialize_hosts",
         default
     )]
-    pub(crate) impression_hosts: Vec<Vec<String>>,
+    pub(crate) impression_hosts: Vec<HostFilter>,
+    /// Optional regex patterns checked against the `impression_url` host
+    /// alongside `impression_hosts`, e.g. for rotating subdomains that are
+    /// impractical to enumerate literally.
+    #[serde(
+        deserialize_with = "deserialize_patterns",
+        serialize_with = "serialize_patterns",
+        default
+    )]
+    pub(crate) impression_host_patterns: Vec<Regex>,
     /// Optional set of valid hosts for the `click_url`
     #[serde(
         deserialize_with = "deserialize_hosts",
         serialize_with = "serialize_hosts",
         default
     )]
-    pub(crate) click_hosts: Vec<Vec<String>>,
+    pub(crate) click_hosts: Vec<HostFilter>,
+    /// Optional regex patterns checked against the `click_url` host
+    /// alongside `click_hosts`.
+    #[serde(
+        deserialize_with = "deserialize_patterns",
+        serialize_with = "serialize_patterns",
+        default
+    )]
+    pub(crate) click_host_patterns: Vec<Regex>,
     #[serde(
         deserialize_with = "deserialize_hosts",
         serialize_with = "serialize_hosts",
         default
     )]
-    pub(crate) image_hosts: Vec<Vec<String>>,
+    pub(crate) image_hosts: Vec<HostFilter>,
+    /// Optional regex patterns checked against the `image_url` host
+    /// alongside `image_hosts`.
+    #[serde(
+        deserialize_with = "deserialize_patterns",
+        serialize_with = "serialize_patterns",
+        default
+    )]
+    pub(crate) image_host_patterns: Vec<Regex>,
     /// valid position for the tile
     pub(crate) position: Option<u8>,
-    /// Optional set of valid countries for the tile (e.g ["US", "GB"])
-    //#[serde(default)]
-    //pub(crate) include_regions: Vec<String>,
+    /// Fallback `include_regions` used when a tile's advertiser entries
+    /// don't declare any of their own (e.g ["US", "GB"])
+    #[serde(default)]
+    pub(crate) include_regions: Vec<String>,
     pub(crate) ignore_advertisers: Option<Vec<String>>,
     pub(crate) ignore_dmas: Option<Vec<u8>>,
+    /// Matching mode for `click_hosts`/`impression_hosts`/`image_hosts` --
+    /// see [HostMatchMode].
+    #[serde(default)]
+    pub(crate) host_match: HostMatchMode,
+    /// Required `click_url` query parameter keys (default: `["ci", "ctag",
+    /// "key", "version"]`, the historical hard-coded set from pg 15 of the
+    /// 5.7.21 spec).
+    #[serde(default = "default_click_req_params")]
+    pub(crate) click_req_params: Vec<String>,
+    /// Additional `click_url` query parameter keys that are allowed but not
+    /// required, on top of `click_req_params` (default: `["click-status"]`).
+    #[serde(default = "default_click_opt_params")]
+    pub(crate) click_opt_params: Vec<String>,
+    /// Per-rejection-reason behavior -- see [FilterActions].
+    #[serde(default)]
+    pub(crate) rejection_actions: FilterActions,
+    /// Allow advertiser/image URLs whose host is a bare IPv4/IPv6 literal
+    /// (default: `false`, rejected). A legitimate sponsored destination is
+    /// always a registered domain, so this exists only as an opt-in for
+    /// test/staging deployments that point at bare IPs.
+    #[serde(default)]
+    pub(crate) allow_ip_hosts: bool,
+}
+
+fn default_click_req_params() -> Vec<String> {
+    ["ci", "ctag", "key", "version"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn default_click_opt_params() -> Vec<String> {
+    vec!["click-status".to_owned()]
+}
+
+impl Default for AdmDefaults {
+    fn default() -> Self {
+        Self {
+            advertiser_urls: Default::default(),
+            impression_hosts: Default::default(),
+            impression_host_patterns: Default::default(),
+            click_hosts: Default::default(),
+            click_host_patterns: Default::default(),
+            image_hosts: Default::default(),
+            image_host_patterns: Default::default(),
+            position: Default::default(),
+            include_regions: Default::default(),
+            ignore_advertisers: Default::default(),
+            ignore_dmas: Default::default(),
+            host_match: Default::default(),
+            click_req_params: default_click_req_params(),
+            click_opt_params: default_click_opt_params(),
+            rejection_actions: Default::default(),
+            allow_ip_hosts: Default::default(),
+        }
+    }
+}
+
+impl Merge for AdmDefaults {
+    /// A non-empty vector or `Some` scalar in `override_doc` wins; an empty
+    /// vector or `None` leaves `self` (the base) untouched.
+    fn merge(self, override_doc: Self) -> Self {
+        fn pick<T>(base: Vec<T>, over: Vec<T>) -> Vec<T> {
+            if over.is_empty() {
+                base
+            } else {
+                over
+            }
+        }
+
+        Self {
+            advertiser_urls: pick(self.advertiser_urls, override_doc.advertiser_urls),
+            impression_hosts: pick(self.impression_hosts, override_doc.impression_hosts),
+            impression_host_patterns: pick(
+                self.impression_host_patterns,
+                override_doc.impression_host_patterns,
+            ),
+            click_hosts: pick(self.click_hosts, override_doc.click_hosts),
+            click_host_patterns: pick(self.click_host_patterns, override_doc.click_host_patterns),
+            image_hosts: pick(self.image_hosts, override_doc.image_hosts),
+            image_host_patterns: pick(self.image_host_patterns, override_doc.image_host_patterns),
+            position: override_doc.position.or(self.position),
+            include_regions: pick(self.include_regions, override_doc.include_regions),
+            ignore_advertisers: override_doc.ignore_advertisers.or(self.ignore_advertisers),
+            ignore_dmas: override_doc.ignore_dmas.or(self.ignore_dmas),
+            // `Suffix` in either document wins; `Strict` is the recessive default.
+            host_match: if override_doc.host_match == HostMatchMode::Suffix
+                || self.host_match == HostMatchMode::Suffix
+            {
+                HostMatchMode::Suffix
+            } else {
+                HostMatchMode::Strict
+            },
+            click_req_params: pick(self.click_req_params, override_doc.click_req_params),
+            click_opt_params: pick(self.click_opt_params, override_doc.click_opt_params),
+            rejection_actions: self.rejection_actions.merge(override_doc.rejection_actions),
+            // `true` in either document wins, mirroring `host_match` above:
+            // an override opting into IP hosts shouldn't be silently undone
+            // by a base document that didn't mention it.
+            allow_ip_hosts: self.allow_ip_hosts || override_doc.allow_ip_hosts,
+        }
+    }
+}
+
+impl AdmDefaults {
+    /// Drop too-broad entries from `advertiser_urls` (see
+    /// [AdvertiserUrlFilter::is_too_broad]), plus any `click_hosts`/
+    /// `impression_hosts`/`image_hosts` entry that allows subdomains --
+    /// whether via its own `include_subdomains: true` or, absent that, the
+    /// blanket [HostMatchMode::Suffix] -- and is itself a bare public
+    /// suffix rather than a registrable domain, e.g. a `["co", "uk"]` entry
+    /// would otherwise act as a wildcard root for every co.uk site. Warns
+    /// on each drop so a misconfigured default doesn't just silently stop
+    /// backwards-expanding.
+    pub(crate) fn reject_overbroad_hosts(&mut self) {
+        self.advertiser_urls.retain(|filter| {
+            let too_broad = filter.is_too_broad();
+            if too_broad {
+                warn!(
+                    "Rejecting too-broad default advertiser_url filter: {:?} is a public suffix",
+                    filter.host
+                );
+            }
+            !too_broad
+        });
+        let blanket_suffix = self.host_match == HostMatchMode::Suffix;
+        for (species, hosts) in [
+            ("click_hosts", &mut self.click_hosts),
+            ("impression_hosts", &mut self.impression_hosts),
+            ("image_hosts", &mut self.image_hosts),
+        ] {
+            hosts.retain(|entry| {
+                if !entry.include_subdomains.unwrap_or(blanket_suffix) {
+                    return true;
+                }
+                let host = entry.labels.join(".");
+                let too_broad = super::psl::registrable_domain(&host).is_none();
+                if too_broad {
+                    warn!(
+                        "Rejecting too-broad default {}: {:?} is a public suffix",
+                        species, host
+                    );
+                }
+                !too_broad
+            });
+        }
+    }
 }
 
-#[derive(Debug, Default, Deserialize, Clone)]
+impl Merge for FilterActions {
+    /// A reason explicitly set to a non-default action in `override_doc`
+    /// wins; otherwise `self` (the base) is kept. Mirrors `host_match`'s
+    /// "non-default wins" rule above, for the same reason: a plain enum
+    /// field has no natural `Option`-shaped "unset" state to merge on.
+    fn merge(self, override_doc: Self) -> Self {
+        fn pick(base: RejectionAction, over: RejectionAction) -> RejectionAction {
+            if over != RejectionAction::default() {
+                over
+            } else {
+                base
+            }
+        }
+        Self {
+            invalid_region: pick(self.invalid_region, override_doc.invalid_region),
+            invalid_advertiser: pick(self.invalid_advertiser, override_doc.invalid_advertiser),
+            invalid_click: pick(self.invalid_click, override_doc.invalid_click),
+            invalid_impression: pick(self.invalid_impression, override_doc.invalid_impression),
+            invalid_image_host: pick(self.invalid_image_host, override_doc.invalid_image_host),
+            invalid_image: pick(self.invalid_image, override_doc.invalid_image),
+            unexpected_advertiser: pick(
+                self.unexpected_advertiser,
+                override_doc.unexpected_advertiser,
+            ),
+            blocklisted: pick(self.blocklisted, override_doc.blocklisted),
+            ip_host: pick(self.ip_host, override_doc.ip_host),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct AdmAdvertiserSettings {
     pub adm_advertisers: HashMap<String, HashMap<String, Vec<AdvertiserUrlFilter>>>,
+    /// Alternate names a settings block should also be looked up under,
+    /// keyed by the canonical (as-configured) advertiser name, e.g.
+    /// `{"Example Co": ["Example", "ExampleCo"]}` registers the `"Example
+    /// Co"` filters under `"example"` and `"exampleco"` as well. Partners
+    /// frequently ship tiles under more than one `name`, or rename
+    /// themselves, so this avoids duplicating the whole filter block per
+    /// alias.
+    pub aliases: HashMap<String, Vec<String>>,
+    /// Advertisers marked `{"deleted": true}` in this document, see
+    /// [AdvertiserEntry]. Only meaningful when this document is merged as a
+    /// per-environment override over a base one (see [Merge]): it removes
+    /// the advertiser from the base instead of leaving it untouched.
+    pub(crate) deleted: HashSet<String>,
+    /// Crate-wide deny list: a tile whose advertiser, click, or image URL
+    /// host matches any entry here is rejected regardless of which
+    /// advertiser it claims to be, taking effect before any per-advertiser
+    /// check runs. Checked the same way as `AdmDefaults`' `*_hosts` (see
+    /// [HostFilter]), so a bare public suffix can't act as a wildcard root
+    /// and an entry may opt into matching subdomains via
+    /// `include_subdomains`. Parsed in [AdmAdvertiserSettings]'s
+    /// `Deserialize` impl via [deserialize_hosts], same as `AdmDefaults`'
+    /// `*_hosts` fields.
+    pub(crate) host_denylist: Vec<HostFilter>,
+    /// Crate-wide allow list: when non-empty, a tile is rejected unless at
+    /// least one of its advertiser, click, or image URL hosts matches an
+    /// entry here. Empty (the default) means unrestricted -- this is an
+    /// opt-in kill-switch, not a replacement for per-advertiser filtering.
+    pub(crate) host_allowlist: Vec<HostFilter>,
+}
+
+/// One entry under `adm_advertisers`: either a normal per-country filter
+/// list, or a `{"deleted": true}` marker. The marker has no effect parsing
+/// a single, standalone document (the advertiser is simply absent) -- see
+/// [AdmAdvertiserSettings::deleted] for where it matters.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum AdvertiserEntry {
+    Deleted { deleted: bool },
+    Filters(HashMap<String, Vec<AdvertiserUrlFilter>>),
+}
+
+impl<'de> Deserialize<'de> for AdmAdvertiserSettings {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct InnerAdmAdvertiserSettings {
+            #[serde(default)]
+            adm_advertisers: HashMap<String, AdvertiserEntry>,
+            #[serde(default)]
+            aliases: HashMap<String, Vec<String>>,
+            #[serde(deserialize_with = "deserialize_hosts", default)]
+            host_denylist: Vec<HostFilter>,
+            #[serde(deserialize_with = "deserialize_hosts", default)]
+            host_allowlist: Vec<HostFilter>,
+        }
+
+        let inner = InnerAdmAdvertiserSettings::deserialize(deserializer)?;
+        let mut adm_advertisers = HashMap::new();
+        let mut deleted = HashSet::new();
+        for (name, entry) in inner.adm_advertisers {
+            match entry {
+                AdvertiserEntry::Filters(filters) => {
+                    adm_advertisers.insert(name, filters);
+                }
+                AdvertiserEntry::Deleted { .. } => {
+                    deleted.insert(name);
+                }
+            }
+        }
+
+        Ok(AdmAdvertiserSettings {
+            adm_advertisers,
+            aliases: inner.aliases,
+            deleted,
+            host_denylist: inner.host_denylist,
+            host_allowlist: inner.host_allowlist,
+        })
+    }
 }
 
 impl Serialize for AdmAdvertiserSettings {
@@ -285,6 +1155,374 @@ impl Serialize for AdmAdvertiserSettings {
     }
 }
 
+impl Merge for AdmAdvertiserSettings {
+    /// `override_doc`'s advertisers replace `self`'s (the base's)
+    /// key-by-key; an advertiser in `override_doc.deleted` is instead
+    /// removed from the merged result. `host_denylist`/`host_allowlist`
+    /// follow the same "non-empty override wins" rule as `AdmDefaults`'
+    /// `*_hosts` fields.
+    fn merge(mut self, override_doc: Self) -> Self {
+        for name in &override_doc.deleted {
+            self.adm_advertisers.remove(name);
+        }
+        self.adm_advertisers.extend(override_doc.adm_advertisers);
+        self.aliases.extend(override_doc.aliases);
+        self.deleted = override_doc.deleted;
+        if !override_doc.host_denylist.is_empty() {
+            self.host_denylist = override_doc.host_denylist;
+        }
+        if !override_doc.host_allowlist.is_empty() {
+            self.host_allowlist = override_doc.host_allowlist;
+        }
+        self
+    }
+}
+
+impl AdmAdvertiserSettings {
+    /// Register each advertiser's filters under its configured `aliases`
+    /// too (in addition to its own name), so a tile whose `name` is an
+    /// alias still resolves to the right filter settings. Lookups are
+    /// always lowercased (see [AdmFilter::update] and
+    /// `AdmFilter::filter_and_process`), so aliases are registered
+    /// lowercased as well.
+    pub(crate) fn expand_aliases(&mut self) {
+        for (canonical, aliases) in &self.aliases {
+            let Some(filters) = self.adm_advertisers.get(&canonical.to_lowercase()).cloned() else {
+                continue;
+            };
+            for alias in aliases {
+                self.adm_advertisers
+                    .entry(alias.to_lowercase())
+                    .or_insert_with(|| filters.clone());
+            }
+        }
+    }
+
+    /// Drop any per-advertiser filter entry that's too broad (see
+    /// [AdvertiserUrlFilter::is_too_broad]), warning so a misconfigured
+    /// filter doesn't just silently fail to anchor anywhere -- rather than
+    /// leave it in place to quietly never match beyond its exact host. Also
+    /// drops any `host_denylist`/`host_allowlist` entry that opts into
+    /// subdomain matching over a bare public suffix, the same overbreadth
+    /// guard `AdmDefaults::reject_overbroad_hosts` applies to `*_hosts`.
+    pub(crate) fn reject_overbroad_hosts(&mut self) {
+        for (advertiser, countries) in self.adm_advertisers.iter_mut() {
+            for (country, filters) in countries.iter_mut() {
+                filters.retain(|filter| {
+                    let too_broad = filter.is_too_broad();
+                    if too_broad {
+                        warn!(
+                            "Rejecting too-broad advertiser_url filter for {:?}/{:?}: {:?} is a public suffix",
+                            advertiser, country, filter.host
+                        );
+                    }
+                    !too_broad
+                });
+            }
+        }
+        for (species, hosts) in [
+            ("host_denylist", &mut self.host_denylist),
+            ("host_allowlist", &mut self.host_allowlist),
+        ] {
+            hosts.retain(|entry| {
+                if !entry.include_subdomains.unwrap_or(false) {
+                    return true;
+                }
+                let host = entry.labels.join(".");
+                let too_broad = super::psl::registrable_domain(&host).is_none();
+                if too_broad {
+                    warn!(
+                        "Rejecting too-broad default {}: {:?} is a public suffix",
+                        species, host
+                    );
+                }
+                !too_broad
+            });
+        }
+    }
+
+    /// Structural dry-run validation for a config push (see
+    /// [AdmFilter::update]), run before the new settings are allowed to
+    /// replace the live ones: a malformed `host` or an unsatisfiable path
+    /// filter shouldn't be able to silently corrupt filtering until the
+    /// next good push. Reports the first problem found, if any; doesn't
+    /// modify `self`.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        for (advertiser, countries) in &self.adm_advertisers {
+            for (country, filters) in countries {
+                for filter in filters {
+                    filter
+                        .validate()
+                        .map_err(|e| format!("{:?}/{:?}: {}", advertiser, country, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Opaque backend-specific revision marker for a settings document -- an S3
+/// ETag, a GCS object generation, or a local file's mtime -- used to detect
+/// an unchanged document without necessarily re-downloading it.
+pub(crate) type Fingerprint = String;
+
+/// Backend-agnostic fetch of the raw ADM settings document, so callers
+/// (the one-off initial load as well as the periodic refresh in
+/// [AdmFilter::fetch_new_settings]) don't need to know whether
+/// `adm_settings` points at `gs://`, `s3://`, or a local `file://` path.
+#[async_trait(?Send)]
+pub(crate) trait SettingsSource: Debug {
+    async fn download(&self) -> Result<Vec<u8>, ConfigError>;
+
+    /// When this source's content was last modified, and its current
+    /// [Fingerprint].
+    async fn stat(&self) -> Result<(OffsetDateTime, Fingerprint), ConfigError>;
+
+    /// Re-`download` only if changed since `since`'s fingerprint -- `None`
+    /// (and no download) if the source hasn't changed. The default impl
+    /// `stat`s first and only `download`s if the fingerprint differs --
+    /// still two round trips on a change. A backend whose client supports a
+    /// true conditional download (S3's `If-None-Match`) should override
+    /// this to collapse both into a single request instead.
+    async fn fetch_if_modified(
+        &self,
+        since: Option<&Fingerprint>,
+    ) -> Result<Option<(Vec<u8>, OffsetDateTime, Fingerprint)>, ConfigError> {
+        let (modified, fingerprint) = self.stat().await?;
+        if since == Some(&fingerprint) {
+            return Ok(None);
+        }
+        Ok(Some((self.download().await?, modified, fingerprint)))
+    }
+}
+
+/// `gs://<bucket>/<path>`, fetched via the `cloud_storage` crate.
+#[derive(Debug)]
+struct GsSettingsSource {
+    client: cloud_storage::Client,
+    bucket: String,
+    path: String,
+}
+
+#[async_trait(?Send)]
+impl SettingsSource for GsSettingsSource {
+    async fn download(&self) -> Result<Vec<u8>, ConfigError> {
+        self.client
+            .object()
+            .download(&self.bucket, &self.path)
+            .await
+            .map_err(|e| ConfigError::Message(format!("Could not download settings: {:?}", e)))
+    }
+
+    async fn stat(&self) -> Result<(OffsetDateTime, Fingerprint), ConfigError> {
+        let object = self
+            .client
+            .object()
+            .read(&self.bucket, &self.path)
+            .await
+            .map_err(|e| ConfigError::Message(format!("Could not stat settings: {:?}", e)))?;
+        let modified = OffsetDateTime::from_unix_timestamp(object.updated.timestamp())
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+        Ok((modified, object.generation.to_string()))
+    }
+}
+
+/// `s3://<bucket>/<key>`, fetched via `aws_sdk_s3`. Region/credentials come
+/// from `Settings::adm_settings_s3_*`, falling back to the AWS-standard
+/// environment (same convention as
+/// [crate::server::img_storage::S3Storage]).
+#[derive(Debug)]
+struct S3SettingsSource {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    key: String,
+}
+
+impl S3SettingsSource {
+    async fn new(settings: &Settings, bucket: String, key: String) -> Result<Self, ConfigError> {
+        let region = aws_sdk_s3::config::Region::new(
+            settings
+                .adm_settings_s3_region
+                .clone()
+                .unwrap_or_else(|| "us-east-1".to_owned()),
+        );
+        let mut loader = aws_config::from_env().region(region);
+        if let (Some(access_env), Some(secret_env)) = (
+            &settings.adm_settings_s3_access_key_env,
+            &settings.adm_settings_s3_secret_key_env,
+        ) {
+            let access_key = std::env::var(access_env)
+                .map_err(|e| ConfigError::Message(format!("Missing {}: {:?}", access_env, e)))?;
+            let secret_key = std::env::var(secret_env)
+                .map_err(|e| ConfigError::Message(format!("Missing {}: {:?}", secret_env, e)))?;
+            loader = loader.credentials_provider(aws_sdk_s3::config::Credentials::new(
+                access_key,
+                secret_key,
+                None,
+                None,
+                "contile-adm-settings",
+            ));
+        }
+        let config = loader.load().await;
+        Ok(Self {
+            client: aws_sdk_s3::Client::new(&config),
+            bucket,
+            key,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl SettingsSource for S3SettingsSource {
+    async fn download(&self) -> Result<Vec<u8>, ConfigError> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .send()
+            .await
+            .map_err(|e| ConfigError::Message(format!("Could not download settings: {:?}", e)))?;
+        let data =
+            output.body.collect().await.map_err(|e| {
+                ConfigError::Message(format!("Could not read settings body: {:?}", e))
+            })?;
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn stat(&self) -> Result<(OffsetDateTime, Fingerprint), ConfigError> {
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .send()
+            .await
+            .map_err(|e| ConfigError::Message(format!("Could not stat settings: {:?}", e)))?;
+        let modified = output
+            .last_modified()
+            .ok_or_else(|| ConfigError::Message("Missing Last-Modified on S3 object".to_owned()))?;
+        let modified = OffsetDateTime::from_unix_timestamp(modified.secs())
+            .map_err(|e| ConfigError::Message(format!("Invalid Last-Modified: {:?}", e)))?;
+        let etag = output
+            .e_tag()
+            .ok_or_else(|| ConfigError::Message("Missing ETag on S3 object".to_owned()))?
+            .to_owned();
+        Ok((modified, etag))
+    }
+
+    /// `aws_sdk_s3` supports a real conditional GET (`If-None-Match`), so
+    /// this collapses the usual stat-then-download into a single request
+    /// when `since` is known -- unlike the GCS/file backends, which still
+    /// pay for a separate stat via the default impl.
+    async fn fetch_if_modified(
+        &self,
+        since: Option<&Fingerprint>,
+    ) -> Result<Option<(Vec<u8>, OffsetDateTime, Fingerprint)>, ConfigError> {
+        let mut request = self.client.get_object().bucket(&self.bucket).key(&self.key);
+        if let Some(etag) = since {
+            request = request.if_none_match(etag);
+        }
+        let output = match request.send().await {
+            Ok(output) => output,
+            Err(e) if is_not_modified(&e) => return Ok(None),
+            Err(e) => return Err(ConfigError::Message(format!(
+                "Could not download settings: {:?}",
+                e
+            ))),
+        };
+        let modified = output
+            .last_modified()
+            .ok_or_else(|| ConfigError::Message("Missing Last-Modified on S3 object".to_owned()))?;
+        let modified = OffsetDateTime::from_unix_timestamp(modified.secs())
+            .map_err(|e| ConfigError::Message(format!("Invalid Last-Modified: {:?}", e)))?;
+        let etag = output
+            .e_tag()
+            .ok_or_else(|| ConfigError::Message("Missing ETag on S3 object".to_owned()))?
+            .to_owned();
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| ConfigError::Message(format!("Could not read settings body: {:?}", e)))?;
+        Ok(Some((data.into_bytes().to_vec(), modified, etag)))
+    }
+}
+
+/// Whether `err` is S3's `304 Not Modified` response to an `If-None-Match`
+/// conditional GET, surfaced by the SDK as a raw HTTP response rather than a
+/// typed `GetObjectError` variant.
+fn is_not_modified(err: &aws_sdk_s3::error::SdkError<aws_sdk_s3::operation::get_object::GetObjectError>) -> bool {
+    err.raw_response()
+        .map(|response| response.status().as_u16() == 304)
+        .unwrap_or(false)
+}
+
+/// `file://<path>`, read directly off the local filesystem. Distinct from
+/// `adm_settings` holding a bare (non-URL) path or inline JSON, which is
+/// still handled in `From<&mut Settings> for HandlerResult<AdmFilter>`
+/// without ever reaching here.
+#[derive(Debug)]
+struct FileSettingsSource {
+    path: String,
+}
+
+#[async_trait(?Send)]
+impl SettingsSource for FileSettingsSource {
+    async fn download(&self) -> Result<Vec<u8>, ConfigError> {
+        std::fs::read(&self.path)
+            .map_err(|e| ConfigError::Message(format!("Could not read {}: {:?}", self.path, e)))
+    }
+
+    async fn stat(&self) -> Result<(OffsetDateTime, Fingerprint), ConfigError> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| ConfigError::Message(format!("Could not stat {}: {:?}", self.path, e)))?;
+        let elapsed = modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let modified = OffsetDateTime::UNIX_EPOCH + elapsed;
+        Ok((modified, elapsed.as_nanos().to_string()))
+    }
+}
+
+/// Build the [SettingsSource] for `settings_bucket`'s scheme (`gs://`,
+/// `s3://`, or `file://`), so [AdmFilter::fetch_new_settings]'s periodic
+/// refresh isn't hard-wired to any one object store.
+pub(crate) async fn settings_source(
+    settings: &Settings,
+    settings_bucket: &url::Url,
+) -> Result<Box<dyn SettingsSource>, ConfigError> {
+    let settings_str = settings_bucket.as_str();
+    let scheme = settings_bucket.scheme();
+    if scheme == "file" {
+        return Ok(Box::new(FileSettingsSource {
+            path: settings_bucket.path().to_owned(),
+        }));
+    }
+    let bucket_name = settings_bucket
+        .host()
+        .ok_or_else(|| {
+            ConfigError::Message(format!("Invalid adm settings bucket name {}", settings_str))
+        })?
+        .to_string();
+    let path = settings_bucket.path().trim_start_matches('/').to_owned();
+    match scheme {
+        "gs" => Ok(Box::new(GsSettingsSource {
+            client: cloud_storage::Client::default(),
+            bucket: bucket_name,
+            path,
+        })),
+        "s3" => Ok(Box::new(
+            S3SettingsSource::new(settings, bucket_name, path).await?,
+        )),
+        other => Err(ConfigError::Message(format!(
+            "Unsupported adm settings bucket scheme: {:?}",
+            other
+        ))),
+    }
+}
+
 /// Create AdmSettings from a string serialized JSON format
 impl AdmFilter {
     /// Parse a JSON string containing the ADM settings. These will be generated by shepherd and
@@ -313,8 +1551,12 @@ impl AdmFilter {
     /// ```
     /// See [AdmFilter] for details.
     ///
-    /// The data can be read from a Google Cloud Storage bucket by passing a `gs://...` URL. The data will be read and
-    /// updated later by the automatic bucket reader, so we skip processing of that for now.
+    /// The data can also be read from a remote bucket by passing a `gs://...`
+    /// or `s3://...` URL (or a local path via `file://...`), via
+    /// [advertisers_from_settings_bucket], as either JSON or YAML (see
+    /// [parse_document]). `gs://`/`s3://`/`file://` data is read and updated
+    /// later by the automatic bucket reader, so we skip processing of that
+    /// for now.
 
     #[cfg(test)]
     pub fn advertisers_to_string(filters: AdmAdvertiserSettings) -> String {
@@ -335,36 +1577,17 @@ impl AdmFilter {
         Value::Object(adm_settings).to_string()
     }
 
-    /// Try to fetch the ADM settings from a Google Storage bucket url.
+    /// Try to fetch the ADM settings from a remote bucket (`gs://`, `s3://`)
+    /// or a local `file://` path, dispatched via [SettingsSource].
     pub async fn advertisers_from_settings_bucket(
-        cloud_storage: &cloud_storage::Client,
+        settings: &Settings,
         settings_bucket: &url::Url,
     ) -> Result<AdmAdvertiserSettings, ConfigError> {
-        let settings_str = settings_bucket.as_str();
-        if settings_bucket.scheme() != "gs" {
-            return Err(ConfigError::Message(format!(
-                "Improper bucket URL: {:?}",
-                settings_str
-            )));
-        }
-        let bucket_name = settings_bucket
-            .host()
-            .ok_or_else(|| {
-                ConfigError::Message(format!("Invalid adm settings bucket name {}", settings_str))
-            })?
-            .to_string();
-        let path = settings_bucket.path().trim_start_matches('/');
-        let contents = cloud_storage
-            .object()
-            .download(&bucket_name, path)
-            .await
-            .map_err(|e| ConfigError::Message(format!("Could not download settings: {:?}", e)))?;
-        serde_json::from_str(
-            &String::from_utf8(contents).map_err(|e| {
-                ConfigError::Message(format!("Could not read ADM Settings: {:?}", e))
-            })?,
-        )
-        .map_err(|e| ConfigError::Message(format!("Could not read ADM Settings: {:?}", e)))
+        let source = settings_source(settings, settings_bucket).await?;
+        let contents = source.download().await?;
+        let contents = String::from_utf8(contents)
+            .map_err(|e| ConfigError::Message(format!("Could not read ADM Settings: {:?}", e)))?;
+        parse_document(&contents, settings_bucket.path())
     }
 }
 
@@ -406,7 +1629,12 @@ impl AdmFilter {
 /// either "exact" where only the exact path is allowed, or "prefix" where the path must
 /// begin with the specified string.
 /// There is a special case for an advertiser having a `"deleted": true` flag indicating
-/// that this advertiser should be removed.
+/// that this advertiser should be removed -- this only has an effect when
+/// `adm_settings_override` is also set and merged over this document, see
+/// [Merge].
+///
+/// Both `adm_settings` and `adm_settings_override` may be written as JSON
+/// or YAML, see [parse_document].
 impl From<&mut Settings> for HandlerResult<AdmFilter> {
     fn from(settings: &mut Settings) -> Self {
         if settings.adm_sub1.is_none() ^ settings.adm_partner_id.is_none() {
@@ -435,7 +1663,10 @@ impl From<&mut Settings> for HandlerResult<AdmFilter> {
 
         let source = settings.adm_settings.clone();
 
-        let source_url = if source.starts_with("gs://") {
+        let is_remote_source = ["gs://", "s3://", "file://"]
+            .iter()
+            .any(|scheme| source.starts_with(scheme));
+        let source_url = if is_remote_source {
             match source.parse::<url::Url>() {
                 Ok(v) => Some(v),
                 Err(e) => {
@@ -449,13 +1680,29 @@ impl From<&mut Settings> for HandlerResult<AdmFilter> {
         } else {
             None
         };
-        let defaults = if let Some(default_str) = &settings.adm_defaults {
-            serde_json::from_str::<AdmDefaults>(default_str)
-                .map_err(|e| HandlerError::internal(&e.to_string()))?
+        let mut defaults = if let Some(default_str) = &settings.adm_defaults {
+            let defaults = parse_document::<AdmDefaults>(default_str, default_str)
+                .map_err(|e| HandlerError::internal(&e.to_string()))?;
+            if let Some(override_str) = &settings.adm_defaults_override {
+                let override_defaults = parse_document::<AdmDefaults>(override_str, override_str)
+                    .map_err(|e| HandlerError::internal(&e.to_string()))?;
+                defaults.merge(override_defaults)
+            } else {
+                defaults
+            }
         } else {
             Default::default()
         };
+        defaults.reject_overbroad_hosts();
         let excluded_countries_200 = settings.excluded_countries_200;
+        let proxy_config = match settings.adm_proxy.as_deref() {
+            Some(proxy_str) if !proxy_str.is_empty() => {
+                serde_json::from_str(proxy_str).map_err(|e| {
+                    HandlerErrorKind::Internal(format!("Invalid adm_proxy specification: {:?}", e))
+                })?
+            }
+            _ => ProxyConfig::default(),
+        };
 
         let settings_str = if Path::new(&settings.adm_settings).exists() {
             read_to_string(&settings.adm_settings)
@@ -477,35 +1724,48 @@ impl From<&mut Settings> for HandlerResult<AdmFilter> {
             settings.adm_settings.clone()
         };
 
-        let advertiser_filters = if source_url.is_some()
+        let mut advertiser_filters = if source_url.is_some()
             || (settings.adm_settings.is_empty() && settings.debug)
         {
-            AdmAdvertiserSettings {
-                adm_advertisers: HashMap::new(),
-            }
+            AdmAdvertiserSettings::default()
         } else {
-            serde_json::from_str(&settings_str)
+            parse_document(&settings_str, &settings.adm_settings)
                 .map_err(|e| ConfigError::Message(format!("Could not read ADM Settings: {:?}", e)))
-                .unwrap_or(AdmAdvertiserSettings {
-                    adm_advertisers: HashMap::new(),
-                })
+                .unwrap_or_default()
         };
-        let ignore_list: HashSet<String> = serde_json::from_str(&ignore_list).map_err(|e| {
+        if let Some(override_hint) = &settings.adm_settings_override {
+            let override_contents = if Path::new(override_hint).exists() {
+                read_to_string(override_hint).unwrap_or_else(|_| override_hint.clone())
+            } else {
+                override_hint.clone()
+            };
+            if let Ok(override_filters) =
+                parse_document::<AdmAdvertiserSettings>(&override_contents, override_hint)
+            {
+                advertiser_filters = advertiser_filters.merge(override_filters);
+            }
+        }
+        advertiser_filters.expand_aliases();
+        advertiser_filters.reject_overbroad_hosts();
+        let ignore_list: Vec<String> = serde_json::from_str(&ignore_list).map_err(|e| {
             HandlerError::internal(&format!("Invalid ADM Ignore list specification: {:?}", e))
         })?;
-        let legacy_list: HashSet<String> = serde_json::from_str(&legacy_list).map_err(|e| {
+        let ignore_list = NameMatchSet::from_iter(ignore_list);
+        let legacy_list: Vec<String> = serde_json::from_str(&legacy_list).map_err(|e| {
             HandlerError::internal(&format!("Invalid ADM Legacy list specification: {:?}", e))
         })?;
+        let legacy_list = NameMatchSet::from_iter(legacy_list);
         Ok(AdmFilter {
             advertiser_filters,
             ignore_list,
             legacy_list,
-            last_updated: source.starts_with("gs://").then(chrono::Utc::now),
+            last_updated: is_remote_source.then(chrono::Utc::now),
             source: Some(source),
             source_url,
             refresh_rate: std::time::Duration::from_secs(refresh_rate),
             defaults,
             excluded_countries_200,
+            proxy_config,
         })
     }
 }
@@ -522,18 +1782,19 @@ mod tests {
         // ignored, but no error is sent to sentry. Unfortunately, sentry 0.19 doesn't
         // support the introspection that later versions offer, so we have no way to
         // easily verify that no error is sent. For now, just make sure that the
-        // data is lower cased.
-        let mut result_list = HashSet::<String>::new();
-        result_list.insert("example".to_owned());
-        result_list.insert("invalid".to_owned());
-
+        // data is lower cased, and that glob patterns match families of
+        // advertisers.
         env::set_var(
             "CONTILE_ADM_IGNORE_ADVERTISERS",
-            r#"["Example", "INVALID"]"#,
+            r#"["Example", "INVALID", "test-*"]"#,
         );
         let mut settings = Settings::with_env_and_config_file(&None, true).unwrap();
         let result = HandlerResult::<AdmFilter>::from(&mut settings).unwrap();
-        assert_eq!(result.ignore_list, result_list);
+        assert!(result.is_ignored("example"));
+        assert!(result.is_ignored("invalid"));
+        assert!(result.is_ignored("test-foo"));
+        assert!(result.is_ignored("TEST-BAR"));
+        assert!(!result.is_ignored("other"));
     }
 
     #[test]
@@ -590,6 +1851,211 @@ mod tests {
         assert!(serde_json::from_str::<AdmAdvertiserSettings>(adm_settings).is_err());
     }
 
+    #[test]
+    pub fn test_reject_overbroad_advertiser_hosts() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [
+                {"host": "foo.com", "allow_subdomains": true},
+                {"host": "co.uk", "allow_subdomains": true},
+                {"host": "*.co.uk"},
+                {"host": "co.uk"}
+            ]
+        }}}"#;
+        let mut settings: AdmAdvertiserSettings = serde_json::from_str(adm_settings).unwrap();
+        settings.reject_overbroad_hosts();
+        let filters = &settings.adm_advertisers["test-adv"]["US"];
+        // Only the subdomain-anchored "foo.com" and the exact-only "co.uk"
+        // survive; "co.uk" as a subdomain root (either spelling) is too broad.
+        assert_eq!(filters.len(), 2);
+        assert!(filters.iter().any(|f| f.host == "foo.com"));
+        assert!(filters
+            .iter()
+            .any(|f| f.host == "co.uk" && !f.allow_subdomains));
+    }
+
+    #[test]
+    pub fn test_reject_overbroad_default_hosts() {
+        let mut defaults = AdmDefaults {
+            host_match: HostMatchMode::Suffix,
+            click_hosts: vec![HostFilter::new("example.com"), HostFilter::new("co.uk")],
+            ..Default::default()
+        };
+        defaults.reject_overbroad_hosts();
+        assert_eq!(defaults.click_hosts, vec![HostFilter::new("example.com")]);
+    }
+
+    #[test]
+    pub fn test_reject_overbroad_per_entry_override() {
+        // `host_match` is the default (`Strict`), but this entry opts into
+        // subdomain acceptance on its own -- it should be checked for
+        // overbreadth just the same as if `host_match` were `Suffix`.
+        let mut defaults = AdmDefaults {
+            click_hosts: vec![HostFilter {
+                labels: break_hosts("co.uk".to_owned()),
+                include_subdomains: Some(true),
+            }],
+            ..Default::default()
+        };
+        defaults.reject_overbroad_hosts();
+        assert!(defaults.click_hosts.is_empty());
+    }
+
+    #[test]
+    pub fn test_deserialize_rejects_malformed_host() {
+        // `host` normalization (see `normalize_host`) runs at deserialize
+        // time, so a malformed host is now rejected before `validate` ever
+        // sees it.
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [{"host": "not a host"}]
+        }}}"#;
+        assert!(serde_json::from_str::<AdmAdvertiserSettings>(adm_settings).is_err());
+    }
+
+    #[test]
+    pub fn test_deserialize_normalizes_homograph_host() {
+        // Cyrillic "а" (U+0430) in place of Latin "a" -- should normalize to
+        // its `xn--` punycode form rather than passing through verbatim.
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [{"host": "аcme.biz"}]
+        }}}"#;
+        let settings: AdmAdvertiserSettings = serde_json::from_str(adm_settings).unwrap();
+        let host = &settings.adm_advertisers["test-adv"]["US"][0].host;
+        assert!(host.starts_with("xn--"));
+        assert_ne!(host, "\u{430}cme.biz");
+    }
+
+    #[test]
+    pub fn test_deserialize_lowercases_host() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [{"host": "ACME.biz"}]
+        }}}"#;
+        let settings: AdmAdvertiserSettings = serde_json::from_str(adm_settings).unwrap();
+        assert_eq!(settings.adm_advertisers["test-adv"]["US"][0].host, "acme.biz");
+    }
+
+    #[test]
+    pub fn test_validate_rejects_unsatisfiable_path_filter() {
+        // Built directly: deserializing would canonicalize the value to
+        // `/no-leading-slash`, which `validate` accepts.
+        let filter = AdvertiserUrlFilter {
+            host: "foo.com".to_owned(),
+            paths: Some(vec![PathFilter {
+                value: "no-leading-slash".to_owned(),
+                matching: PathMatching::Glob,
+                glob: None,
+                regex: None,
+            }]),
+            ..Default::default()
+        };
+        let settings = AdmAdvertiserSettings {
+            adm_advertisers: HashMap::from([(
+                "test-adv".to_owned(),
+                HashMap::from([("US".to_owned(), vec![filter])]),
+            )]),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    pub fn test_validate_accepts_well_formed_settings() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [{"host": "foo.com", "paths": [{"value": "/ca/", "matching": "prefix"}]}]
+        }}}"#;
+        let settings: AdmAdvertiserSettings = serde_json::from_str(adm_settings).unwrap();
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    pub fn test_glob_match() {
+        assert!(glob_match(b"/products/*/buy", b"/products/widget/buy"));
+        assert!(!glob_match(
+            b"/products/*/buy",
+            b"/products/widget/extra/buy"
+        ));
+        assert!(glob_match(
+            b"/products/**/buy",
+            b"/products/widget/extra/buy"
+        ));
+        assert!(glob_match(b"/products/?oo", b"/products/foo"));
+        assert!(!glob_match(b"/products/?oo", b"/products/fooo"));
+        assert!(!glob_match(b"/products/*/buy", b"/other/widget/buy"));
+    }
+
+    #[test]
+    pub fn test_canonicalize_path() {
+        assert_eq!(canonicalize_path("/ca/"), "/ca/");
+        assert_eq!(canonicalize_path("/%63a/"), "/ca/");
+        assert_eq!(canonicalize_path("/./ca/"), "/ca/");
+        assert_eq!(canonicalize_path("//ca/"), "/ca/");
+        assert_eq!(canonicalize_path("/foo/../ca/"), "/ca/");
+        assert_eq!(canonicalize_path("/"), "/");
+        assert_eq!(canonicalize_path(""), "/");
+        // A reserved byte's percent-encoding is left alone -- decoding it
+        // would turn one path segment into two.
+        assert_eq!(canonicalize_path("/ca%2f"), "/ca%2f");
+    }
+
+    #[test]
+    pub fn test_valid_glob_path_filters() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [
+                {
+                    "host": "foo.com",
+                    "paths": [
+                        {
+                            "value": "/products/*/buy",
+                            "matching": "glob"
+                        }
+                    ]
+                }
+            ]
+        }}}"#;
+        let result: Result<AdmAdvertiserSettings, _> = serde_json::from_str(adm_settings);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    pub fn test_valid_regex_path_filter() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [
+                {
+                    "host": "foo.com",
+                    "paths": [
+                        {
+                            "value": "^/[a-z]{2}/campaign-[0-9]+/$",
+                            "matching": "regex"
+                        }
+                    ]
+                }
+            ]
+        }}}"#;
+        let settings: AdmAdvertiserSettings = serde_json::from_str(adm_settings).unwrap();
+        let rule = &settings.adm_advertisers["test-adv"]["US"][0].paths.as_ref().unwrap()[0];
+        assert!(rule.regex.as_ref().unwrap().is_match("/ca/campaign-42/"));
+        assert!(!rule.regex.as_ref().unwrap().is_match("/ca/campaign-42/extra"));
+    }
+
+    #[test]
+    pub fn test_invalid_regex_path_filter_rejected_at_load() {
+        let adm_settings = r#"{"adm_advertisers":{"test-adv": {
+            "US": [{"host": "foo.com", "paths": [{"value": "(unclosed", "matching": "regex"}]}]
+        }}}"#;
+        assert!(serde_json::from_str::<AdmAdvertiserSettings>(adm_settings).is_err());
+    }
+
+    #[test]
+    pub fn test_regex_path_filter_length_capped() {
+        let oversized = "a".repeat(MAX_PATH_REGEX_LEN + 1);
+        let adm_settings = format!(
+            r#"{{"adm_advertisers":{{"test-adv": {{
+            "US": [{{"host": "foo.com", "paths": [{{"value": {:?}, "matching": "regex"}}]}}]
+        }}}}}}"#,
+            oversized
+        );
+        assert!(serde_json::from_str::<AdmAdvertiserSettings>(&adm_settings).is_err());
+    }
+
     #[test]
     pub fn test_invalid_path_filters() {
         let adm_settings = r#"{"test-adv": {
@@ -619,4 +2085,253 @@ mod tests {
         }}"#;
         assert!(serde_json::from_str::<AdmAdvertiserSettings>(adm_settings).is_err());
     }
+
+    #[test]
+    pub fn test_proxy_config_default_is_none() {
+        assert!(matches!(ProxyConfig::default(), ProxyConfig::None));
+    }
+
+    #[test]
+    pub fn test_proxy_config_global() {
+        let proxy: ProxyConfig =
+            serde_json::from_str(r#"{"global": {"url": "http://proxy.example:3128"}}"#).unwrap();
+        match proxy {
+            ProxyConfig::Global { url } => assert_eq!(url, "http://proxy.example:3128"),
+            other => panic!("expected ProxyConfig::Global, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_proxy_config_by_domain() {
+        let adm_proxy = r#"{"by_domain": [
+            {"for_host": "foo.com", "url": "http://foo-proxy.example:3128"},
+            {"for_host": "*.bar.com", "url": "http://bar-proxy.example:3128"}
+        ]}"#;
+        let proxy: ProxyConfig = serde_json::from_str(adm_proxy).unwrap();
+        match proxy {
+            ProxyConfig::ByDomain(domains) => {
+                assert_eq!(domains.len(), 2);
+                assert!(matches!(domains[0].host_matching, HostMatching::Exact(_)));
+                assert!(matches!(domains[1].host_matching, HostMatching::Suffix(_)));
+            }
+            other => panic!("expected ProxyConfig::ByDomain, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_settings_source_file() {
+        let path = std::env::temp_dir().join("contile_test_settings_source.json");
+        std::fs::write(&path, r#"{"adm_advertisers": {}}"#).unwrap();
+        let url = url::Url::parse(&format!("file://{}", path.display())).unwrap();
+
+        let source = settings_source(&Settings::default(), &url).await.unwrap();
+        let contents = source.download().await.unwrap();
+        assert_eq!(contents, br#"{"adm_advertisers": {}}"#);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_settings_source_unsupported_scheme() {
+        let url = url::Url::parse("https://example.com/settings.json").unwrap();
+        assert!(settings_source(&Settings::default(), &url).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_settings_source_fetch_if_modified() {
+        let path = std::env::temp_dir().join("contile_test_fetch_if_modified.json");
+        std::fs::write(&path, r#"{"adm_advertisers": {}}"#).unwrap();
+        let url = url::Url::parse(&format!("file://{}", path.display())).unwrap();
+        let source = settings_source(&Settings::default(), &url).await.unwrap();
+
+        // Never fetched before: always returns the contents.
+        let (contents, _modified, fingerprint) =
+            source.fetch_if_modified(None).await.unwrap().unwrap();
+        assert_eq!(contents, br#"{"adm_advertisers": {}}"#);
+
+        // Not modified since: no re-download.
+        assert!(source
+            .fetch_if_modified(Some(&fingerprint))
+            .await
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_is_remote_source_recognizes_s3_and_file() {
+        let mut settings = Settings {
+            adm_endpoint_url: "http://localhost:8080".to_owned(),
+            adm_sub1: Some("test".to_owned()),
+            adm_partner_id: Some("test".to_owned()),
+            ..Default::default()
+        };
+
+        settings.adm_settings = "s3://bucket/path/settings.json".to_owned();
+        let filter = HandlerResult::<AdmFilter>::from(&mut settings).unwrap();
+        assert!(filter.source_url.is_some());
+        assert!(filter.last_updated.is_some());
+        assert!(filter.is_cloud());
+
+        settings.adm_settings = "file:///tmp/settings.json".to_owned();
+        let filter = HandlerResult::<AdmFilter>::from(&mut settings).unwrap();
+        assert!(filter.source_url.is_some());
+        assert!(filter.is_cloud());
+    }
+
+    #[test]
+    fn test_parse_document_yaml_and_json() {
+        let json: AdmAdvertiserSettings =
+            parse_document(r#"{"adm_advertisers": {}}"#, "settings.json").unwrap();
+        assert!(json.adm_advertisers.is_empty());
+
+        let yaml: AdmAdvertiserSettings =
+            parse_document("adm_advertisers: {}\n", "settings.yaml").unwrap();
+        assert!(yaml.adm_advertisers.is_empty());
+
+        // No recognized extension: sniff from content.
+        let sniffed_yaml: AdmAdvertiserSettings =
+            parse_document("adm_advertisers: {}\n", "").unwrap();
+        assert!(sniffed_yaml.adm_advertisers.is_empty());
+    }
+
+    #[test]
+    fn test_merge_adm_defaults() {
+        let base: AdmDefaults = serde_json::from_str(
+            r#"{"position": 1, "include_regions": ["US"], "ignore_dmas": [1, 2]}"#,
+        )
+        .unwrap();
+        // Override's non-empty/Some fields win; empty/None leave the base.
+        let over: AdmDefaults =
+            serde_json::from_str(r#"{"position": 2, "include_regions": []}"#).unwrap();
+
+        let merged = base.merge(over);
+        assert_eq!(merged.position, Some(2));
+        assert_eq!(merged.include_regions, vec!["US".to_owned()]);
+        assert_eq!(merged.ignore_dmas, Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_adm_defaults_default_has_historical_click_params() {
+        // A deployment with no `adm_defaults` configured at all must keep
+        // the historical hard-coded click_url query param behavior.
+        let defaults = AdmDefaults::default();
+        assert_eq!(
+            defaults.click_req_params,
+            vec!["ci", "ctag", "key", "version"]
+        );
+        assert_eq!(defaults.click_opt_params, vec!["click-status"]);
+        assert_eq!(defaults.rejection_actions, FilterActions::default());
+    }
+
+    #[test]
+    fn test_merge_adm_defaults_rejection_actions() {
+        let base: AdmDefaults =
+            serde_json::from_str(r#"{"rejection_actions": {"invalid_click": "silent"}}"#).unwrap();
+        assert_eq!(
+            base.rejection_actions.invalid_click,
+            RejectionAction::Silent
+        );
+        // Untouched reasons keep reporting to Sentry by default.
+        assert_eq!(
+            base.rejection_actions.invalid_region,
+            RejectionAction::Report
+        );
+
+        let over: AdmDefaults =
+            serde_json::from_str(r#"{"rejection_actions": {"invalid_region": "metric"}}"#).unwrap();
+        let merged = base.merge(over);
+        // Override's explicitly-set reason wins...
+        assert_eq!(
+            merged.rejection_actions.invalid_region,
+            RejectionAction::Metric
+        );
+        // ...and the base's reason survives since override left it unset.
+        assert_eq!(
+            merged.rejection_actions.invalid_click,
+            RejectionAction::Silent
+        );
+    }
+
+    #[test]
+    fn test_merge_adm_defaults_allow_ip_hosts() {
+        // Rejected (the default) unless a document explicitly opts in.
+        assert!(!AdmDefaults::default().allow_ip_hosts);
+
+        let base: AdmDefaults = serde_json::from_str(r#"{"allow_ip_hosts": true}"#).unwrap();
+        let over = AdmDefaults::default();
+        // A base opt-in isn't silently undone by an override that never
+        // mentions it.
+        assert!(base.merge(over).allow_ip_hosts);
+    }
+
+    #[test]
+    fn test_merge_adm_advertiser_settings_replaces_and_deletes() {
+        let base: AdmAdvertiserSettings = serde_json::from_str(
+            r#"{"adm_advertisers": {
+                "keep": {"US": [{"host": "keep.com"}]},
+                "remove": {"US": [{"host": "remove.com"}]}
+            }}"#,
+        )
+        .unwrap();
+        let over: AdmAdvertiserSettings = serde_json::from_str(
+            r#"{"adm_advertisers": {
+                "keep": {"US": [{"host": "keep-overridden.com"}]},
+                "remove": {"deleted": true}
+            }}"#,
+        )
+        .unwrap();
+
+        let merged = base.merge(over);
+        assert!(!merged.adm_advertisers.contains_key("remove"));
+        assert_eq!(
+            merged.adm_advertisers["keep"]["US"][0].host,
+            "keep-overridden.com"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_host_denylist_and_allowlist() {
+        let settings: AdmAdvertiserSettings = serde_json::from_str(
+            r#"{"host_denylist": ["evil.example"], "host_allowlist": [{"host": "acme.biz", "include_subdomains": true}]}"#,
+        )
+        .unwrap();
+        assert_eq!(settings.host_denylist, vec![HostFilter::new("evil.example")]);
+        assert_eq!(
+            settings.host_allowlist,
+            vec![HostFilter {
+                labels: break_hosts("acme.biz".to_owned()),
+                include_subdomains: Some(true),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reject_overbroad_blocklist_hosts() {
+        let mut settings = AdmAdvertiserSettings {
+            host_denylist: vec![HostFilter {
+                labels: break_hosts("co.uk".to_owned()),
+                include_subdomains: Some(true),
+            }],
+            ..Default::default()
+        };
+        settings.reject_overbroad_hosts();
+        assert!(settings.host_denylist.is_empty());
+    }
+
+    #[test]
+    fn test_merge_adm_advertiser_settings_blocklist() {
+        let base: AdmAdvertiserSettings = serde_json::from_str(
+            r#"{"host_denylist": ["evil.example"]}"#,
+        )
+        .unwrap();
+        let over: AdmAdvertiserSettings =
+            serde_json::from_str(r#"{"host_allowlist": ["acme.biz"]}"#).unwrap();
+
+        let merged = base.merge(over);
+        // The override document's empty `host_denylist` doesn't clobber the base's.
+        assert_eq!(merged.host_denylist, vec![HostFilter::new("evil.example")]);
+        assert_eq!(merged.host_allowlist, vec![HostFilter::new("acme.biz")]);
+    }
 }
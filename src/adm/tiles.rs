@@ -1,7 +1,12 @@
 use std::{fmt::Debug, fs::File, io::BufReader, path::Path, time::Duration};
 
 use actix_http::http::header::{HeaderMap, HeaderValue};
+use actix_web::rt;
 use actix_web_location::Location;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use opentelemetry::trace::{FutureExt, SpanKind, Tracer};
+use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -12,7 +17,8 @@ use crate::{
     server::ServerState,
     settings::Settings,
     tags::Tags,
-    web::DeviceInfo,
+    tracing::TRACER_NAME,
+    web::{middleware::sentry as l_sentry, DeviceInfo},
 };
 
 /// The payload provided by ADM
@@ -59,6 +65,318 @@ impl AdmTileResponse {
     }
 }
 
+/// ADM upstream revalidation metadata captured from a response's `ETag`/
+/// `Last-Modified`, echoed back as `If-None-Match`/`If-Modified-Since` on
+/// the next fetch for the same query so an unchanged response costs ADM a
+/// `304` instead of a full re-serve.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AdmRevalidation {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl AdmRevalidation {
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Cache lifetime implied by ADM's `Cache-Control` response header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AdmCacheLifetime {
+    /// No (or unparseable) `Cache-Control`: fall back to the caller's own
+    /// default (`Settings::tiles_ttl`).
+    Unspecified,
+    /// `no-store`/`no-cache`: don't treat this response as cacheable at all.
+    Uncacheable,
+    /// `max-age=N`
+    MaxAge(Duration),
+}
+
+impl AdmCacheLifetime {
+    fn parse(headers: &reqwest::header::HeaderMap) -> Self {
+        let Some(value) = headers
+            .get(reqwest::header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return Self::Unspecified;
+        };
+        let mut max_age = None;
+        for directive in value.split(',').map(str::trim) {
+            if directive.eq_ignore_ascii_case("no-store")
+                || directive.eq_ignore_ascii_case("no-cache")
+            {
+                return Self::Uncacheable;
+            }
+            if let Some(secs) = directive
+                .strip_prefix("max-age=")
+                .and_then(|secs| secs.trim().parse().ok())
+            {
+                max_age = Some(Duration::from_secs(secs));
+            }
+        }
+        max_age.map(Self::MaxAge).unwrap_or(Self::Unspecified)
+    }
+
+    /// Resolve to an actual TTL, falling back to `default_ttl` when ADM
+    /// didn't send a `Cache-Control` we understand.
+    pub(crate) fn resolve(self, default_ttl: Duration) -> Duration {
+        match self {
+            Self::MaxAge(ttl) => ttl,
+            Self::Uncacheable => Duration::ZERO,
+            Self::Unspecified => default_ttl,
+        }
+    }
+}
+
+/// Outcome of a (possibly conditional) request to ADM.
+pub enum AdmFetchOutcome {
+    /// ADM confirmed via `304 Not Modified` that the previously-fetched
+    /// tiles are still current.
+    NotModified {
+        cache_lifetime: AdmCacheLifetime,
+        /// Fresh revalidation info, if ADM resent `ETag`/`Last-Modified`
+        /// alongside the `304`; `None` to keep using whatever was passed in
+        /// as `revalidate`.
+        revalidation: Option<AdmRevalidation>,
+    },
+    /// ADM returned a fresh tile listing.
+    Modified {
+        response: AdmTileResponse,
+        cache_lifetime: AdmCacheLifetime,
+        /// `None` if the response was uncacheable, or carried neither an
+        /// `ETag` nor a `Last-Modified` to revalidate against next time.
+        revalidation: Option<AdmRevalidation>,
+    },
+}
+
+/// Fetches the raw tile listing from ADM (or an equivalent source, e.g. a
+/// test double).
+///
+/// Abstracting this behind a trait keeps `get_tiles`'s filtering/position/
+/// error-mapping logic unit-testable without going over the network (or
+/// relying on the `test_mode`/`fake-response` file-based hack, which remains
+/// for manual/QA testing against a real deployment).
+#[async_trait(?Send)]
+pub trait AdmRequester: Debug + Send + Sync {
+    /// `revalidate`, if given, is sent as `If-None-Match`/`If-Modified-Since`
+    /// so ADM can reply `304 Not Modified` instead of resending the same
+    /// tiles.
+    ///
+    /// `tags` receives debugging `extra` (e.g. the response status) for the
+    /// 3AM page, without making it searchable (see [Tags::add_extra]).
+    async fn fetch(
+        &self,
+        url: &str,
+        ua: &str,
+        revalidate: Option<&AdmRevalidation>,
+        tags: &mut Tags,
+        metrics: &Metrics,
+    ) -> HandlerResult<AdmFetchOutcome>;
+}
+
+/// 5xx statuses worth retrying -- a transient upstream/gateway hiccup, as
+/// opposed to e.g. a 500 that'll just recur until ADM's code changes.
+const ADM_RETRYABLE_STATUSES: [reqwest::StatusCode; 3] = [
+    reqwest::StatusCode::BAD_GATEWAY,
+    reqwest::StatusCode::SERVICE_UNAVAILABLE,
+    reqwest::StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// Ceiling for the exponential backoff delay between ADM retries, regardless
+/// of how many attempts have elapsed.
+const ADM_RETRY_BACKOFF_CAP_MS: u64 = 5_000;
+
+/// Production [AdmRequester] backed by a [reqwest::Client]
+#[derive(Clone, Debug)]
+pub struct ReqwestAdmRequester {
+    client: reqwest::Client,
+    timeout: Duration,
+    /// `Settings::adm_max_retries`
+    max_retries: u32,
+    /// `Settings::adm_retry_base_ms`
+    retry_base_ms: u64,
+}
+
+impl ReqwestAdmRequester {
+    pub fn new(
+        client: reqwest::Client,
+        timeout: Duration,
+        max_retries: u32,
+        retry_base_ms: u64,
+    ) -> Self {
+        Self {
+            client,
+            timeout,
+            max_retries,
+            retry_base_ms,
+        }
+    }
+
+    async fn attempt(
+        &self,
+        url: &str,
+        ua: &str,
+        revalidate: Option<&AdmRevalidation>,
+    ) -> reqwest::Result<reqwest::Response> {
+        // A child span of the request's server span, so ADM latency can be
+        // attributed separately from filtering/position/image-store work.
+        let tracer = opentelemetry::global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder("adm.fetch")
+            .with_kind(SpanKind::Client)
+            .start(&tracer);
+        let cx = opentelemetry::Context::current_with_span(span);
+
+        async move {
+            let mut req = self
+                .client
+                .get(url)
+                .header("User-Agent", ua)
+                .timeout(self.timeout);
+            if let Some(revalidate) = revalidate {
+                if let Some(etag) = &revalidate.etag {
+                    req = req.header(reqwest::header::IF_NONE_MATCH, etag.clone());
+                }
+                if let Some(last_modified) = &revalidate.last_modified {
+                    req = req.header(reqwest::header::IF_MODIFIED_SINCE, last_modified.clone());
+                }
+            }
+            req.send().await
+        }
+        .with_context(cx)
+        .await
+    }
+
+    /// Exponential backoff with full jitter: a delay drawn uniformly from
+    /// `[0, min(cap, base * 2^attempt)]`, so concurrent requests retrying
+    /// after the same blip don't all land on ADM at once.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .retry_base_ms
+            .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX));
+        let cap_ms = backoff.min(ADM_RETRY_BACKOFF_CAP_MS);
+        Duration::from_millis(thread_rng().gen_range(0..=cap_ms))
+    }
+}
+
+/// Parse a `Retry-After` header value (we only understand the delay-seconds
+/// form ADM is expected to send, not the HTTP-date form).
+fn retry_after_delay(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())?;
+    Some(Duration::from_secs(seconds))
+}
+
+#[async_trait(?Send)]
+impl AdmRequester for ReqwestAdmRequester {
+    async fn fetch(
+        &self,
+        url: &str,
+        ua: &str,
+        revalidate: Option<&AdmRevalidation>,
+        tags: &mut Tags,
+        metrics: &Metrics,
+    ) -> HandlerResult<AdmFetchOutcome> {
+        let mut attempt: u32 = 0;
+        let response = loop {
+            let response = self.attempt(url, ua, revalidate).await.map_err(|e| {
+                // ADM servers are down, or improperly configured
+                let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
+                err.tags.add_extra("error", &e.to_string());
+                err
+            });
+            // A non-2xx/304 status is classified directly (it hasn't been
+            // turned into a `HandlerErrorKind` yet at this point); a
+            // connection-level failure was already mapped to
+            // `AdmServerError` above, so `HandlerErrorKind::is_retryable`
+            // classifies it the same way it would any other caller's error.
+            let retryable = match &response {
+                Ok(response) => ADM_RETRYABLE_STATUSES.contains(&response.status()),
+                Err(e) => e.kind().is_retryable(),
+            };
+            if !retryable || attempt >= self.max_retries {
+                if retryable {
+                    warn!("adm::fetch giving up after {} attempts", attempt + 1);
+                    metrics.incr_with_tags("tiles.adm.exhausted", Some(tags));
+                }
+                // Record the attempts made so far (if this does turn out to
+                // be an error) so it's traceable end-to-end without
+                // grepping logs -- see `error::ErrorMeta::retry_count`.
+                break response
+                    .map_err(|mut e| {
+                        e.meta.retry_count = attempt;
+                        e
+                    })?;
+            }
+            let delay = match &response {
+                Ok(response) => {
+                    retry_after_delay(response).unwrap_or_else(|| self.backoff_delay(attempt))
+                }
+                Err(_) => self.backoff_delay(attempt),
+            };
+            warn!(
+                "adm::fetch retrying after {:?} (attempt {})",
+                delay,
+                attempt + 1
+            );
+            metrics.incr_with_tags("tiles.adm.retry", Some(tags));
+            attempt += 1;
+            rt::time::sleep(delay).await;
+        };
+
+        tags.add_extra("adm.status", response.status().as_str());
+
+        let cache_lifetime = AdmCacheLifetime::parse(response.headers());
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let last_modified = response
+            .headers()
+            .get(reqwest::header::LAST_MODIFIED)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let revalidation = AdmRevalidation {
+            etag,
+            last_modified,
+        };
+        let revalidation = (!revalidation.is_empty()
+            && !matches!(cache_lifetime, AdmCacheLifetime::Uncacheable))
+        .then_some(revalidation);
+
+        if revalidate.is_some() && response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(AdmFetchOutcome::NotModified {
+                cache_lifetime,
+                revalidation,
+            });
+        }
+
+        let response = response.error_for_status().map_err(|e| {
+            // ADM answered with a (non-retryable, or retried out) error status
+            let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
+            err.tags.add_extra("error", &e.to_string());
+            err
+        })?;
+        let response: AdmTileResponse = response.json().await.map_err(|e| {
+            // ADM servers are not returning correct information
+            HandlerError::from(HandlerErrorKind::BadAdmResponse(format!(
+                "ADM provided invalid response: {:?}",
+                e
+            )))
+        })?;
+        Ok(AdmFetchOutcome::Modified {
+            response,
+            cache_lifetime,
+            revalidation,
+        })
+    }
+}
+
 /// The individual tile data provided by ADM
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AdmTile {
@@ -121,7 +439,30 @@ pub fn filtered_dma(exclude: &Option<Vec<u16>>, dma: &u16) -> String {
     }
 }
 
+/// Outcome of [get_tiles]: either a fresh `TileResponse`, or confirmation
+/// that ADM's previously-fetched tiles are still current (see
+/// [AdmFetchOutcome::NotModified]).
+pub enum GetTilesOutcome {
+    NotModified {
+        /// How long the caller should consider its existing cached
+        /// `TileResponse` fresh for, per ADM's `Cache-Control`.
+        ttl: Duration,
+        adm_revalidation: Option<AdmRevalidation>,
+    },
+    Modified {
+        response: TileResponse,
+        /// How long the caller should consider `response` fresh for, per
+        /// ADM's `Cache-Control` (falling back to `Settings::tiles_ttl`).
+        ttl: Duration,
+        adm_revalidation: Option<AdmRevalidation>,
+    },
+}
+
 /// Main handler for the User Agent HTTP request
+///
+/// `revalidate`, if given, is sent to ADM as a conditional request; a `304`
+/// reply is surfaced as `GetTilesOutcome::NotModified` instead of refetching
+/// and re-filtering tiles that haven't actually changed.
 pub async fn get_tiles(
     state: &ServerState,
     location: &Location,
@@ -129,14 +470,29 @@ pub async fn get_tiles(
     tags: &mut Tags,
     metrics: &Metrics,
     headers: Option<&HeaderMap>,
-) -> Result<TileResponse, HandlerError> {
+    revalidate: Option<&AdmRevalidation>,
+) -> Result<GetTilesOutcome, HandlerError> {
     let settings = &state.settings;
     let image_store = &state.img_store;
+    tags.add_location(location);
+    let partner = crate::adm::settings::AdmPse::appropriate_from_settings(&device_info, settings);
+    tags.add_tag("adm.partner", &partner.partner_id);
+    // Guaranteed `Some` by the startup check in `Server::with_settings`, but
+    // we still fail gracefully here rather than relying solely on that
+    // invariant holding.
+    let partner_id = settings
+        .adm_partner_id
+        .as_deref()
+        .ok_or_else(|| HandlerErrorKind::InvalidSettings("Missing adm_partner_id".to_owned()))?;
+    let sub1 = settings
+        .adm_sub1
+        .as_deref()
+        .ok_or_else(|| HandlerErrorKind::InvalidSettings("Missing adm_sub1".to_owned()))?;
     let adm_url = Url::parse_with_params(
         &state.adm_endpoint_url,
         &[
-            ("partner", settings.adm_partner_id.clone().unwrap().as_str()),
-            ("sub1", settings.adm_sub1.clone().unwrap().as_str()),
+            ("partner", partner_id),
+            ("sub1", sub1),
             ("sub2", "newtab"),
             (
                 "country-code",
@@ -163,38 +519,57 @@ pub async fn get_tiles(
     let adm_url = adm_url.as_str();
 
     info!("adm::get_tiles GET {}", adm_url);
+    tags.add_extra("adm.url", adm_url);
     metrics.incr("tiles.adm.request");
-    let response: AdmTileResponse = if state.settings.test_mode {
+    let outcome: AdmFetchOutcome = if state.settings.test_mode {
         let default = HeaderValue::from_str(DEFAULT).unwrap();
         let test_response = headers
             .unwrap_or(&HeaderMap::new())
             .get("fake-response")
             .unwrap_or(&default)
             .to_str()
-            .unwrap()
+            .map_err(|e| {
+                HandlerErrorKind::InvalidUpstreamData(format!(
+                    "Invalid fake-response header: {:?}",
+                    e
+                ))
+            })?
             .to_owned();
         trace!("Getting fake response: {:?}", &test_response);
-        AdmTileResponse::fake_response(&state.settings, test_response)?
+        AdmFetchOutcome::Modified {
+            response: AdmTileResponse::fake_response(&state.settings, test_response)?,
+            cache_lifetime: AdmCacheLifetime::Unspecified,
+            revalidation: None,
+        }
     } else {
         state
-            .reqwest_client
-            .get(adm_url)
-            .timeout(Duration::from_secs(settings.adm_timeout))
-            .send()
-            .await
-            .map_err(|e| {
-                // ADM servers are down, or improperly configured
-                let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
-                err.tags.add_extra("error", &e.to_string());
-                err
-            })?
-            .error_for_status()?
-            .json()
-            .await
-            .map_err(|e| {
-                // ADM servers are not returning correct information
-                HandlerErrorKind::BadAdmResponse(format!("ADM provided invalid response: {:?}", e))
-            })?
+            .adm_requester
+            .fetch(
+                adm_url,
+                crate::server::REQWEST_USER_AGENT,
+                revalidate,
+                tags,
+                metrics,
+            )
+            .await?
+    };
+    let (response, cache_lifetime, adm_revalidation) = match outcome {
+        AdmFetchOutcome::NotModified {
+            cache_lifetime,
+            revalidation,
+        } => {
+            trace!("adm::get_tiles not modified {}", adm_url);
+            metrics.incr("tiles.adm.not_modified");
+            return Ok(GetTilesOutcome::NotModified {
+                ttl: cache_lifetime.resolve(settings.tiles_ttl_with_jitter()),
+                adm_revalidation: revalidation.or_else(|| revalidate.cloned()),
+            });
+        }
+        AdmFetchOutcome::Modified {
+            response,
+            cache_lifetime,
+            revalidation,
+        } => (response, cache_lifetime, revalidation),
     };
     if response.tiles.is_empty() {
         warn!("adm::get_tiles empty response {}", adm_url);
@@ -216,20 +591,118 @@ pub async fn get_tiles(
         warn!("adm::get_tiles no valid tiles {}", adm_url);
         metrics.incr_with_tags("filter.adm.all_filtered", Some(tags));
     }
-    let mut tiles: Vec<Tile> = Vec::new();
-    for mut tile in filtered {
-        if let Some(storage) = image_store {
-            // we should have already proven the image_url in `filter_and_process`
-            // we need to validate the image, store the image for eventual CDN retrieval,
-            // and get the metrics of the image.
-            let result = storage.store(&tile.image_url.parse().unwrap()).await?;
-            tile.image_url = result.url.to_string();
-            // Since height should equal width, using either value here works.
-            tile.image_size = Some(result.image_metrics.width);
+    let tiles = if let Some(storage) = image_store {
+        // Fetch/store each tile's image concurrently (bounded, so we don't
+        // open unbounded connections to the image host), rather than
+        // awaiting them one at a time. `buffer_unordered` completes futures
+        // out of order, so each is tagged with its original index and
+        // restored to response order afterward.
+        //
+        // A bad image shouldn't sink the whole response: drop just this
+        // tile and report it, the same way the other `filter_and_process`
+        // checks do.
+        let tags = &*tags;
+        let concurrency = (settings.adm_image_concurrency as usize).max(1);
+        let mut stored: Vec<(usize, Option<Tile>)> = stream::iter(filtered.into_iter().enumerate())
+            .map(|(order, mut tile)| async move {
+                // we should have already proven the image_url in `filter_and_process`
+                // we need to validate the image, store the image for eventual CDN retrieval,
+                // and get the metrics of the image.
+                let image_url = match tile.image_url.parse() {
+                    Ok(image_url) => image_url,
+                    Err(e) => {
+                        warn!("Rejecting tile: invalid image_url {:?}", &e);
+                        metrics.incr_with_tags("filter.adm.err.invalid_image_url", Some(tags));
+                        return (order, None);
+                    }
+                };
+                match storage.store(&image_url).await {
+                    Ok(result) => {
+                        tile.image_url = result.url.to_string();
+                        // Since height should equal width, using either value here works.
+                        tile.image_size = Some(result.image_metrics.width);
+                        (order, Some(tile))
+                    }
+                    Err(e) => {
+                        warn!("Rejecting tile: bad image store {:?}", &e);
+                        metrics.incr_with_tags("filter.adm.err.invalid_image_store", Some(tags));
+                        l_sentry::report(&e, &e.tags);
+                        (order, None)
+                    }
+                }
+            })
+            .buffer_unordered(concurrency)
+            .collect()
+            .await;
+        stored.sort_by_key(|(order, _)| *order);
+        stored.into_iter().filter_map(|(_, tile)| tile).collect()
+    } else {
+        filtered
+    };
+    Ok(GetTilesOutcome::Modified {
+        response: TileResponse { tiles },
+        ttl: cache_lifetime.resolve(settings.tiles_ttl_with_jitter()),
+        adm_revalidation,
+    })
+}
+
+/// Test double for [AdmRequester]: always returns the canned `response` it
+/// was built with, regardless of `url`/`ua`.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct MockAdmRequester {
+    response: Result<String, String>,
+}
+
+#[cfg(test)]
+impl MockAdmRequester {
+    pub(crate) fn with_tiles(tiles: Vec<AdmTile>) -> Self {
+        Self {
+            response: Ok(serde_json::to_string(&AdmTileResponse { tiles }).unwrap()),
+        }
+    }
+
+    /// `fetch` returns a [HandlerErrorKind::AdmServerError] carrying `error`
+    /// as its `error` extra tag.
+    pub(crate) fn with_error(error: &str) -> Self {
+        Self {
+            response: Err(error.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait(?Send)]
+impl AdmRequester for MockAdmRequester {
+    async fn fetch(
+        &self,
+        _url: &str,
+        _ua: &str,
+        _revalidate: Option<&AdmRevalidation>,
+        _tags: &mut Tags,
+        _metrics: &Metrics,
+    ) -> HandlerResult<AdmFetchOutcome> {
+        match &self.response {
+            Ok(body) => serde_json::from_str(body)
+                .map(|response| AdmFetchOutcome::Modified {
+                    response,
+                    cache_lifetime: AdmCacheLifetime::Unspecified,
+                    revalidation: None,
+                })
+                .map_err(|e| {
+                    HandlerErrorKind::BadAdmResponse(format!(
+                        "ADM provided invalid response: {:?}",
+                        e
+                    ))
+                    .into()
+                }),
+            Err(msg) => {
+                let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
+                err.tags.add_extra("error", msg);
+                Err(err)
+            }
         }
-        tiles.push(tile);
     }
-    Ok(TileResponse { tiles })
 }
 
 #[cfg(test)]
@@ -237,6 +710,90 @@ mod test {
     use super::*;
     use crate::settings::test_settings;
 
+    fn test_tile(id: u64) -> AdmTile {
+        AdmTile {
+            id,
+            name: "test".to_owned(),
+            advertiser_url: "https://example.com".to_owned(),
+            click_url: "https://example.com".to_owned(),
+            image_url: "https://example.com/img.jpg".to_owned(),
+            impression_url: "https://example.com".to_owned(),
+            position: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn mock_requester_returns_configured_tiles() {
+        let requester = MockAdmRequester::with_tiles(vec![test_tile(1), test_tile(2)]);
+        let mut tags = Tags::default();
+        let metrics = Metrics::noop();
+        let outcome = requester
+            .fetch("https://example.com", "test-ua", None, &mut tags, &metrics)
+            .await
+            .unwrap();
+        let response = match outcome {
+            AdmFetchOutcome::Modified { response, .. } => response,
+            AdmFetchOutcome::NotModified { .. } => panic!("expected Modified"),
+        };
+        assert_eq!(response.tiles.len(), 2);
+        assert_eq!(response.tiles[0].id, 1);
+    }
+
+    #[actix_web::test]
+    async fn mock_requester_returns_configured_error() {
+        let requester = MockAdmRequester::with_error("boom");
+        let mut tags = Tags::default();
+        let metrics = Metrics::noop();
+        let result = requester
+            .fetch("https://example.com", "test-ua", None, &mut tags, &metrics)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_adm_cache_lifetime_parse() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(
+            AdmCacheLifetime::parse(&headers),
+            AdmCacheLifetime::Unspecified
+        );
+
+        headers.insert(
+            reqwest::header::CACHE_CONTROL,
+            "max-age=300".parse().unwrap(),
+        );
+        assert_eq!(
+            AdmCacheLifetime::parse(&headers),
+            AdmCacheLifetime::MaxAge(Duration::from_secs(300))
+        );
+
+        headers.insert(
+            reqwest::header::CACHE_CONTROL,
+            "no-store, max-age=300".parse().unwrap(),
+        );
+        assert_eq!(
+            AdmCacheLifetime::parse(&headers),
+            AdmCacheLifetime::Uncacheable
+        );
+    }
+
+    #[test]
+    fn test_adm_cache_lifetime_resolve() {
+        let default_ttl = Duration::from_secs(900);
+        assert_eq!(
+            AdmCacheLifetime::Unspecified.resolve(default_ttl),
+            default_ttl
+        );
+        assert_eq!(
+            AdmCacheLifetime::Uncacheable.resolve(default_ttl),
+            Duration::ZERO
+        );
+        assert_eq!(
+            AdmCacheLifetime::MaxAge(Duration::from_secs(60)).resolve(default_ttl),
+            Duration::from_secs(60)
+        );
+    }
+
     #[test]
     fn test_filtered_dma() {
         let settings = test_settings();
@@ -1,5 +1,6 @@
 //! Common errors
 use backtrace::Backtrace;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 use std::result;
@@ -18,12 +19,34 @@ use crate::tags::Tags;
 /// The standard Result type for Contile (returns Error = [`HandlerError`])
 pub type HandlerResult<T> = result::Result<T, HandlerError>;
 
+/// Structured metadata carried alongside a [HandlerError]'s `kind`, the way a
+/// typed upstream error sidecars its own code/message/request id rather than
+/// cramming everything into the error message string. Populated piecemeal as
+/// an error crosses boundaries (e.g. the ADM retry loop attaching
+/// `retry_count`, a handler attaching the inbound `request_id`) so it can be
+/// traced end-to-end without grepping logs -- see
+/// [HandlerError::error_response]'s truncated subset and
+/// [HandlerError::sentry_context]'s full view.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorMeta {
+    pub request_id: Option<String>,
+    /// The HTTP status an upstream partner (e.g. ADM) itself responded with,
+    /// as distinct from [HandlerErrorKind::http_status] (the status *we*
+    /// return to the client for this error).
+    pub upstream_status: Option<u16>,
+    /// How many retries were attempted before this error was finally
+    /// surfaced, see [HandlerError::record_retry].
+    pub retry_count: u32,
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
 /// The Standard Error for most of Contile
 #[derive(Debug)]
 pub struct HandlerError {
     kind: HandlerErrorKind,
     pub(crate) backtrace: Box<Backtrace>,
     pub tags: Box<Tags>,
+    pub meta: ErrorMeta,
 }
 
 /// The specific context types of HandlerError.
@@ -37,6 +60,11 @@ pub enum HandlerErrorKind {
     #[error("Internal error: {:?}", _0)]
     Internal(String),
 
+    /// A required setting was missing or unusable at startup (e.g. ADM
+    /// partner/sub1 ids left unconfigured)
+    #[error("Invalid settings: {:?}", _0)]
+    InvalidSettings(String),
+
     /// An error fetching information from ADM
     #[error("Reqwest error: {:?}", _0)]
     Reqwest(#[from] reqwest::Error),
@@ -61,6 +89,11 @@ pub enum HandlerErrorKind {
     #[error("Unexpected Advertiser: {:?}", _0)]
     UnexpectedAdvertiser(String),
 
+    /// A tile's advertiser is configured with `include_regions`, and the
+    /// request's location didn't match any of them
+    #[error("{:?} region not included for advertiser: {:?}", _1, _0)]
+    InvalidRegion(String, String),
+
     /// A tile was missing a host, or presented an unparsable one.
     #[error("Missing {} Host: {:?}", _0, _1)]
     MissingHost(&'static str, String),
@@ -81,6 +114,12 @@ pub enum HandlerErrorKind {
     #[error("Adm Cache Load Error")]
     AdmLoadError(),
 
+    /// Malformed data received from an upstream dependency (ADM, or the
+    /// `fake-response` test harness) that should be handled gracefully
+    /// rather than panicking
+    #[error("Invalid upstream data: {:?}", _0)]
+    InvalidUpstreamData(String),
+
     /// Invalid UserAgent request
     #[error("Invalid user agent")]
     InvalidUA,
@@ -100,7 +139,9 @@ impl HandlerErrorKind {
             HandlerErrorKind::BadAdmResponse(_)
             | HandlerErrorKind::InvalidHost(_, _)
             | HandlerErrorKind::UnexpectedHost(_, _)
+            | HandlerErrorKind::InvalidRegion(_, _)
             | HandlerErrorKind::BadImage(_)
+            | HandlerErrorKind::InvalidUpstreamData(_)
             | HandlerErrorKind::CloudStorage(_) => StatusCode::BAD_GATEWAY,
             &HandlerErrorKind::InvalidUA => StatusCode::FORBIDDEN,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
@@ -112,10 +153,12 @@ impl HandlerErrorKind {
         match self {
             HandlerErrorKind::General(_) => 500,
             HandlerErrorKind::Internal(_) => 510,
+            HandlerErrorKind::InvalidSettings(_) => 511,
             HandlerErrorKind::Reqwest(_) => 520,
             HandlerErrorKind::BadAdmResponse(_) => 521,
             HandlerErrorKind::AdmServerError() => 522,
             HandlerErrorKind::AdmLoadError() => 523,
+            HandlerErrorKind::InvalidUpstreamData(_) => 524,
             HandlerErrorKind::Location(_) => 530,
             HandlerErrorKind::Validation(_) => 600,
             HandlerErrorKind::InvalidHost(_, _) => 601,
@@ -123,6 +166,7 @@ impl HandlerErrorKind {
             HandlerErrorKind::MissingHost(_, _) => 603,
             HandlerErrorKind::UnexpectedAdvertiser(_) => 604,
             HandlerErrorKind::BadImage(_) => 605,
+            HandlerErrorKind::InvalidRegion(_, _) => 606,
             HandlerErrorKind::CloudStorage(_) => 620,
             HandlerErrorKind::InvalidUA => 700,
         }
@@ -145,9 +189,25 @@ impl HandlerErrorKind {
             && !matches!(self, HandlerErrorKind::Reqwest(e) if e.is_timeout() || e.is_connect())
     }
 
+    /// Whether retrying the request that produced this error stands a
+    /// chance of succeeding: a transient network blip or an upstream's own
+    /// transient failure, as opposed to e.g. bad partner data that will just
+    /// fail the same way again. Mirrors the transient/permanent split other
+    /// connection-oriented error enums draw (e.g. distinguishing
+    /// Connect/Io/Body failures from the rest).
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, HandlerErrorKind::Reqwest(e) if e.is_timeout() || e.is_connect())
+            || matches!(
+                self,
+                HandlerErrorKind::AdmServerError() | HandlerErrorKind::AdmLoadError()
+            )
+    }
+
     pub fn as_response_string(&self) -> String {
         match self {
-            HandlerErrorKind::General(_) | HandlerErrorKind::Internal(_) => self.to_string(),
+            HandlerErrorKind::General(_)
+            | HandlerErrorKind::Internal(_)
+            | HandlerErrorKind::InvalidSettings(_) => self.to_string(),
             // Not really an error
             HandlerErrorKind::Reqwest(_) => {
                 "An error occurred while trying to request data".to_string()
@@ -155,11 +215,13 @@ impl HandlerErrorKind {
             HandlerErrorKind::BadAdmResponse(_)
             | HandlerErrorKind::AdmServerError()
             | HandlerErrorKind::AdmLoadError()
+            | HandlerErrorKind::InvalidUpstreamData(_)
             | HandlerErrorKind::Validation(_)
             | HandlerErrorKind::InvalidHost(_, _)
             | HandlerErrorKind::UnexpectedHost(_, _)
             | HandlerErrorKind::MissingHost(_, _)
             | HandlerErrorKind::UnexpectedAdvertiser(_)
+            | HandlerErrorKind::InvalidRegion(_, _)
             | HandlerErrorKind::BadImage(_) => {
                 "An invalid response received from the partner".to_string()
             }
@@ -191,6 +253,43 @@ impl HandlerError {
     pub fn internal(msg: &str) -> Self {
         HandlerErrorKind::Internal(msg.to_owned()).into()
     }
+
+    /// Attach the inbound request's id to `self.meta`, chainable at the
+    /// point an error is constructed or mapped.
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.meta.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Attach the HTTP status an upstream partner itself responded with.
+    pub fn with_upstream_status(mut self, status: u16) -> Self {
+        self.meta.upstream_status = Some(status);
+        self
+    }
+
+    /// Record one more retry attempt against this error. (The ADM fetch
+    /// retry loop, `adm::tiles::ReqwestAdmRequester::fetch`, sets
+    /// `retry_count` directly once it gives up instead.)
+    pub fn record_retry(&mut self) {
+        self.meta.retry_count += 1;
+    }
+
+    /// `self.meta`'s fields as a Sentry `extra` context map -- the full
+    /// picture, as opposed to [Self::error_response]'s truncated subset.
+    pub fn sentry_context(&self) -> BTreeMap<String, serde_json::Value> {
+        let mut context = BTreeMap::new();
+        if let Some(request_id) = &self.meta.request_id {
+            context.insert("request_id".to_owned(), json!(request_id));
+        }
+        if let Some(upstream_status) = self.meta.upstream_status {
+            context.insert("upstream_status".to_owned(), json!(upstream_status));
+        }
+        if self.meta.retry_count > 0 {
+            context.insert("retry_count".to_owned(), json!(self.meta.retry_count));
+        }
+        context.extend(self.meta.extra.clone());
+        context
+    }
 }
 
 impl Error for HandlerError {
@@ -224,6 +323,7 @@ where
             kind: HandlerErrorKind::from(item),
             backtrace: Box::new(Backtrace::new()),
             tags: Box::<Tags>::default(),
+            meta: ErrorMeta::default(),
         }
     }
 }
@@ -242,14 +342,81 @@ impl fmt::Display for HandlerError {
 
 impl ResponseError for HandlerError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(json!({
+        // A truncated subset of `self.meta` -- just enough for a caller to
+        // correlate this response with server-side logs/Sentry, not the
+        // full `extra` map (see `sentry_context` for that).
+        let mut body = json!({
             "code": self.kind().http_status().as_u16(),
             "errno": self.kind().errno(),
             "error": self.kind().as_response_string(),
-        }))
+        });
+        let obj = body.as_object_mut().expect("json!({...}) is an object");
+        if let Some(request_id) = &self.meta.request_id {
+            obj.insert("request_id".to_owned(), json!(request_id));
+        }
+        if let Some(upstream_status) = self.meta.upstream_status {
+            obj.insert("upstream_status".to_owned(), json!(upstream_status));
+        }
+        if self.meta.retry_count > 0 {
+            obj.insert("retry_count".to_owned(), json!(self.meta.retry_count));
+        }
+        HttpResponse::build(self.status_code()).json(body)
     }
 
     fn status_code(&self) -> StatusCode {
         self.kind().http_status()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(HandlerErrorKind::AdmServerError().is_retryable());
+        assert!(HandlerErrorKind::AdmLoadError().is_retryable());
+        // Validation/host/advertiser/image errors can't be fixed by retrying.
+        assert!(!HandlerErrorKind::Validation("boom".to_owned()).is_retryable());
+        assert!(!HandlerErrorKind::InvalidHost("Advertiser", "bad".to_owned()).is_retryable());
+        assert!(!HandlerErrorKind::UnexpectedAdvertiser("bad".to_owned()).is_retryable());
+        assert!(!HandlerErrorKind::BadImage("bad").is_retryable());
+    }
+
+    #[test]
+    fn test_with_request_id_and_upstream_status() {
+        let err: HandlerError = HandlerErrorKind::AdmServerError().into();
+        let err = err.with_request_id("req-123").with_upstream_status(502);
+        assert_eq!(err.meta.request_id.as_deref(), Some("req-123"));
+        assert_eq!(err.meta.upstream_status, Some(502));
+        assert_eq!(err.meta.retry_count, 0);
+    }
+
+    #[test]
+    fn test_record_retry() {
+        let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
+        err.record_retry();
+        err.record_retry();
+        assert_eq!(err.meta.retry_count, 2);
+    }
+
+    #[test]
+    fn test_sentry_context_includes_meta_fields() {
+        let mut err: HandlerError = HandlerErrorKind::AdmServerError().into();
+        err = err.with_request_id("req-123").with_upstream_status(502);
+        err.record_retry();
+        let context = err.sentry_context();
+        assert_eq!(context.get("request_id"), Some(&json!("req-123")));
+        assert_eq!(context.get("upstream_status"), Some(&json!(502)));
+        assert_eq!(context.get("retry_count"), Some(&json!(1)));
+    }
+
+    #[test]
+    fn test_sentry_context_omits_unset_meta() {
+        let err: HandlerError = HandlerErrorKind::AdmServerError().into();
+        let context = err.sentry_context();
+        assert_eq!(context.get("request_id"), None);
+        assert_eq!(context.get("upstream_status"), None);
+        assert_eq!(context.get("retry_count"), None);
+    }
+}
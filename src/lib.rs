@@ -21,7 +21,9 @@ pub mod error;
 pub mod metrics;
 pub mod server;
 pub mod settings;
+pub mod sov;
 pub mod tags;
+pub mod tracing;
 pub mod web;
 
 /// Create the version string (e.g. "contile/1.0.0") with the given separator.
@@ -26,8 +26,19 @@ struct Args {
 
 use contile::{logging, server, settings};
 
+// Enabled via the `dhat-heap` feature for ad hoc allocation profiling of the
+// ADM hot path (`get_tiles`/`filter_and_process`) under representative
+// load -- see `Profiler::new_heap` below.
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // Dropped just before `logging::reset_logging()` below, so the
+    // `dhat-heap.json` it writes on drop captures the whole run.
+    #[cfg(feature = "dhat-heap")]
+    let dhat_profiler = dhat::Profiler::new_heap();
     let args: Args = Docopt::new(USAGE)
         .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
@@ -67,6 +78,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     info!("Server running on {}", banner);
     server.await?;
     info!("Server closing");
+    #[cfg(feature = "dhat-heap")]
+    drop(dhat_profiler);
     logging::reset_logging();
 
     Ok(())
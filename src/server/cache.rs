@@ -1,27 +1,38 @@
 //! Tile cache manager
 use std::{
     fmt::Debug,
-    sync::Arc,
-    time::{Duration, SystemTime},
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use actix_web::{
-    http::header::{CacheControl, CacheDirective, TryIntoHeaderPair},
+    http::header::{
+        CacheControl, CacheDirective, HeaderMap, HttpDate, TryIntoHeaderPair, ETAG,
+        IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    },
     rt, HttpResponse,
 };
 use cadence::StatsdClient;
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
 use crate::web::handlers::TilesHandlerResponse;
 use crate::{
     error::HandlerError,
     metrics::Metrics,
+    tags::Tags,
     web::{FormFactor, OsFamily},
 };
 
 /// AudienceKey is the primary key used to store and fetch tiles from the
 /// local cache.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct AudienceKey {
     /// Country in ISO 3166-1 alpha-2 format
     pub country_code: String,
@@ -39,13 +50,36 @@ pub struct AudienceKey {
 
 #[derive(Debug, Clone)]
 pub struct TilesCache {
-    inner: Arc<DashMap<AudienceKey, TilesState>>,
+    inner: Arc<dyn TileStore>,
+    composition: Arc<CacheComposition>,
+    /// Monotonic counter bumped once per GC sweep (see
+    /// `tiles_cache_periodic_reporter`), used to age-rank entries for
+    /// eviction via `CacheEntry::last_access` instead of a wall-clock
+    /// timestamp.
+    age: Arc<AtomicU64>,
+    /// Maximum number of entries the sweep will allow before evicting the
+    /// least-recently-used ones. `None` leaves the cache unbounded (the
+    /// historical behavior).
+    max_entries: Option<usize>,
+    /// Maximum approximate byte footprint (see `TilesState::size`) the
+    /// sweep will allow before evicting. `None` leaves the cache unbounded.
+    max_bytes: Option<usize>,
 }
 
 impl TilesCache {
-    pub fn new(capacity: usize) -> Self {
+    /// Build a cache backed by `store` (see [TileStore], [InMemoryTileStore],
+    /// [DiskTileStore]).
+    pub fn new(
+        store: Arc<dyn TileStore>,
+        max_entries: Option<usize>,
+        max_bytes: Option<usize>,
+    ) -> Self {
         Self {
-            inner: Arc::new(DashMap::with_capacity(capacity)),
+            inner: store,
+            composition: Arc::new(CacheComposition::default()),
+            age: Arc::new(AtomicU64::new(0)),
+            max_entries,
+            max_bytes,
         }
     }
 
@@ -60,12 +94,24 @@ impl TilesCache {
         });
     }
 
-    /// Get an immutable reference to an entry in the cache
-    pub fn get(
-        &self,
-        audience_key: &AudienceKey,
-    ) -> Option<dashmap::mapref::one::Ref<'_, AudienceKey, TilesState>> {
-        self.inner.get(audience_key)
+    /// Get a handle to an entry in the cache, stamping it with the cache's
+    /// current sweep age so the next GC sweep knows it was recently used.
+    pub fn get(&self, audience_key: &AudienceKey) -> Option<Arc<CacheEntry>> {
+        let entry = self.inner.get(audience_key)?;
+        entry.touch(self.age.load(Ordering::Relaxed));
+        Some(entry)
+    }
+
+    /// A cheap, read-only snapshot of the cache's contents for operational
+    /// introspection (see `web::admin::dump_tiles`). Pass `audience_key` to
+    /// inspect a single entry instead of dumping the whole cache.
+    pub fn dump(&self, audience_key: Option<&AudienceKey>) -> Vec<TilesStateDump> {
+        self.inner
+            .snapshot()
+            .into_iter()
+            .filter(|(key, _)| audience_key.map_or(true, |target| target == key))
+            .map(|(key, entry)| TilesStateDump::new(&key, &entry.state))
+            .collect()
     }
 
     /// Prepare to write to the cache.
@@ -80,24 +126,38 @@ impl TilesCache {
     ) -> WriteHandle<'a, impl FnOnce(()) + '_> {
         let mut fallback_tiles = None;
 
+        let age = self.age.load(Ordering::Relaxed);
+
         if expired {
             // The cache entry's expired and we're about to refresh it
             trace!("prepare_write: Fresh now expired, Refreshing");
             self.inner
-                .alter(audience_key, |_, tiles_state| match tiles_state {
+                .alter(audience_key, &mut |entry| match &entry.state {
                     TilesState::Fresh { tiles } if tiles.expired() => {
                         // In case an error occurs while doing the write work
                         // we'll render the current value as a fallback
                         fallback_tiles = Some(tiles.clone());
-                        TilesState::Refreshing { tiles }
+                        self.composition.to_refreshing(tiles);
+                        entry.with_state(TilesState::Refreshing {
+                            tiles: tiles.clone(),
+                            since: Instant::now(),
+                        })
                     }
-                    _ => tiles_state,
+                    _ => entry.clone(),
                 });
         } else {
             // We'll populate this cache entry for probably the first time
             trace!("prepare_write: Populating");
-            self.inner
-                .insert(audience_key.clone(), TilesState::Populating);
+            self.inner.insert(
+                audience_key.clone(),
+                Arc::new(CacheEntry::new(
+                    TilesState::Populating {
+                        since: Instant::now(),
+                    },
+                    age,
+                )),
+            );
+            self.composition.populating.fetch_add(1, Ordering::Relaxed);
         };
 
         let guard = scopeguard::guard((), move |_| {
@@ -106,14 +166,24 @@ impl TilesCache {
                 // Back to Fresh (though the tiles are expired): so a later
                 // request will retry refreshing again
                 self.inner
-                    .alter(audience_key, |_, tiles_state| match tiles_state {
-                        TilesState::Refreshing { tiles } => TilesState::Fresh { tiles },
-                        _ => tiles_state,
+                    .alter(audience_key, &mut |entry| match &entry.state {
+                        TilesState::Refreshing { tiles, .. } => {
+                            self.composition.refreshing.fetch_sub(1, Ordering::Relaxed);
+                            self.composition.inc_fresh_bucket(tiles);
+                            entry.with_state(TilesState::Fresh {
+                                tiles: tiles.clone(),
+                            })
+                        }
+                        _ => entry.clone(),
                     });
             } else {
                 // Clear the entry: a later request will retry populating again
-                self.inner.remove_if(audience_key, |_, tiles_state| {
-                    matches!(tiles_state, TilesState::Populating)
+                self.inner.remove_if(audience_key, &|entry| {
+                    let is_populating = matches!(entry.state, TilesState::Populating { .. });
+                    if is_populating {
+                        self.composition.populating.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    is_populating
                 });
             }
         });
@@ -122,8 +192,338 @@ impl TilesCache {
             audience_key,
             guard,
             fallback_tiles,
+            expired,
+            // Dropped (and thus cancelled) if the populating/refreshing task
+            // never completes in time: lets the caller abort a stuck fetch
+            // instead of leaving this entry stuck forever.
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+/// A `TilesCache` entry, wrapping the actual [TilesState] with the
+/// bookkeeping the periodic GC sweep needs to decide what to evict.
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub state: TilesState,
+    /// The sweep `TilesCache::age` this entry was last read at (see
+    /// `TilesCache::get`). The GC sweep evicts over-budget caches in
+    /// ascending order of this, oldest first.
+    last_access: AtomicU64,
+    /// Set on every `get`, intended for a future "only evict entries that
+    /// truly went untouched between sweeps" refinement; not yet acted on by
+    /// the sweep itself.
+    accessed_since_sweep: AtomicBool,
+}
+
+impl CacheEntry {
+    fn new(state: TilesState, age: u64) -> Self {
+        Self {
+            state,
+            last_access: AtomicU64::new(age),
+            accessed_since_sweep: AtomicBool::new(false),
+        }
+    }
+
+    fn touch(&self, age: u64) {
+        self.last_access.store(age, Ordering::Relaxed);
+        self.accessed_since_sweep.store(true, Ordering::Relaxed);
+    }
+
+    /// Build a replacement entry carrying `state`, preserving this entry's
+    /// `last_access`/`accessed_since_sweep` bookkeeping. Used by
+    /// `TileStore::alter` implementations, which hand back a brand new
+    /// `Arc<CacheEntry>` rather than mutating the shared one in place (a
+    /// reader may be holding a clone of it, see `TilesCache::get`).
+    fn with_state(&self, state: TilesState) -> Arc<Self> {
+        Arc::new(Self {
+            state,
+            last_access: AtomicU64::new(self.last_access.load(Ordering::Relaxed)),
+            accessed_since_sweep: AtomicBool::new(
+                self.accessed_since_sweep.load(Ordering::Relaxed),
+            ),
+        })
+    }
+}
+
+/// Storage operations `TilesCache` needs from its backing store, extracted
+/// so an alternate backend (see [DiskTileStore]) can be swapped in via
+/// `Settings::tiles_cache_backend` without touching `TilesCache`'s read/
+/// write/GC logic. See [InMemoryTileStore] for the historical (and default)
+/// in-process-only behavior.
+pub trait TileStore: Debug + Send + Sync {
+    /// Look up `key`, if present.
+    fn get(&self, key: &AudienceKey) -> Option<Arc<CacheEntry>>;
+    /// Insert (or overwrite) the entry at `key`.
+    fn insert(&self, key: AudienceKey, entry: Arc<CacheEntry>);
+    /// Replace the entry at `key` via `f`, if present.
+    fn alter(&self, key: &AudienceKey, f: &mut dyn FnMut(Arc<CacheEntry>) -> Arc<CacheEntry>);
+    /// Remove the entry at `key` if `predicate` holds, returning it.
+    fn remove_if(
+        &self,
+        key: &AudienceKey,
+        predicate: &dyn Fn(&CacheEntry) -> bool,
+    ) -> Option<Arc<CacheEntry>>;
+    /// Remove every entry for which `predicate` returns `false`, returning
+    /// how many were removed.
+    fn retain(&self, predicate: &mut dyn FnMut(&AudienceKey, &CacheEntry) -> bool) -> usize;
+    /// A snapshot of every entry currently stored, for the periodic
+    /// reporter's count/size/candidate scan and `TilesCache::dump`.
+    fn snapshot(&self) -> Vec<(AudienceKey, Arc<CacheEntry>)>;
+}
+
+/// The historical `TilesCache` backend: an in-process `DashMap`. Nothing
+/// survives a restart, so every deploy cold-starts the cache.
+#[derive(Debug)]
+pub struct InMemoryTileStore(DashMap<AudienceKey, Arc<CacheEntry>>);
+
+impl InMemoryTileStore {
+    pub fn new(capacity: usize) -> Self {
+        Self(DashMap::with_capacity(capacity))
+    }
+}
+
+impl TileStore for InMemoryTileStore {
+    fn get(&self, key: &AudienceKey) -> Option<Arc<CacheEntry>> {
+        self.0.get(key).map(|entry| Arc::clone(entry.value()))
+    }
+
+    fn insert(&self, key: AudienceKey, entry: Arc<CacheEntry>) {
+        self.0.insert(key, entry);
+    }
+
+    fn alter(&self, key: &AudienceKey, f: &mut dyn FnMut(Arc<CacheEntry>) -> Arc<CacheEntry>) {
+        self.0.alter(key, |_, entry| f(entry));
+    }
+
+    fn remove_if(
+        &self,
+        key: &AudienceKey,
+        predicate: &dyn Fn(&CacheEntry) -> bool,
+    ) -> Option<Arc<CacheEntry>> {
+        self.0
+            .remove_if(key, |_, entry| predicate(entry.as_ref()))
+            .map(|(_, entry)| entry)
+    }
+
+    fn retain(&self, predicate: &mut dyn FnMut(&AudienceKey, &CacheEntry) -> bool) -> usize {
+        let mut removed = 0;
+        self.0.retain(|key, entry| {
+            let keep = predicate(key, entry.as_ref());
+            if !keep {
+                removed += 1;
+            }
+            keep
+        });
+        removed
+    }
+
+    fn snapshot(&self) -> Vec<(AudienceKey, Arc<CacheEntry>)> {
+        self.0
+            .iter()
+            .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+            .collect()
+    }
+}
+
+/// Content-addressable disk-backed [TileStore]: persists `Fresh` tiles
+/// (serialized as a [DiskRecord]) under a filename hashed from their
+/// `AudienceKey`, so a restart can repopulate `hot` (see `new`) and serve
+/// `stale-if-error` fallbacks immediately instead of cold-starting and
+/// hammering the ADM partner while the cache re-warms. `Populating`/
+/// `Refreshing` entries only ever live in `hot` -- a restart always loses
+/// whichever write was in flight, disk-backed or not.
+#[derive(Debug)]
+pub struct DiskTileStore {
+    hot: InMemoryTileStore,
+    dir: PathBuf,
+}
+
+impl DiskTileStore {
+    pub fn new(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let hot = InMemoryTileStore::new(16);
+        for entry in fs::read_dir(&dir)?.filter_map(Result::ok) {
+            let Ok(bytes) = fs::read(entry.path()) else {
+                continue;
+            };
+            let Ok(record) = serde_json::from_slice::<DiskRecord>(&bytes) else {
+                continue;
+            };
+            hot.insert(
+                record.audience_key,
+                Arc::new(CacheEntry::new(
+                    TilesState::Fresh {
+                        tiles: record.tiles,
+                    },
+                    0,
+                )),
+            );
+        }
+        Ok(Self { hot, dir })
+    }
+
+    /// Content-addressed by a hash of the (JSON-serialized) key, rather than
+    /// e.g. its `Debug` representation, so it never needs
+    /// filesystem-unsafe-character escaping. The JSON follows `AudienceKey`'s
+    /// field declaration order, so reordering those fields changes every
+    /// entry's filename.
+    fn path_for(&self, key: &AudienceKey) -> PathBuf {
+        let hash = serde_json::to_vec(key)
+            .map(|bytes| blake3::hash(&bytes).to_hex().to_string())
+            .unwrap_or_else(|_| "unknown".to_owned());
+        self.dir.join(format!("{}.json", hash))
+    }
+
+    fn persist(&self, key: &AudienceKey, tiles: &Tiles) {
+        let record = DiskRecord {
+            audience_key: key.clone(),
+            tiles: tiles.clone(),
+        };
+        match serde_json::to_vec(&record) {
+            Ok(bytes) => {
+                if let Err(e) = fs::write(self.path_for(key), bytes) {
+                    warn!("DiskTileStore: failed writing cache entry: {:?}", e);
+                }
+            }
+            Err(e) => warn!("DiskTileStore: failed serializing cache entry: {:?}", e),
         }
     }
+
+    fn remove_persisted(&self, key: &AudienceKey) {
+        // The file may never have existed (e.g. this key was never Fresh);
+        // that's not an error.
+        let _ = fs::remove_file(self.path_for(key));
+    }
+}
+
+impl TileStore for DiskTileStore {
+    fn get(&self, key: &AudienceKey) -> Option<Arc<CacheEntry>> {
+        self.hot.get(key)
+    }
+
+    fn insert(&self, key: AudienceKey, entry: Arc<CacheEntry>) {
+        match &entry.state {
+            TilesState::Fresh { tiles } => self.persist(&key, tiles),
+            TilesState::Populating { .. } | TilesState::Refreshing { .. } => {}
+        }
+        self.hot.insert(key, entry);
+    }
+
+    fn alter(&self, key: &AudienceKey, f: &mut dyn FnMut(Arc<CacheEntry>) -> Arc<CacheEntry>) {
+        let mut to_persist = None;
+        self.hot.alter(key, &mut |entry| {
+            let new_entry = f(entry);
+            if let TilesState::Fresh { tiles } = &new_entry.state {
+                to_persist = Some(tiles.clone());
+            }
+            new_entry
+        });
+        if let Some(tiles) = to_persist {
+            self.persist(key, &tiles);
+        }
+    }
+
+    fn remove_if(
+        &self,
+        key: &AudienceKey,
+        predicate: &dyn Fn(&CacheEntry) -> bool,
+    ) -> Option<Arc<CacheEntry>> {
+        let removed = self.hot.remove_if(key, predicate);
+        if removed.is_some() {
+            self.remove_persisted(key);
+        }
+        removed
+    }
+
+    fn retain(&self, predicate: &mut dyn FnMut(&AudienceKey, &CacheEntry) -> bool) -> usize {
+        // `InMemoryTileStore::retain` doesn't report which keys were
+        // dropped, and we need those to also drop their on-disk record, so
+        // collect them up front via a (non-atomic, best-effort) scan first.
+        let to_remove: Vec<AudienceKey> = self
+            .hot
+            .snapshot()
+            .into_iter()
+            .filter(|(key, entry)| !predicate(key, entry))
+            .map(|(key, _)| key)
+            .collect();
+        let mut removed = 0;
+        for key in &to_remove {
+            if self.hot.remove_if(key, &|_| true).is_some() {
+                self.remove_persisted(key);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    fn snapshot(&self) -> Vec<(AudienceKey, Arc<CacheEntry>)> {
+        self.hot.snapshot()
+    }
+}
+
+/// On-disk representation of one `DiskTileStore` entry.
+#[derive(Debug, Deserialize, Serialize)]
+struct DiskRecord {
+    audience_key: AudienceKey,
+    tiles: Tiles,
+}
+
+/// Lightweight, incrementally-maintained breakdown of the cache by
+/// [TilesState], updated at each state transition so the periodic reporter
+/// can emit per-state gauges without an extra scan of the map. `expired` is
+/// the exception: whether a `Fresh` entry is expired changes purely with the
+/// clock, so it's sampled during the full scan the reporter already performs
+/// for `tiles_cache.count`/`.size`.
+#[derive(Debug, Default)]
+struct CacheComposition {
+    populating: AtomicI64,
+    refreshing: AtomicI64,
+    fresh: AtomicI64,
+    empty: AtomicI64,
+}
+
+impl CacheComposition {
+    /// Record a fresh `tiles_state` being written into the cache for the
+    /// first time (the composition had nothing to forget).
+    fn record(&self, tiles_state: &TilesState) {
+        match tiles_state {
+            TilesState::Populating { .. } => self.populating.fetch_add(1, Ordering::Relaxed),
+            TilesState::Refreshing { .. } => self.refreshing.fetch_add(1, Ordering::Relaxed),
+            TilesState::Fresh { tiles } => self.inc_fresh_bucket(tiles),
+        };
+    }
+
+    fn to_refreshing(&self, tiles: &Tiles) {
+        self.dec_fresh_bucket(tiles);
+        self.refreshing.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn inc_fresh_bucket(&self, tiles: &Tiles) -> i64 {
+        if matches!(tiles.content, TilesContent::Empty) {
+            self.empty.fetch_add(1, Ordering::Relaxed)
+        } else {
+            self.fresh.fetch_add(1, Ordering::Relaxed)
+        }
+    }
+
+    fn dec_fresh_bucket(&self, tiles: &Tiles) -> i64 {
+        if matches!(tiles.content, TilesContent::Empty) {
+            self.empty.fetch_sub(1, Ordering::Relaxed)
+        } else {
+            self.fresh.fetch_sub(1, Ordering::Relaxed)
+        }
+    }
+
+    /// A `(state, count)` snapshot suitable for emitting as gauges.
+    fn snapshot(&self) -> [(&'static str, i64); 4] {
+        [
+            ("populating", self.populating.load(Ordering::Relaxed)),
+            ("refreshing", self.refreshing.load(Ordering::Relaxed)),
+            ("fresh", self.fresh.load(Ordering::Relaxed)),
+            ("empty", self.empty.load(Ordering::Relaxed)),
+        ]
+    }
 }
 
 /// Manages a write to a specific `TilesCache` entry.
@@ -139,6 +539,12 @@ where
     audience_key: &'a AudienceKey,
     guard: scopeguard::ScopeGuard<(), F>,
     pub fallback_tiles: Option<Tiles>,
+    /// Whether this handle is completing a `Refreshing` (vs `Populating`)
+    /// write, so `insert` knows which composition bucket to retire.
+    expired: bool,
+    /// Cancelled by the caller if the populate/refresh work misses its
+    /// deadline, so a hung upstream fetch doesn't run forever.
+    pub cancel: CancellationToken,
 }
 
 impl<F> WriteHandle<'_, F>
@@ -147,7 +553,23 @@ where
 {
     /// Insert a value into the cache for our audience_key
     pub fn insert(self, tiles: TilesState) {
-        self.cache.inner.insert(self.audience_key.clone(), tiles);
+        if self.expired {
+            self.cache
+                .composition
+                .refreshing
+                .fetch_sub(1, Ordering::Relaxed);
+        } else {
+            self.cache
+                .composition
+                .populating
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+        self.cache.composition.record(&tiles);
+        let age = self.cache.age.load(Ordering::Relaxed);
+        self.cache.inner.insert(
+            self.audience_key.clone(),
+            Arc::new(CacheEntry::new(tiles, age)),
+        );
         // With the write completed cancel scopeguard's cleanup
         scopeguard::ScopeGuard::into_inner(self.guard);
         trace!("WriteHandle: ScopeGuard defused (cancelled)");
@@ -158,27 +580,88 @@ where
 /// Wrapper around Tiles with additional state about any outstanding partner
 /// requests
 pub enum TilesState {
-    /// A task is currently populating this entry (via [crate::adm::get_tiles])
-    Populating,
+    /// A task is currently populating this entry (via [crate::adm::get_tiles]).
+    /// `since` is when that task began, so stalled requests can be detected.
+    Populating { since: Instant },
     /// Tiles that haven't expired (or been identified as expired) yet
     Fresh { tiles: Tiles },
     /// A task is currently refreshing this expired entry (via
-    /// [crate::adm::get_tiles])
-    Refreshing { tiles: Tiles },
+    /// [crate::adm::get_tiles]). `since` is when the refresh began.
+    Refreshing { tiles: Tiles, since: Instant },
 }
 
 impl TilesState {
     fn size(&self) -> usize {
         match self {
             TilesState::Populating { .. } => 0,
-            TilesState::Fresh { tiles } | TilesState::Refreshing { tiles } => tiles.content.size(),
+            TilesState::Fresh { tiles } | TilesState::Refreshing { tiles, .. } => {
+                tiles.content.size()
+            }
+        }
+    }
+
+    /// Whether a `Populating`/`Refreshing` task has been running longer than
+    /// `deadline`, suggesting it's stuck rather than merely in-flight.
+    pub fn stalled(&self, deadline: Duration) -> bool {
+        match self {
+            TilesState::Populating { since } | TilesState::Refreshing { since, .. } => {
+                since.elapsed() > deadline
+            }
+            TilesState::Fresh { .. } => false,
+        }
+    }
+
+    /// Whether this entry's `Tiles` have passed their `fallback_expiry` and
+    /// so are no longer worth serving even as a stale fallback. A
+    /// `Populating`/`Refreshing` entry has a write in flight and is never
+    /// considered fallback-expired.
+    fn fallback_expired(&self) -> bool {
+        match self {
+            TilesState::Fresh { tiles } => tiles.fallback_expired(),
+            TilesState::Populating { .. } | TilesState::Refreshing { .. } => false,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// A read-only summary of one `TilesCache` entry, for the `/__dump__/tiles`
+/// introspection endpoint (see `web::admin::dump_tiles`).
+#[derive(Debug, Serialize)]
+pub struct TilesStateDump {
+    pub audience_key: AudienceKey,
+    pub state: &'static str,
+    /// Seconds the entry's been `Populating`/`Refreshing`, `None` otherwise
+    pub since_secs: Option<u64>,
+    pub tile_count: Option<usize>,
+    pub expired: Option<bool>,
+    pub expires_in_secs: Option<i64>,
+    pub fallback_expires_in_secs: Option<i64>,
+}
+
+impl TilesStateDump {
+    fn new(audience_key: &AudienceKey, tiles_state: &TilesState) -> Self {
+        let (state, since, tiles) = match tiles_state {
+            TilesState::Populating { since } => ("populating", Some(*since), None),
+            TilesState::Refreshing { tiles, since } => ("refreshing", Some(*since), Some(tiles)),
+            TilesState::Fresh { tiles } => ("fresh", None, Some(tiles)),
+        };
+        Self {
+            audience_key: audience_key.clone(),
+            state,
+            since_secs: since.map(|since| since.elapsed().as_secs()),
+            tile_count: tiles.map(|tiles| tiles.tile_count),
+            expired: tiles.map(|tiles| tiles.expired()),
+            expires_in_secs: tiles.map(|tiles| tiles.expires_in_secs()),
+            fallback_expires_in_secs: tiles.map(|tiles| tiles.fallback_expires_in_secs()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Tiles {
     pub content: TilesContent,
+    /// Number of tiles this holds, for operational introspection (see
+    /// `web::admin::dump_tiles`)
+    pub tile_count: usize,
     /// When this is in need of a refresh (the `Cache-Control` `max-age`)
     expiry: SystemTime,
     /// After expiry we'll continue serving the stale version of these Tiles
@@ -186,6 +669,18 @@ pub struct Tiles {
     /// upstream service outages). `fallback_expiry` is when we stop serving
     /// this stale Tiles completely
     fallback_expiry: SystemTime,
+    /// After expiry, `get_tiles` may still serve this stale Tiles
+    /// immediately (refreshing it in the background instead of blocking)
+    /// until `swr_expiry`, also advertised as the `Cache-Control`
+    /// `stale-while-revalidate` directive
+    swr_expiry: SystemTime,
+    /// When this entry was populated, reported as `Last-Modified`
+    inserted_at: SystemTime,
+    /// ADM's `ETag`/`Last-Modified` for the response these Tiles were built
+    /// from, if any, passed back to [crate::adm::get_tiles] as the next
+    /// refresh's `revalidate` so an unchanged upstream response costs ADM a
+    /// `304` instead of a full re-serve.
+    pub adm_revalidation: Option<crate::adm::AdmRevalidation>,
 }
 
 impl Tiles {
@@ -193,24 +688,61 @@ impl Tiles {
         tiles_handler_response: TilesHandlerResponse,
         ttl: Duration,
         fallback_ttl: Duration,
+        stale_while_revalidate: Duration,
+        adm_revalidation: Option<crate::adm::AdmRevalidation>,
     ) -> Result<Self, HandlerError> {
-        let empty = Self::empty(ttl, fallback_ttl);
+        let empty = Self::empty(ttl, fallback_ttl, stale_while_revalidate, adm_revalidation);
         if tiles_handler_response.tile_response.tiles.is_empty() {
             return Ok(empty);
         }
+        let tile_count = tiles_handler_response.tile_response.tiles.len();
         let json = serde_json::to_string(&tiles_handler_response)
             .map_err(|e| HandlerError::internal(&format!("Response failed to serialize: {}", e)))?;
         Ok(Self {
             content: TilesContent::Json(json),
+            tile_count,
             ..empty
         })
     }
 
-    pub fn empty(ttl: Duration, fallback_ttl: Duration) -> Self {
+    pub fn empty(
+        ttl: Duration,
+        fallback_ttl: Duration,
+        stale_while_revalidate: Duration,
+        adm_revalidation: Option<crate::adm::AdmRevalidation>,
+    ) -> Self {
+        let expiry = SystemTime::now() + ttl;
         Self {
             content: TilesContent::Empty,
-            expiry: SystemTime::now() + ttl,
+            tile_count: 0,
+            expiry,
             fallback_expiry: SystemTime::now() + fallback_ttl,
+            swr_expiry: expiry + stale_while_revalidate,
+            inserted_at: SystemTime::now(),
+            adm_revalidation,
+        }
+    }
+
+    /// Rebuild this Tiles' expiry window (and `adm_revalidation`) after ADM
+    /// confirmed via `304 Not Modified` that its underlying content is still
+    /// current, reusing `content`/`tile_count`/`inserted_at` rather than
+    /// re-serializing a response ADM didn't actually resend.
+    pub fn revalidated(
+        &self,
+        ttl: Duration,
+        fallback_ttl: Duration,
+        stale_while_revalidate: Duration,
+        adm_revalidation: Option<crate::adm::AdmRevalidation>,
+    ) -> Self {
+        let expiry = SystemTime::now() + ttl;
+        Self {
+            content: self.content.clone(),
+            tile_count: self.tile_count,
+            expiry,
+            fallback_expiry: SystemTime::now() + fallback_ttl,
+            swr_expiry: expiry + stale_while_revalidate,
+            inserted_at: self.inserted_at,
+            adm_revalidation,
         }
     }
 
@@ -222,25 +754,95 @@ impl Tiles {
         self.fallback_expiry <= SystemTime::now()
     }
 
-    pub fn to_response(&self, cache_control_header: bool) -> HttpResponse {
-        match &self.content {
-            TilesContent::Json(json) => {
-                let mut builder = HttpResponse::Ok();
-                if cache_control_header {
-                    builder.insert_header(self.cache_control_header());
-                }
-                builder
-                    .content_type("application/json")
-                    .body(json.to_owned())
-            }
+    /// True once the `stale-while-revalidate` grace window (past `expiry`)
+    /// has also elapsed, i.e. `get_tiles` should no longer serve this stale
+    /// and refresh in the background, and should instead block on a
+    /// synchronous refresh (or fall back to `fallback_expired`).
+    pub fn stale_while_revalidate_expired(&self) -> bool {
+        self.swr_expiry <= SystemTime::now()
+    }
+
+    /// Seconds until `expiry`, negative if already expired
+    fn expires_in_secs(&self) -> i64 {
+        self.expiry
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            - now_secs()
+    }
+
+    /// Seconds until `fallback_expiry`, negative if already expired
+    fn fallback_expires_in_secs(&self) -> i64 {
+        self.fallback_expiry
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+            - now_secs()
+    }
+
+    /// Render this Tiles as a response, honoring the incoming request's
+    /// conditional headers: a matching `If-None-Match` (checked ahead of
+    /// `If-Modified-Since`, per RFC 7232 §3.3) short-circuits to a bodyless
+    /// `304 Not Modified`. A `204 No Content` (nothing to serve for this
+    /// audience) never carries an `ETag`/`Last-Modified` -- there's no body
+    /// to revalidate, and an empty-content hash would otherwise collide
+    /// across every excluded audience. Stale fallback Tiles still have a
+    /// real JSON body, so they keep revalidating normally.
+    pub fn to_response(&self, cache_control_header: bool, req_headers: &HeaderMap) -> HttpResponse {
+        let json = match &self.content {
+            TilesContent::Json(json) => json,
             TilesContent::Empty => {
                 let mut builder = HttpResponse::NoContent();
                 if cache_control_header {
                     builder.insert_header(self.cache_control_header());
                 }
-                builder.finish()
+                return builder.finish();
+            }
+        };
+        let etag = self.etag(json);
+        if self.not_modified(req_headers, &etag) {
+            let mut builder = HttpResponse::NotModified();
+            if cache_control_header {
+                builder.insert_header(self.cache_control_header());
             }
+            builder.insert_header((ETAG, etag));
+            builder.insert_header((LAST_MODIFIED, HttpDate::from(self.inserted_at)));
+            return builder.finish();
+        }
+        let mut builder = HttpResponse::Ok();
+        if cache_control_header {
+            builder.insert_header(self.cache_control_header());
         }
+        builder.insert_header((ETAG, etag));
+        builder.insert_header((LAST_MODIFIED, HttpDate::from(self.inserted_at)));
+        builder.content_type("application/json").body(json.to_owned())
+    }
+
+    /// Strong `ETag` over the serialized response body, which already embeds
+    /// the current `SOVManager` encoded blob version (see
+    /// `TilesHandlerResponse`'s flattened `sov_response`), so a single hash
+    /// covers both the tiles and the SOV split.
+    fn etag(&self, json: &str) -> String {
+        format!("\"{}\"", blake3::hash(json.as_bytes()).to_hex())
+    }
+
+    /// True if the request's conditional headers indicate the client already
+    /// has this exact Tiles cached. `If-None-Match` takes precedence over
+    /// `If-Modified-Since` when both are present.
+    fn not_modified(&self, req_headers: &HeaderMap, etag: &str) -> bool {
+        if let Some(if_none_match) = req_headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+            return if_none_match
+                .split(',')
+                .any(|candidate| candidate.trim() == etag || candidate.trim() == "*");
+        }
+        if let Some(since) = req_headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<HttpDate>().ok())
+        {
+            return HttpDate::from(self.inserted_at) <= since;
+        }
+        false
     }
 
     /// Return the Tiles' `Cache-Control` header
@@ -251,6 +853,9 @@ impl Tiles {
         let stale_if_error = (self.fallback_expiry.duration_since(SystemTime::now()))
             .unwrap_or_default()
             .as_secs();
+        let stale_while_revalidate = (self.swr_expiry.duration_since(SystemTime::now()))
+            .unwrap_or_default()
+            .as_secs();
         let header_value = CacheControl(vec![
             CacheDirective::Private,
             CacheDirective::MaxAge(max_age as u32),
@@ -258,12 +863,16 @@ impl Tiles {
                 "stale-if-error".to_owned(),
                 Some(stale_if_error.to_string()),
             ),
+            CacheDirective::Extension(
+                "stale-while-revalidate".to_owned(),
+                Some(stale_while_revalidate.to_string()),
+            ),
         ]);
         ("Cache-Control", header_value)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum TilesContent {
     Json(String),
     Empty,
@@ -278,23 +887,111 @@ impl TilesContent {
     }
 }
 
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
 async fn tiles_cache_periodic_reporter(cache: &TilesCache, metrics: &Metrics) {
     trace!("tiles_cache_periodic_reporter");
-    // calculate the size and GC (for seldomly used Tiles) while we're at it
-    let mut cache_count = 0;
-    let mut cache_size = 0;
-    for refm in cache.inner.iter() {
+    let age = cache.age.fetch_add(1, Ordering::Relaxed) + 1;
+
+    // First pass: drop anything that's not even worth serving as a stale
+    // fallback any more. This always runs, regardless of whether
+    // `max_entries`/`max_bytes` are configured.
+    let fallback_expired_evicted = cache.inner.retain(&mut |_, entry| {
+        let expired = entry.state.fallback_expired();
+        if expired {
+            // fallback_expired() is only ever true for `Fresh`, so this is
+            // always retiring a `fresh`/`empty` composition bucket entry.
+            if let TilesState::Fresh { tiles } = &entry.state {
+                cache.composition.dec_fresh_bucket(tiles);
+            }
+        }
+        !expired
+    }) as i64;
+
+    // Second pass: tally what's left and collect LRU-eviction candidates
+    // (anything not `Populating`/`Refreshing`, since those have a write in
+    // flight and are never evicted).
+    let mut cache_count: i64 = 0;
+    let mut cache_size: usize = 0;
+    let mut expired_count = 0;
+    let mut candidates: Vec<(AudienceKey, u64, usize)> = Vec::new();
+    for (key, entry) in cache.inner.snapshot() {
         cache_count += 1;
-        cache_size += refm.value().size();
+        let size = entry.state.size();
+        cache_size += size;
+        if let TilesState::Fresh { tiles } = &entry.state {
+            if tiles.expired() {
+                expired_count += 1;
+            }
+        }
+        if !matches!(
+            entry.state,
+            TilesState::Populating { .. } | TilesState::Refreshing { .. }
+        ) {
+            candidates.push((key, entry.last_access.load(Ordering::Relaxed), size));
+        }
+    }
+
+    // Third pass: if we're over budget, evict the oldest candidates first
+    // until both configured budgets are satisfied.
+    let mut lru_evicted: i64 = 0;
+    if cache.max_entries.is_some() || cache.max_bytes.is_some() {
+        candidates.sort_unstable_by_key(|(_, last_access, _)| *last_access);
+        for (audience_key, _, size) in candidates {
+            let over_entries = cache
+                .max_entries
+                .is_some_and(|max| cache_count as usize > max);
+            let over_bytes = cache.max_bytes.is_some_and(|max| cache_size > max);
+            if !over_entries && !over_bytes {
+                break;
+            }
+            let removed = cache.inner.remove_if(&audience_key, &|entry| {
+                !matches!(
+                    entry.state,
+                    TilesState::Populating { .. } | TilesState::Refreshing { .. }
+                )
+            });
+            if let Some(entry) = removed {
+                // Only non-`Populating`/`Refreshing` entries are ever
+                // candidates here, i.e. always `Fresh`.
+                if let TilesState::Fresh { tiles } = &entry.state {
+                    cache.composition.dec_fresh_bucket(tiles);
+                }
+                cache_count -= 1;
+                cache_size -= size;
+                lru_evicted += 1;
+            }
+        }
     }
 
     metrics.count("tiles_cache.count", cache_count);
+    // Approximate memory footprint of the cached tile responses.
     metrics.count("tiles_cache.size", cache_size as i64);
+    metrics.count(
+        "tiles_cache.gc.evicted_fallback_expired",
+        fallback_expired_evicted,
+    );
+    metrics.count("tiles_cache.gc.evicted_lru", lru_evicted);
+    metrics.count("tiles_cache.gc.age", age as i64);
+
+    for (state, count) in cache.composition.snapshot() {
+        let mut tags = Tags::default();
+        tags.add_tag("state", state);
+        metrics.gauge_with_tags("tiles_cache.entries", count.max(0) as u64, Some(&tags));
+    }
+    let mut expired_tags = Tags::default();
+    expired_tags.add_tag("state", "expired");
+    metrics.gauge_with_tags("tiles_cache.entries", expired_count, Some(&expired_tags));
 }
 
 #[cfg(test)]
 mod test_tile_cache {
-    use super::TilesCache;
+    use super::{InMemoryTileStore, TilesCache};
     use crate::server::TILES_CACHE_INITIAL_CAPACITY;
     use actix_web::rt;
     use cadence::{SpyMetricSink, StatsdClient};
@@ -302,7 +999,8 @@ mod test_tile_cache {
 
     #[actix_web::test]
     async fn test_spawn_periodic_reporter() {
-        let tiles_cache = TilesCache::new(TILES_CACHE_INITIAL_CAPACITY);
+        let store = Arc::new(InMemoryTileStore::new(TILES_CACHE_INITIAL_CAPACITY));
+        let tiles_cache = TilesCache::new(store, None, None);
         let (spy, sink) = SpyMetricSink::new();
         let statsd_client = StatsdClient::builder("test", sink).build();
 
@@ -331,21 +1029,141 @@ mod test_tile_cache {
     }
 }
 
+#[cfg(test)]
+mod test_tiles_cache_gc {
+    use super::{
+        tiles_cache_periodic_reporter, AudienceKey, InMemoryTileStore, Tiles, TilesCache,
+        TilesState,
+    };
+    use crate::{
+        metrics::Metrics,
+        web::{FormFactor, OsFamily},
+    };
+    use cadence::{SpyMetricSink, StatsdClient};
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    fn audience_key(country_code: &str) -> AudienceKey {
+        AudienceKey {
+            country_code: country_code.to_owned(),
+            region_code: None,
+            dma_code: None,
+            form_factor: FormFactor::Desktop,
+            os_family: OsFamily::Linux,
+            legacy_only: false,
+        }
+    }
+
+    fn test_metrics() -> Metrics {
+        let (_spy, sink) = SpyMetricSink::new();
+        Metrics::from(std::sync::Arc::new(
+            StatsdClient::builder("test", sink).build(),
+        ))
+    }
+
+    #[actix_web::test]
+    async fn test_gc_evicts_fallback_expired() {
+        let cache = TilesCache::new(Arc::new(InMemoryTileStore::new(10)), None, None);
+        let key = audience_key("US");
+        let already_expired = Tiles::empty(
+            Duration::from_secs(60),
+            Duration::from_secs(0),
+            Duration::from_secs(0),
+            None,
+        );
+        cache.inner.insert(
+            key.clone(),
+            Arc::new(super::CacheEntry::new(
+                TilesState::Fresh {
+                    tiles: already_expired,
+                },
+                0,
+            )),
+        );
+
+        tiles_cache_periodic_reporter(&cache, &test_metrics()).await;
+
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_gc_never_evicts_populating_or_refreshing() {
+        let cache = TilesCache::new(Arc::new(InMemoryTileStore::new(10)), Some(0), Some(0));
+        let key = audience_key("US");
+        cache.inner.insert(
+            key.clone(),
+            Arc::new(super::CacheEntry::new(
+                TilesState::Populating {
+                    since: Instant::now(),
+                },
+                0,
+            )),
+        );
+
+        tiles_cache_periodic_reporter(&cache, &test_metrics()).await;
+
+        assert!(cache.get(&key).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_gc_evicts_lru_over_max_entries() {
+        let cache = TilesCache::new(Arc::new(InMemoryTileStore::new(10)), Some(1), None);
+        let older = audience_key("US");
+        let newer = audience_key("CA");
+        let fresh = || {
+            Tiles::empty(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                None,
+            )
+        };
+        cache.inner.insert(
+            older.clone(),
+            Arc::new(super::CacheEntry::new(
+                TilesState::Fresh { tiles: fresh() },
+                0,
+            )),
+        );
+        cache.inner.insert(
+            newer.clone(),
+            Arc::new(super::CacheEntry::new(
+                TilesState::Fresh { tiles: fresh() },
+                1,
+            )),
+        );
+
+        tiles_cache_periodic_reporter(&cache, &test_metrics()).await;
+
+        assert!(cache.get(&older).is_none());
+        assert!(cache.get(&newer).is_some());
+    }
+}
+
 #[cfg(test)]
 mod test_tiles_state {
     use super::{Tiles, TilesState};
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_size_populating() {
-        let tiles_state = TilesState::Populating;
+        let tiles_state = TilesState::Populating {
+            since: Instant::now(),
+        };
         assert_eq!(tiles_state.size(), 0);
     }
 
     #[test]
     fn test_size_fresh() {
         let tiles_state = TilesState::Fresh {
-            tiles: Tiles::empty(Duration::from_secs(60), Duration::from_secs(60)),
+            tiles: Tiles::empty(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                None,
+            ),
         };
         assert_eq!(tiles_state.size(), 0);
     }
@@ -353,10 +1171,25 @@ mod test_tiles_state {
     #[test]
     fn test_size_refreshing() {
         let tiles_state = TilesState::Refreshing {
-            tiles: Tiles::empty(Duration::from_secs(60), Duration::from_secs(60)),
+            tiles: Tiles::empty(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                None,
+            ),
+            since: Instant::now(),
         };
         assert_eq!(tiles_state.size(), 0);
     }
+
+    #[test]
+    fn test_stalled() {
+        let tiles_state = TilesState::Populating {
+            since: Instant::now() - Duration::from_secs(30),
+        };
+        assert!(tiles_state.stalled(Duration::from_secs(10)));
+        assert!(!tiles_state.stalled(Duration::from_secs(60)));
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,125 @@
+//! Byte-signature sniffing for downloaded tile images
+//!
+//! ADM only ever gives us a `Content-Type` header and an `image_url`; we
+//! have no guarantee the bytes behind that URL are actually an image, let
+//! alone the claimed type. Sniffing the leading bytes against known magic
+//! numbers before [crate::server::img_storage::ImageStore] accepts/stores a
+//! tile's image stops a misconfigured or malicious ADM response from
+//! smuggling an HTML/script payload in under an `image_url`.
+
+use std::fmt;
+
+/// An image family detected from a byte signature, independent of whatever
+/// `Content-Type` the server claimed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DetectedImageType {
+    Jpeg,
+    Png,
+    Gif,
+    WebP,
+    Svg,
+    Unknown,
+}
+
+impl DetectedImageType {
+    /// The short name used in [crate::server::img_storage::ImageMetricSettings::allowed_image_types].
+    pub fn as_allowlist_name(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::Gif => "gif",
+            Self::WebP => "webp",
+            Self::Svg => "svg",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Whether a (lowercased) `Content-Type` value is one of the aliases
+    /// contile accepts for this detected family. `Unknown` never matches.
+    pub fn matches_content_type(&self, content_type_lowercase: &str) -> bool {
+        let aliases: &[&str] = match self {
+            Self::Jpeg => &["image/jpeg", "image/jpg"],
+            Self::Png => &["image/png"],
+            Self::Gif => &["image/gif"],
+            Self::WebP => &["image/webp"],
+            Self::Svg => &["image/svg+xml", "image/svg"],
+            Self::Unknown => &[],
+        };
+        aliases.contains(&content_type_lowercase)
+    }
+}
+
+impl fmt::Display for DetectedImageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_allowlist_name())
+    }
+}
+
+/// Sniff the image family from its leading bytes, ignoring whatever
+/// `Content-Type` the server sent.
+pub fn sniff(bytes: &[u8]) -> DetectedImageType {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return DetectedImageType::Jpeg;
+    }
+    if bytes.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return DetectedImageType::Png;
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return DetectedImageType::Gif;
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return DetectedImageType::WebP;
+    }
+    // SVG is just XML text: skip any leading BOM/whitespace and look for a
+    // `<?xml` or `<svg` opener in the first chunk of the body.
+    let head = &bytes[..bytes.len().min(256)];
+    let text = String::from_utf8_lossy(head);
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with("<?xml") || trimmed.starts_with("<svg") {
+        return DetectedImageType::Svg;
+    }
+    DetectedImageType::Unknown
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sniff, DetectedImageType};
+
+    #[test]
+    fn test_sniff_jpeg() {
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), DetectedImageType::Jpeg);
+    }
+
+    #[test]
+    fn test_sniff_png() {
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00];
+        assert_eq!(sniff(&png), DetectedImageType::Png);
+    }
+
+    #[test]
+    fn test_sniff_gif() {
+        assert_eq!(sniff(b"GIF89a..."), DetectedImageType::Gif);
+    }
+
+    #[test]
+    fn test_sniff_webp() {
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&[0, 0, 0, 0]);
+        webp.extend_from_slice(b"WEBP");
+        assert_eq!(sniff(&webp), DetectedImageType::WebP);
+    }
+
+    #[test]
+    fn test_sniff_svg() {
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\"?><svg></svg>"),
+            DetectedImageType::Svg
+        );
+        assert_eq!(sniff(b"  <svg xmlns=\"\"></svg>"), DetectedImageType::Svg);
+    }
+
+    #[test]
+    fn test_sniff_unknown() {
+        assert_eq!(sniff(b"<html><body>nope</body></html>"), DetectedImageType::Unknown);
+    }
+}
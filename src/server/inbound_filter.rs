@@ -0,0 +1,177 @@
+//! Pre-fetch inbound request filtering (crawlers and blocklisted networks)
+//!
+//! Runs in [crate::web::handlers::get_tiles] (via `maybe_early_respond`)
+//! before the ADM round-trip, so that known web crawlers and requests from
+//! blocklisted IP ranges don't cost a live ADM fetch or count toward
+//! impressions. Unlike [crate::server::rate_limit], a match here isn't
+//! abuse -- it's traffic we expect and don't want to pay for -- so it's
+//! reported via a metric/tag rather than an error response.
+use std::net::IpAddr;
+
+use regex::Regex;
+
+use crate::error::{HandlerError, HandlerErrorKind, HandlerResult};
+
+/// Matches a request's `User-Agent` against a configured set of web-crawler
+/// patterns, compiled once at startup.
+#[derive(Clone, Debug, Default)]
+pub struct CrawlerFilter {
+    patterns: Vec<Regex>,
+}
+
+impl CrawlerFilter {
+    /// Parse `patterns_json` (a JSON list of regex strings) into a compiled
+    /// `CrawlerFilter`. Returns `None` if `patterns_json` is `None` or empty.
+    pub fn from_json(patterns_json: Option<&str>) -> HandlerResult<Option<Self>> {
+        let Some(patterns_json) = patterns_json.filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        let raw: Vec<String> = serde_json::from_str(patterns_json).map_err(|e| {
+            HandlerError::internal(&format!("Invalid crawler_ua_patterns: {:?}", e))
+        })?;
+        let patterns = raw
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    HandlerErrorKind::Internal(format!(
+                        "Invalid crawler UA pattern {:?}: {}",
+                        pattern, e
+                    ))
+                    .into()
+                })
+            })
+            .collect::<HandlerResult<_>>()?;
+        Ok(Some(Self { patterns }))
+    }
+
+    /// Whether `ua` looks like a known web crawler.
+    pub fn is_crawler(&self, ua: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(ua))
+    }
+}
+
+/// A single CIDR network (address + prefix length).
+#[derive(Clone, Copy, Debug)]
+struct Network {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Network {
+    fn parse(cidr: &str) -> Result<Self, String> {
+        let (addr, prefix_len) = cidr
+            .split_once('/')
+            .ok_or_else(|| format!("Missing prefix length in {:?}", cidr))?;
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|e| format!("Invalid address in {:?}: {}", cidr, e))?;
+        let prefix_len: u8 = prefix_len
+            .parse()
+            .map_err(|e| format!("Invalid prefix length in {:?}: {}", cidr, e))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max_len {
+            return Err(format!("Prefix length out of range in {:?}", cidr));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// Whether `ip` falls within this network, masking both addresses down
+    /// to `prefix_len` bits. Mixed v4/v6 comparisons never match.
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = mask_for(self.prefix_len, 32) as u32;
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = mask_for(self.prefix_len, 128) as u128;
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A left-aligned bitmask `bits` wide within a field of `width` bits (e.g.
+/// `mask_for(24, 32)` is `0xffffff00`). Guards the `bits == 0` case, where a
+/// native shift by the full field width would panic/is UB.
+fn mask_for(bits: u8, width: u8) -> u128 {
+    if bits == 0 {
+        0
+    } else {
+        (u128::MAX << (width - bits)) & (u128::MAX >> (128 - width))
+    }
+}
+
+/// Drops requests whose source IP falls within any of a configured set of
+/// CIDR ranges, parsed once at startup.
+#[derive(Clone, Debug, Default)]
+pub struct IpBlocklist {
+    networks: Vec<Network>,
+}
+
+impl IpBlocklist {
+    /// Parse `cidrs_json` (a JSON list of CIDR strings, e.g.
+    /// `["203.0.113.0/24", "2001:db8::/32"]`) into a compiled `IpBlocklist`.
+    /// Returns `None` if `cidrs_json` is `None` or empty.
+    pub fn from_json(cidrs_json: Option<&str>) -> HandlerResult<Option<Self>> {
+        let Some(cidrs_json) = cidrs_json.filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        let raw: Vec<String> = serde_json::from_str(cidrs_json)
+            .map_err(|e| HandlerError::internal(&format!("Invalid blocked_cidrs: {:?}", e)))?;
+        let networks = raw
+            .iter()
+            .map(|cidr| Network::parse(cidr).map_err(|e| HandlerErrorKind::Internal(e).into()))
+            .collect::<HandlerResult<_>>()?;
+        Ok(Some(Self { networks }))
+    }
+
+    /// Whether `ip` falls within any configured blocked network.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crawler_filter_matches_configured_patterns() {
+        let filter = CrawlerFilter::from_json(Some(r#"["(?i)googlebot", "(?i)bingbot"]"#))
+            .unwrap()
+            .unwrap();
+        assert!(filter.is_crawler("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+        assert!(!filter.is_crawler("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Firefox/100.0"));
+    }
+
+    #[test]
+    fn crawler_filter_absent_when_unconfigured() {
+        assert!(CrawlerFilter::from_json(None).unwrap().is_none());
+        assert!(CrawlerFilter::from_json(Some("")).unwrap().is_none());
+    }
+
+    #[test]
+    fn ip_blocklist_matches_v4_cidr() {
+        let blocklist = IpBlocklist::from_json(Some(r#"["203.0.113.0/24"]"#))
+            .unwrap()
+            .unwrap();
+        assert!(blocklist.contains(&"203.0.113.42".parse().unwrap()));
+        assert!(!blocklist.contains(&"203.0.114.42".parse().unwrap()));
+    }
+
+    #[test]
+    fn ip_blocklist_matches_v6_cidr() {
+        let blocklist = IpBlocklist::from_json(Some(r#"["2001:db8::/32"]"#))
+            .unwrap()
+            .unwrap();
+        assert!(blocklist.contains(&"2001:db8::1".parse().unwrap()));
+        assert!(!blocklist.contains(&"2001:db9::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ip_blocklist_rejects_invalid_cidr() {
+        assert!(IpBlocklist::from_json(Some(r#"["not-a-cidr"]"#)).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use actix_web::{http::HeaderName, HttpRequest};
 use actix_web_location::{
     providers::{FallbackProvider, MaxMindProvider},
@@ -5,9 +7,57 @@ use actix_web_location::{
 };
 use async_trait::async_trait;
 use cadence::StatsdClient;
+use dashmap::DashMap;
+use serde::Deserialize;
 
 use crate::{error::HandlerError, settings::Settings};
 
+/// Strip the leading two-letter country-code prefix off a Unicode CLDR
+/// subdivision ID, such as `USCA` -> `CA` (modeled from Google Load
+/// Balancer's `client_region_subdivision`). Leaves shorter values untouched.
+fn strip_cldr_country_prefix(subdivision: &str) -> &str {
+    if subdivision.len() > 2 {
+        &subdivision[2..]
+    } else {
+        subdivision
+    }
+}
+
+/// Parse a `"country,region,dma"` CSV value (the same layout
+/// [TestHeaderProvider] and [UpstreamHeaderProvider] both accept) into a
+/// [Location].
+fn location_from_csv(provider: &str, value: &str) -> Result<Location, Error> {
+    let mut builder = Location::build().provider(provider.to_owned());
+    let mut parts = value.split(',');
+
+    if let Some(country) = parts.next() {
+        let country = country.trim();
+        if !country.is_empty() {
+            builder = builder.country(country.to_owned())
+        }
+    }
+
+    if let Some(subdivision) = parts.next() {
+        let subdivision = strip_cldr_country_prefix(subdivision.trim());
+        if !subdivision.is_empty() {
+            builder = builder.region(subdivision.to_owned());
+        }
+    }
+
+    if let Some(dma) = parts.next() {
+        let dma = dma.trim().parse().unwrap_or(0);
+        // Non-exact validation (there's only 210 DMA regions) but
+        // close enough for testing
+        if (500..=900).contains(&dma) {
+            builder = builder.dma(dma);
+        }
+    }
+
+    builder
+        .finish()
+        .map_err(|_| Error::Provider(HandlerError::internal("Couldn't build Location").into()))
+}
+
 /// Provides the location from a configurable client specified header for
 /// testing purposes.
 pub struct TestHeaderProvider {
@@ -34,46 +84,195 @@ impl Provider for TestHeaderProvider {
     }
 
     async fn get_location(&self, req: &HttpRequest) -> Result<Option<Location>, Error> {
-        if let Some(header) = req.headers().get(&self.test_header) {
-            let mut builder = Location::build().provider(self.name().to_owned());
-            let mut parts = header.to_str().unwrap_or_default().split(',');
-
-            if let Some(country) = parts.next() {
-                let country = country.trim();
-                if !country.is_empty() {
-                    builder = builder.country(country.to_owned())
-                }
-            }
+        match req.headers().get(&self.test_header) {
+            Some(header) => Ok(Some(location_from_csv(
+                self.name(),
+                header.to_str().unwrap_or_default(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
 
-            if let Some(subdivision) = parts.next() {
-                let mut subdivision = subdivision.trim();
-                // Expect a "Unicode CLDR subdivision ID, such as USCA or CAON"
-                // (modeled from Google Load Balancer's
-                // client_region_subdivision)
-                if subdivision.len() > 2 {
-                    subdivision = &subdivision[2..];
-                }
-                if !subdivision.is_empty() {
-                    builder = builder.region(subdivision.to_owned());
-                }
-            }
+/// Trusts a geo header set by an upstream load balancer/CDN (as opposed to
+/// [TestHeaderProvider], which is only ever wired in for integration tests).
+/// Expects the same `country,region,dma` CSV layout.
+pub struct UpstreamHeaderProvider {
+    geo_header: HeaderName,
+}
+
+impl UpstreamHeaderProvider {
+    pub fn new(geo_header: &str) -> Self {
+        Self {
+            geo_header: HeaderName::from_lowercase(geo_header.to_lowercase().as_ref())
+                .expect("Invalid geo_header"),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Provider for UpstreamHeaderProvider {
+    fn name(&self) -> &str {
+        "geo_header"
+    }
+
+    fn expect_city(&self) -> bool {
+        false
+    }
 
-            if let Some(dma) = parts.next() {
-                let dma = dma.trim().parse().unwrap_or(0);
-                // Non-exact validation (there's only 210 DMA regions) but
-                // close enough for testing
-                if (500..=900).contains(&dma) {
-                    builder = builder.dma(dma);
-                }
+    async fn get_location(&self, req: &HttpRequest) -> Result<Option<Location>, Error> {
+        match req.headers().get(&self.geo_header) {
+            Some(header) => Ok(Some(location_from_csv(
+                self.name(),
+                header.to_str().unwrap_or_default(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Result shape expected back from `geo_api_url`.
+#[derive(Deserialize)]
+struct GeoApiResponse {
+    country: Option<String>,
+    region: Option<String>,
+    dma: Option<u16>,
+}
+
+/// Looks up each request's IP against an outbound HTTP geolocation API,
+/// caching results for a short TTL (keyed by IP) to avoid hammering it.
+pub struct HttpGeoProvider {
+    url_template: String,
+    client: reqwest::Client,
+    ttl: Duration,
+    cache: DashMap<String, (Location, Instant)>,
+}
+
+impl HttpGeoProvider {
+    pub fn new(
+        url_template: &str,
+        connect_timeout: Duration,
+        timeout: Duration,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            url_template: url_template.to_owned(),
+            client: reqwest::Client::builder()
+                .connect_timeout(connect_timeout)
+                .timeout(timeout)
+                .build()
+                .expect("Could not build geo_api_url client"),
+            ttl,
+            cache: DashMap::new(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Provider for HttpGeoProvider {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn expect_city(&self) -> bool {
+        false
+    }
+
+    async fn get_location(&self, req: &HttpRequest) -> Result<Option<Location>, Error> {
+        let Some(ip) = req.connection_info().realip_remote_addr().map(str::to_owned) else {
+            return Ok(None);
+        };
+
+        if let Some(cached) = self.cache.get(&ip) {
+            let (location, fetched_at) = cached.value();
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(Some(location.clone()));
             }
+        }
+
+        let url = self.url_template.replace("{ip}", &ip);
+        let resp: GeoApiResponse = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| Error::Provider(HandlerError::internal(&e.to_string()).into()))?
+            .json()
+            .await
+            .map_err(|e| Error::Provider(HandlerError::internal(&e.to_string()).into()))?;
 
-            let location = builder.finish().map_err(|_| {
-                Error::Provider(HandlerError::internal("Couldn't build Location").into())
-            })?;
-            Ok(Some(location))
-        } else {
-            Ok(None)
+        let mut builder = Location::build().provider(self.name().to_owned());
+        if let Some(country) = resp.country {
+            builder = builder.country(country);
+        }
+        if let Some(region) = resp.region {
+            builder = builder.region(region);
         }
+        if let Some(dma) = resp.dma {
+            builder = builder.dma(dma);
+        }
+        let location = builder
+            .finish()
+            .map_err(|_| Error::Provider(HandlerError::internal("Couldn't build Location").into()))?;
+
+        self.cache.insert(ip, (location.clone(), Instant::now()));
+        Ok(Some(location))
+    }
+}
+
+/// Parses standard CDN/edge geo headers without requiring an operator to
+/// configure a specific header name: either a combined `X-Client-Geo`
+/// `"country,region,dma"` CSV header (the same convention
+/// [TestHeaderProvider]/[UpstreamHeaderProvider] use), or CloudFront's
+/// discrete `CloudFront-Viewer-Country`/`CloudFront-Viewer-Country-Region`/
+/// `CloudFront-Viewer-Metro-Code` headers.
+pub struct CdnGeoHeaderProvider;
+
+#[async_trait(?Send)]
+impl Provider for CdnGeoHeaderProvider {
+    fn name(&self) -> &str {
+        "cloudfront_header"
+    }
+
+    fn expect_city(&self) -> bool {
+        false
+    }
+
+    async fn get_location(&self, req: &HttpRequest) -> Result<Option<Location>, Error> {
+        let headers = req.headers();
+
+        if let Some(combined) = headers.get("x-client-geo").and_then(|v| v.to_str().ok()) {
+            return Ok(Some(location_from_csv(self.name(), combined)?));
+        }
+
+        let Some(country) = headers
+            .get("cloudfront-viewer-country")
+            .and_then(|v| v.to_str().ok())
+        else {
+            return Ok(None);
+        };
+
+        let mut builder = Location::build()
+            .provider(self.name().to_owned())
+            .country(country.to_owned());
+        if let Some(region) = headers
+            .get("cloudfront-viewer-country-region")
+            .and_then(|v| v.to_str().ok())
+        {
+            builder = builder.region(strip_cldr_country_prefix(region).to_owned());
+        }
+        if let Some(dma) = headers
+            .get("cloudfront-viewer-metro-code")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+        {
+            builder = builder.dma(dma);
+        }
+
+        let location = builder
+            .finish()
+            .map_err(|_| Error::Provider(HandlerError::internal("Couldn't build Location").into()))?;
+        Ok(Some(location))
     }
 }
 
@@ -85,13 +284,40 @@ pub fn location_config_from_settings(
     if let Some(ref test_header) = settings.location_test_header {
         location_config = location_config.with_provider(TestHeaderProvider::new(test_header));
     }
-    if let Some(ref path) = settings.maxminddb_loc {
-        location_config = location_config
-            .with_provider(MaxMindProvider::from_path(path).expect("Could not read mmdb file"));
+    for provider in &settings.location_providers {
+        location_config = match provider.as_str() {
+            "maxmind" => {
+                let Some(ref path) = settings.maxminddb_loc else {
+                    continue;
+                };
+                location_config
+                    .with_provider(MaxMindProvider::from_path(path).expect("Could not read mmdb file"))
+            }
+            "header" => {
+                let Some(ref geo_header) = settings.geo_header else {
+                    continue;
+                };
+                location_config.with_provider(UpstreamHeaderProvider::new(geo_header))
+            }
+            "cloudfront_header" => location_config.with_provider(CdnGeoHeaderProvider),
+            "http" => {
+                let Some(ref url_template) = settings.geo_api_url else {
+                    continue;
+                };
+                location_config.with_provider(HttpGeoProvider::new(
+                    url_template,
+                    Duration::from_secs(settings.connect_timeout),
+                    Duration::from_secs(settings.request_timeout),
+                    Duration::from_secs(settings.geo_api_ttl),
+                ))
+            }
+            "fallback" => location_config.with_provider(FallbackProvider::new(
+                Location::build().country(settings.fallback_country.clone()),
+            )),
+            _ => continue,
+        };
     }
-    location_config.with_provider(FallbackProvider::new(
-        Location::build().country(settings.fallback_country.clone()),
-    ))
+    location_config
 }
 
 #[cfg(test)]
@@ -9,24 +9,61 @@ use actix_web::{
 use cadence::StatsdClient;
 
 use crate::{
-    adm::{spawn_updater, AdmFilter},
-    error::{HandlerError, HandlerResult},
+    adm::{admin_api, spawn_updater, AdmFilter, AdmRequester, ReqwestAdmRequester},
+    error::{HandlerError, HandlerErrorKind, HandlerResult},
     metrics::metrics_from_opts,
-    server::{img_storage::ImageStore, location::location_config_from_settings},
-    settings::Settings,
-    web::{dockerflow, handlers, middleware},
+    server::{
+        img_storage::ImageStore,
+        inbound_filter::{CrawlerFilter, IpBlocklist},
+        location::location_config_from_settings,
+        rate_limit::RateLimiter,
+    },
+    settings::{Settings, TileCacheBackend},
+    sov::SOVManager,
+    web::{admin, dockerflow, handlers, headers, img, middleware},
 };
 
 pub mod cache;
+pub mod image_sniff;
 pub mod img_storage;
+pub mod inbound_filter;
 pub mod location;
+pub mod rate_limit;
+pub mod redirect;
+pub mod remote_cache;
+pub mod svg_sanitize;
 
 /// Arbitrary initial cache size based on the expected mean, feel free to
 /// adjust
 const TILES_CACHE_INITIAL_CAPACITY: usize = 768;
 
 /// User-Agent sent to adM
-const REQWEST_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+pub(crate) const REQWEST_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+
+/// Build the CORS middleware from `Settings::cors_allowed_origins`. Each
+/// configured origin is registered individually (rather than
+/// `Cors::permissive()`/`send_wildcard()`) so actix-cors echoes back
+/// exactly the single matching `Origin` and adds `Vary: Origin` -- never a
+/// blanket `*`, never every allowed origin concatenated. A request whose
+/// `Origin` isn't on the list simply proceeds without CORS headers instead
+/// of being rejected; that's actix-cors' default behavior for anything but
+/// a preflight.
+pub(crate) fn cors_policy(settings: &Settings) -> Cors {
+    let origins: Vec<String> = serde_json::from_str(&settings.cors_allowed_origins)
+        .unwrap_or_else(|e| {
+            warn!("Invalid cors_allowed_origins, ignoring: {:?}", e);
+            Vec::new()
+        });
+    let mut cors = Cors::default()
+        .allowed_methods(vec!["GET", "OPTIONS"])
+        .allow_any_header()
+        .max_age(settings.cors_max_age_secs);
+    for origin in &origins {
+        cors = cors.allowed_origin(origin);
+    }
+    cors
+}
 
 /// This is the global HTTP state object that will be made available to all
 /// HTTP API calls.
@@ -34,12 +71,23 @@ pub struct ServerState {
     /// Metric reporting
     pub metrics: Box<StatsdClient>,
     pub reqwest_client: reqwest::Client,
+    /// Fetches the raw tile listing from ADM; swapped for a test double in
+    /// unit tests (see [crate::adm::AdmRequester])
+    pub adm_requester: Arc<dyn AdmRequester>,
     pub tiles_cache: cache::TilesCache,
     pub settings: Settings,
     pub filter: Arc<RwLock<AdmFilter>>,
     pub img_store: Option<ImageStore>,
     pub excluded_dmas: Option<Vec<u16>>,
     pub start_up: Instant,
+    /// Per-client abuse shield in front of `get_tiles`, `None` if disabled
+    pub rate_limiter: Option<RateLimiter>,
+    /// Current Share-of-Voice allocation, served alongside tiles to Desktop
+    pub sov_manager: Arc<tokio::sync::RwLock<SOVManager>>,
+    /// Known web-crawler User-Agent matcher, `None` if unconfigured
+    pub crawler_filter: Option<CrawlerFilter>,
+    /// Blocklisted source IP ranges, `None` if unconfigured
+    pub ip_blocklist: Option<IpBlocklist>,
 }
 
 impl Clone for ServerState {
@@ -47,12 +95,17 @@ impl Clone for ServerState {
         Self {
             metrics: self.metrics.clone(),
             reqwest_client: self.reqwest_client.clone(),
+            adm_requester: self.adm_requester.clone(),
             tiles_cache: self.tiles_cache.clone(),
             settings: self.settings.clone(),
             filter: self.filter.clone(),
             img_store: self.img_store.clone(),
             excluded_dmas: self.excluded_dmas.clone(),
             start_up: self.start_up,
+            rate_limiter: self.rate_limiter.clone(),
+            sov_manager: self.sov_manager.clone(),
+            crawler_filter: self.crawler_filter.clone(),
+            ip_blocklist: self.ip_blocklist.clone(),
         }
     }
 }
@@ -75,47 +128,114 @@ pub struct Server;
 /// Simplified Actix app builder (used by both the app and unit test)
 #[macro_export]
 macro_rules! build_app {
-    ($state: expr, $location_config: expr) => {
+    ($state: expr, $location_config: expr) => {{
+        let __contile_state = $state;
+        let __contile_cors = $crate::server::cors_policy(&__contile_state.settings);
         App::new()
-            .data($state)
+            .data(__contile_state)
             .data($location_config.clone())
             // Middleware is applied LIFO
             // These will wrap all outbound responses with matching status codes.
             .wrap(ErrorHandlers::new().handler(StatusCode::NOT_FOUND, HandlerError::render_404))
             // These are our wrappers
             .wrap(middleware::sentry::SentryWrapper::default())
+            .wrap(middleware::otlp::OtlpTracing::default())
             // Followed by the "official middleware" so they run first.
-            // actix is getting increasingly tighter about CORS headers. Our server is
-            // not a huge risk but does deliver XHR JSON content.
-            // For now, let's be permissive and use NGINX (the wrapping server)
-            // for finer grained specification.
-            .wrap(Cors::permissive())
+            // Origins are matched against `Settings::cors_allowed_origins`;
+            // see `cors_policy`.
+            .wrap(__contile_cors)
+            // Outermost: apply operator-configured security headers last, so
+            // nothing downstream can clobber them.
+            .wrap(headers::ResponseHeaders::default())
             // Next, the API we are implementing
             .service(web::resource("/v1/tiles").route(web::get().to(handlers::get_tiles)))
-            // image cache tester...
-            //.service(web::resource("/v1/test").route(web::get().to(handlers::get_image)))
+            // Serve cached tile images directly, with Range/ETag support
+            .service(web::resource("/v1/img/{key}").route(web::get().to(img::get_image)))
+            // Operational introspection, e.g. `/__dump__/tiles`
+            .service(web::scope("/").configure(admin::service))
+            // Authenticated admin mutation endpoints, e.g. `/admin/reload`
+            .service(web::scope("/").configure(admin_api::service))
             // And finally the behavior necessary to satisfy Dockerflow
             .service(web::scope("/").configure(dockerflow::service))
-    };
+    }};
 }
 
 impl Server {
     /// initialize a new instance of the server from [Settings]
     pub async fn with_settings(mut settings: Settings) -> Result<dev::Server, HandlerError> {
+        crate::tracing::init(&settings)?;
+        // `get_tiles` relies on these being set for every request; fail fast
+        // here instead of panicking on the first request.
+        if settings.adm_partner_id.is_none() || settings.adm_sub1.is_none() {
+            return Err(HandlerErrorKind::InvalidSettings(
+                "adm_partner_id and adm_sub1 must both be set".to_owned(),
+            )
+            .into());
+        }
         let metrics = metrics_from_opts(&settings)?;
         let mut raw_filter = HandlerResult::<AdmFilter>::from(&mut settings)?;
         // try to update from the bucket if possible.
         if raw_filter.is_cloud() {
             raw_filter.update().await?
         }
+        let proxy = raw_filter.proxy_config.into_reqwest_proxy()?;
         let filter = Arc::new(RwLock::new(raw_filter));
-        let req = reqwest::Client::builder()
+        let redirect_filter = Arc::clone(&filter);
+        let mut req_builder = reqwest::Client::builder()
             .connect_timeout(Duration::from_secs(settings.connect_timeout))
             .timeout(Duration::from_secs(settings.request_timeout))
             .user_agent(REQWEST_USER_AGENT)
-            .build()?;
+            // Advertise (and transparently decode) gzip/deflate-compressed
+            // responses -- ADM's tile payloads for large advertiser sets
+            // compress well, so this meaningfully cuts egress from ADM.
+            .gzip(true)
+            .deflate(true)
+            // Redirects from ADM or an image host are followed, but only to
+            // a bounded, non-downgrading, still-allow-listed host -- see
+            // [redirect::safe_policy].
+            .redirect(redirect::safe_policy(
+                settings.redirect_max_hops,
+                move |host| {
+                    redirect_filter
+                        .read()
+                        .map(|filter| filter.allows_redirect_host(host))
+                        .unwrap_or(false)
+                },
+            ));
+        if let Some(proxy) = proxy {
+            req_builder = req_builder.proxy(proxy);
+        }
+        let req = req_builder.build()?;
         spawn_updater(&filter, req.clone());
-        let tiles_cache = cache::TilesCache::new(TILES_CACHE_INITIAL_CAPACITY);
+        let adm_requester: Arc<dyn AdmRequester> = Arc::new(ReqwestAdmRequester::new(
+            req.clone(),
+            Duration::from_secs(settings.adm_timeout),
+            settings.adm_max_retries,
+            settings.adm_retry_base_ms,
+        ));
+        let tile_store: Arc<dyn cache::TileStore> = match settings.tiles_cache_backend {
+            TileCacheBackend::Memory => {
+                Arc::new(cache::InMemoryTileStore::new(TILES_CACHE_INITIAL_CAPACITY))
+            }
+            TileCacheBackend::Disk => {
+                let dir = settings.tiles_cache_disk_path.clone().ok_or_else(|| {
+                    HandlerError::internal(
+                        "tiles_cache_disk_path must be set when tiles_cache_backend is Disk",
+                    )
+                })?;
+                Arc::new(cache::DiskTileStore::new(dir).map_err(|e| {
+                    HandlerError::internal(&format!(
+                        "Could not initialize disk tile cache: {:?}",
+                        e
+                    ))
+                })?)
+            }
+        };
+        let tiles_cache = cache::TilesCache::new(
+            tile_store,
+            settings.tiles_cache_max_entries,
+            settings.tiles_cache_max_bytes,
+        );
         let img_store = ImageStore::create(&settings, &metrics, &req).await?;
         let excluded_dmas = if let Some(exclude_dmas) = &settings.exclude_dma {
             serde_json::from_str(exclude_dmas).map_err(|e| {
@@ -124,15 +244,31 @@ impl Server {
         } else {
             None
         };
+        let rate_limiter = RateLimiter::from_settings(&settings);
+        let crawler_filter = CrawlerFilter::from_json(settings.crawler_ua_patterns.as_deref())?;
+        let ip_blocklist = IpBlocklist::from_json(settings.blocked_cidrs.as_deref())?;
+        let raw_sov = HandlerResult::<SOVManager>::from(&mut settings)?;
+        let sov_manager = Arc::new(tokio::sync::RwLock::new(raw_sov));
+        crate::sov::spawn_updater(
+            Duration::from_secs(settings.sov_refresh_rate_secs),
+            &sov_manager,
+            Arc::new(cloud_storage::Client::default()),
+            Arc::new(metrics.clone()),
+        )?;
         let state = ServerState {
             metrics: Box::new(metrics.clone()),
             reqwest_client: req,
+            adm_requester,
             tiles_cache: tiles_cache.clone(),
             settings: settings.clone(),
             filter,
             img_store,
             excluded_dmas,
             start_up: Instant::now(),
+            rate_limiter,
+            sov_manager,
+            crawler_filter,
+            ip_blocklist,
         };
         let location_config = location_config_from_settings(&settings, &metrics);
 
@@ -0,0 +1,214 @@
+//! Per-client token-bucket rate limiting
+//!
+//! Guards [crate::web::handlers::get_tiles] against a flood of requests from
+//! a single source (e.g. a botnet hammering `/v1/tiles` with one repeated
+//! User-Agent). Clients are keyed by their remote IP (optionally combined
+//! with the raw `User-Agent` string) and tracked in a
+//! sharded concurrent map so the cost of the check stays well below the cost
+//! of the request it's guarding.
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::settings::Settings;
+
+/// Arbitrary initial capacity, similar in spirit to
+/// `server::TILES_CACHE_INITIAL_CAPACITY`
+const RATE_LIMIT_INITIAL_CAPACITY: usize = 768;
+
+/// Safe floor for `rate`: guards `1.0 / rate` in [RateLimiter::check]
+/// against becoming infinite (or, via a negative/NaN `rate_limit_rps`,
+/// negative/NaN) when a deployment misconfigures the rate to `<= 0` --
+/// either of which would otherwise panic in `Duration::from_secs_f64` on
+/// every request through [crate::web::handlers::get_tiles].
+const RATE_LIMIT_MIN_RPS: f64 = 0.001;
+
+/// Identifies a rate limited client.
+///
+/// Built from the request's remote IP and, if
+/// [`Settings::rate_limit_key_ua`] is set, the raw `User-Agent` string. If
+/// [`Settings::rate_limit_key_country`] is set, `ip` instead holds the
+/// resolved country code (e.g. `"US"`) -- a coarser key shared by every
+/// client in that country, rather than one bucket per IP.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ClientKey {
+    ip: String,
+    ua: Option<String>,
+}
+
+impl ClientKey {
+    pub fn new(ip: String, ua: Option<String>) -> Self {
+        Self { ip, ua }
+    }
+}
+
+/// A simple token bucket: refills continuously at `rate` tokens/sec, up to
+/// `burst` tokens, and is charged one token per allowed request.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: f64) -> Self {
+        Self {
+            tokens: burst,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Refill, then try to take a single token. Returns `true` if the
+    /// request is allowed.
+    fn try_take(&mut self, rate: f64, burst: f64) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_refill.elapsed()
+    }
+}
+
+/// Sharded, concurrent token-bucket rate limiter.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    buckets: std::sync::Arc<DashMap<ClientKey, TokenBucket>>,
+    rate: f64,
+    burst: f64,
+    /// Buckets idle longer than this are evicted on access, so spoofed IPs
+    /// can't grow the map without bound.
+    bucket_ttl: Duration,
+    pub(crate) key_on_ua: bool,
+    /// When set, callers should key [ClientKey] on `location.country()`
+    /// instead of the client IP -- see [Settings::rate_limit_key_country].
+    pub(crate) key_on_country: bool,
+}
+
+impl RateLimiter {
+    pub fn new(
+        rate: f64,
+        burst: f64,
+        bucket_ttl: Duration,
+        key_on_ua: bool,
+        key_on_country: bool,
+    ) -> Self {
+        Self {
+            buckets: std::sync::Arc::new(DashMap::with_capacity(RATE_LIMIT_INITIAL_CAPACITY)),
+            rate: rate.max(RATE_LIMIT_MIN_RPS),
+            burst,
+            bucket_ttl,
+            key_on_ua,
+            key_on_country,
+        }
+    }
+
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        if !settings.rate_limit_enabled {
+            return None;
+        }
+        Some(Self::new(
+            settings.rate_limit_rps,
+            settings.rate_limit_burst,
+            Duration::from_secs(settings.rate_limit_bucket_ttl_secs),
+            settings.rate_limit_key_ua,
+            settings.rate_limit_key_country,
+        ))
+    }
+
+    /// Check (and consume) a token for `key`. Returns `Some(retry_after)` if
+    /// the request should be rejected.
+    pub fn check(&self, key: &ClientKey) -> Option<Duration> {
+        self.evict_idle(key);
+        let mut bucket = self
+            .buckets
+            .entry(key.clone())
+            .or_insert_with(|| TokenBucket::new(self.burst));
+        if bucket.try_take(self.rate, self.burst) {
+            None
+        } else {
+            // Enough time for at least one token to become available again.
+            Some(Duration::from_secs_f64((1.0 / self.rate).max(1.0)))
+        }
+    }
+
+    /// Lazily drop this client's bucket if it's gone unused for longer than
+    /// `bucket_ttl`, so a flood of spoofed IPs can't grow the map forever.
+    fn evict_idle(&self, key: &ClientKey) {
+        self.buckets.remove_if(key, |_, bucket| {
+            bucket.idle_for() > self.bucket_ttl
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_within_burst() {
+        let limiter = RateLimiter::new(1.0, 3.0, Duration::from_secs(60), false, false);
+        let key = ClientKey::new("127.0.0.1".to_owned(), None);
+        assert!(limiter.check(&key).is_none());
+        assert!(limiter.check(&key).is_none());
+        assert!(limiter.check(&key).is_none());
+    }
+
+    #[test]
+    fn test_rejects_over_burst() {
+        let limiter = RateLimiter::new(0.001, 1.0, Duration::from_secs(60), false, false);
+        let key = ClientKey::new("127.0.0.1".to_owned(), None);
+        assert!(limiter.check(&key).is_none());
+        assert!(limiter.check(&key).is_some());
+    }
+
+    #[test]
+    fn test_distinct_clients_have_distinct_buckets() {
+        let limiter = RateLimiter::new(0.001, 1.0, Duration::from_secs(60), false, false);
+        let a = ClientKey::new("127.0.0.1".to_owned(), None);
+        let b = ClientKey::new("127.0.0.2".to_owned(), None);
+        assert!(limiter.check(&a).is_none());
+        assert!(limiter.check(&b).is_none());
+    }
+
+    #[test]
+    fn test_key_on_country_shares_a_bucket_across_ips() {
+        let limiter = RateLimiter::new(0.001, 1.0, Duration::from_secs(60), false, true);
+        assert!(limiter.key_on_country);
+        // Two distinct client IPs resolving to the same country share a
+        // bucket when `key_on_country` is set (callers build the key from
+        // `location.country()` rather than the IP in that case).
+        let key = ClientKey::new("US".to_owned(), None);
+        assert!(limiter.check(&key).is_none());
+        assert!(limiter.check(&key).is_some());
+    }
+
+    #[test]
+    fn test_non_positive_rate_does_not_panic() {
+        // A misconfigured `rate_limit_rps` of `<= 0` (or NaN) must not reach
+        // `Duration::from_secs_f64` as `1.0 / rate` -- `new` clamps it to
+        // `RATE_LIMIT_MIN_RPS` instead of panicking on the first rejection.
+        for rate in [0.0, -1.0, f64::NAN, f64::NEG_INFINITY] {
+            let limiter = RateLimiter::new(rate, 1.0, Duration::from_secs(60), false, false);
+            let key = ClientKey::new("127.0.0.1".to_owned(), None);
+            assert!(limiter.check(&key).is_none());
+            assert!(limiter.check(&key).is_some());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Safe redirect following for outbound ADM/image fetches.
+//!
+//! `reqwest` already resolves a `Location` header against the request that
+//! produced it before a [reqwest::redirect::Policy] ever sees it -- [resolve]
+//! mostly exists so the resolution itself (absolute, protocol-relative,
+//! absolute-path, and relative references, per RFC 3986 §4.2) is directly
+//! unit-testable. [safe_policy] is the part that actually matters at fetch
+//! time: it caps the redirect chain length, refuses to downgrade a chain
+//! from `https` to `http`, and -- when given a `host_allowed` predicate --
+//! re-checks every hop's host, so a redirect can't walk a fetch off the
+//! hosts it was configured to trust.
+
+use reqwest::{redirect::Policy, Url};
+
+/// Resolve a (possibly relative/protocol-relative) `Location` header value
+/// against `base`: an absolute URL (`http://`/`https://`) is used as-is, a
+/// protocol-relative reference (`//host/path`) inherits `base`'s scheme, and
+/// an absolute-path (`/path`) or relative reference is joined onto `base`.
+/// This is exactly the merge [Url::join] already implements.
+pub(crate) fn resolve(base: &Url, location: &str) -> Option<Url> {
+    base.join(location).ok()
+}
+
+/// Whether following a redirect from `from` to `to` would downgrade the
+/// connection from `https` to a non-`https` scheme.
+fn is_downgrade(from: &Url, to: &Url) -> bool {
+    from.scheme() == "https" && to.scheme() != "https"
+}
+
+/// Build a [Policy] that follows at most `max_redirects` hops, refuses to
+/// downgrade `https` -> `http` partway through a chain, and drops any hop
+/// whose resolved host `host_allowed` rejects.
+pub(crate) fn safe_policy(
+    max_redirects: usize,
+    host_allowed: impl Fn(&str) -> bool + Send + Sync + 'static,
+) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() >= max_redirects {
+            return attempt.error("too many redirects");
+        }
+        if let Some(previous) = attempt.previous().last() {
+            if is_downgrade(previous, attempt.url()) {
+                return attempt.error("refusing to follow a redirect from https to http");
+            }
+        }
+        match attempt.url().host_str() {
+            Some(host) if host_allowed(host) => attempt.follow(),
+            _ => attempt.error("redirect host not in allow-list"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve;
+    use reqwest::Url;
+
+    fn base() -> Url {
+        Url::parse("https://cdn.example.com/tiles/a/b.jpg").unwrap()
+    }
+
+    #[test]
+    fn resolve_absolute() {
+        let resolved = resolve(&base(), "http://other.example.com/c.jpg").unwrap();
+        assert_eq!(resolved.as_str(), "http://other.example.com/c.jpg");
+    }
+
+    #[test]
+    fn resolve_protocol_relative() {
+        let resolved = resolve(&base(), "//other.example.com/c.jpg").unwrap();
+        assert_eq!(resolved.as_str(), "https://other.example.com/c.jpg");
+    }
+
+    #[test]
+    fn resolve_absolute_path() {
+        let resolved = resolve(&base(), "/c.jpg").unwrap();
+        assert_eq!(resolved.as_str(), "https://cdn.example.com/c.jpg");
+    }
+
+    #[test]
+    fn resolve_relative() {
+        let resolved = resolve(&base(), "c.jpg").unwrap();
+        assert_eq!(resolved.as_str(), "https://cdn.example.com/tiles/a/c.jpg");
+    }
+}
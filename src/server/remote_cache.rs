@@ -1,13 +1,24 @@
-use redis::Commands;
+use std::time::Duration;
+
+use cadence::{CountedExt, StatsdClient};
+use deadpool_redis::{Config as RedisConfig, Pool, PoolConfig, Runtime, Timeouts};
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, json};
 
 use crate::error::{HandlerError, HandlerResult};
 use crate::settings::Settings;
 
+/// Shared, pooled handle to the Redis-backed image cache.
+///
+/// Every clone of this handle shares the same connection pool, so unlike a
+/// raw `redis::Client::get_connection()` (a blocking, synchronous
+/// connection opened fresh per call) a lookup no longer serializes onto a
+/// new TCP handshake every time it runs on an async actix worker thread.
 #[derive(Clone, Debug)]
 pub struct RemoteImageCache {
-    client: redis::Client,
+    pool: Pool,
+    cadence_metrics: StatsdClient,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -23,37 +34,98 @@ pub struct CacheValue {
 }
 
 impl RemoteImageCache {
-    pub fn new(settings: &Settings) -> HandlerResult<Self> {
-        let client = redis::Client::open(settings.redis_server.clone())
+    pub fn new(settings: &Settings, cadence_metrics: &StatsdClient) -> HandlerResult<Self> {
+        let mut config = RedisConfig::from_url(settings.redis_server.clone());
+        let timeout = Duration::from_secs(settings.redis_connection_timeout_secs);
+        config.pool = Some(PoolConfig {
+            max_size: settings.redis_pool_max_size,
+            timeouts: Timeouts {
+                wait: Some(timeout),
+                create: Some(timeout),
+                recycle: Some(timeout),
+            },
+            ..Default::default()
+        });
+        let pool = config
+            .create_pool(Some(Runtime::Tokio1))
             .map_err(|e| HandlerError::internal(&e.to_string()))?;
-        Ok(Self { client })
+        Ok(Self {
+            pool,
+            cadence_metrics: cadence_metrics.clone(),
+        })
     }
 
-    pub fn put(self, key: &str, value: CacheValue) -> HandlerResult<()> {
+    /// Write `value`, overwriting any prior entry, and expire it after
+    /// `ttl_secs` -- see [Settings::cache_image_available_ttl_secs] for the
+    /// `Available` state this is normally used for.
+    pub async fn put(&self, key: &str, value: CacheValue, ttl_secs: u64) -> HandlerResult<()> {
         let mut conn = self
-            .client
-            .get_connection()
+            .pool
+            .get()
+            .await
             .map_err(|e| HandlerError::internal(&e.to_string()))?;
-        conn.set(key, json!(value).to_string())
+        conn.set_ex(key, json!(value).to_string(), ttl_secs as usize)
+            .await
             .map_err(|e| HandlerError::internal(&e.to_string()))?;
         Ok(())
     }
 
-    pub fn get(self, key: &str) -> HandlerResult<Option<CacheValue>> {
+    /// Atomically claim the `Pending` slot for `key` only if no entry
+    /// already exists there (`SET key value NX EX ttl_secs`), so exactly
+    /// one caller performs the expensive upload while every other
+    /// concurrent caller short-circuits instead of stampeding it. Pair
+    /// `ttl_secs` with [Settings::cache_image_pending_ttl_secs] (e.g. the
+    /// upload timeout), so a lock orphaned by a worker that crashes
+    /// mid-upload self-heals once it expires rather than wedging the key
+    /// in `Pending` forever.
+    ///
+    /// Returns `true` if this call won the claim, `false` if another
+    /// caller already holds it.
+    pub async fn put_with_ttl(
+        &self,
+        key: &str,
+        value: CacheValue,
+        ttl_secs: u64,
+    ) -> HandlerResult<bool> {
         let mut conn = self
-            .client
-            .get_connection()
+            .pool
+            .get()
+            .await
             .map_err(|e| HandlerError::internal(&e.to_string()))?;
-        let result: String = match conn.get(key) {
-            Ok(v) => v,
-            Err(e) => {
-                dbg!(e);
-                "".to_owned()
-            }
-        };
-        if result.is_empty() {
-            return Ok(None);
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(json!(value).to_string())
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| HandlerError::internal(&e.to_string()))?;
+        let won = result.is_some();
+        if won {
+            self.cadence_metrics.incr("cache.image.lock_won").ok();
+        } else {
+            self.cadence_metrics.incr("cache.image.lock_lost").ok();
         }
+        Ok(won)
+    }
+
+    /// Returns `Ok(None)` for a genuine cache miss (no such key), and
+    /// `Err` when Redis itself couldn't be reached or returned malformed
+    /// data, so a down cache is distinguishable from "never populated".
+    pub async fn get(&self, key: &str) -> HandlerResult<Option<CacheValue>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| HandlerError::internal(&e.to_string()))?;
+        let result: Option<String> = conn
+            .get(key)
+            .await
+            .map_err(|e| HandlerError::internal(&e.to_string()))?;
+        let Some(result) = result else {
+            return Ok(None);
+        };
         Ok(Some(from_str::<CacheValue>(&result).map_err(|e| {
             HandlerError::internal(&format!(
                 "Could not deserialize shared cache entry: {} {:?}",
@@ -62,12 +134,96 @@ impl RemoteImageCache {
         })?))
     }
 
-    pub fn del(self, key: &str) -> HandlerResult<()> {
+    pub async fn del(&self, key: &str) -> HandlerResult<()> {
         let mut conn = self
-            .client
-            .get_connection()
+            .pool
+            .get()
+            .await
             .map_err(|e| HandlerError::internal(&e.to_string()))?;
         conn.del(key)
+            .await
             .map_err(|e| HandlerError::internal(&e.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cadence::{NopMetricSink, StatsdClient};
+    use rand::Rng;
+
+    use super::*;
+
+    fn test_cadence_metrics() -> StatsdClient {
+        StatsdClient::builder("", NopMetricSink).build()
+    }
+
+    fn test_cache() -> RemoteImageCache {
+        let settings = Settings {
+            redis_server: std::env::var("CONTILE_TEST_REDIS_URL")
+                .unwrap_or_else(|_| "redis://127.0.0.1/".to_owned()),
+            ..Default::default()
+        };
+        RemoteImageCache::new(&settings, &test_cadence_metrics()).unwrap()
+    }
+
+    fn test_key(label: &str) -> String {
+        format!("contile-test-{}-{}", label, rand::thread_rng().gen::<u64>())
+    }
+
+    /// `true` if a real Redis is reachable at the configured test URL, so
+    /// tests needing one can skip cleanly where it isn't (e.g. CI without a
+    /// Redis service) -- same convention as `img_storage.rs`'s
+    /// `GOOGLE_APPLICATION_CREDENTIALS` check.
+    async fn redis_available(cache: &RemoteImageCache) -> bool {
+        cache.get(&test_key("connectivity-probe")).await.is_ok()
+    }
+
+    #[actix_web::test]
+    async fn test_put_with_ttl_single_flight() {
+        let cache = test_cache();
+        if !redis_available(&cache).await {
+            println!("Skipping test: No redis available.");
+            return;
+        }
+        let key = test_key("lock");
+        let value = CacheValue {
+            state: CacheState::Pending,
+            data: None,
+        };
+
+        let won = cache
+            .put_with_ttl(&key, value.clone(), 30)
+            .await
+            .unwrap();
+        assert!(won, "first claim on an absent key should win the lock");
+
+        let lost = cache.put_with_ttl(&key, value, 30).await.unwrap();
+        assert!(!lost, "second claim on an already-locked key should lose");
+
+        cache.del(&key).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_get_returns_none_for_missing_key() {
+        let cache = test_cache();
+        if !redis_available(&cache).await {
+            println!("Skipping test: No redis available.");
+            return;
+        }
+        assert!(cache.get(&test_key("missing")).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_get_errors_when_redis_down() {
+        // Port 1 is reserved and nothing listens there, so the connection
+        // is refused immediately -- this should surface as an `Err`, not
+        // the `Ok(None)` a genuine cache miss returns.
+        let settings = Settings {
+            redis_server: "redis://127.0.0.1:1/".to_owned(),
+            redis_connection_timeout_secs: 1,
+            ..Default::default()
+        };
+        let cache = RemoteImageCache::new(&settings, &test_cadence_metrics()).unwrap();
+        assert!(cache.get(&test_key("down")).await.is_err());
+    }
+}
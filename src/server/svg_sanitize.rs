@@ -0,0 +1,115 @@
+//! SVG upload sanitization
+//!
+//! ADM's `image_url` is attacker-adjacent: anything it points to gets
+//! uploaded straight to Contile's own CDN bucket. Raster formats get their
+//! pixels decoded (and nothing else) by the `image` crate, but an SVG is
+//! just XML a browser will happily execute `<script>` in or use to probe
+//! the filesystem via an entity/DOCTYPE declaration. Reject those outright,
+//! then hand the rest to `usvg` to parse and measure -- if `usvg` can't
+//! make sense of it, neither should a client's renderer.
+
+use actix_web::web::Bytes;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::error::{HandlerError, HandlerErrorKind, HandlerResult};
+
+/// Reject constructs that have no business in a tile icon: inline
+/// scripting, a DOCTYPE/entity declaration (a classic XXE/entity-expansion
+/// vector), an `on*` event-handler attribute (`onload`, `onerror`, ... --
+/// the most common real-world SVG XSS vector, since these fire even
+/// without a `<script>` element in sight), or an `href`/`xlink:href`
+/// pointing outside the document itself (fragment references like
+/// `href="#foo"` are the only ones an icon needs).
+fn reject_unsafe_constructs(svg: &str) -> HandlerResult<()> {
+    lazy_static! {
+        /// Matches `on<name>=` (e.g. `onload=`, `onerror=`), regardless of
+        /// surrounding whitespace, so attribute-level event handlers don't
+        /// slip past the `<script>` check.
+        static ref EVENT_HANDLER_ATTR: Regex = Regex::new(r"\son[a-z]+\s*=").unwrap();
+    }
+    let lower = svg.to_lowercase();
+    if lower.contains("<script") {
+        return Err(HandlerErrorKind::BadImage("SVG contains a <script> element").into());
+    }
+    if lower.contains("<!doctype") || lower.contains("<!entity") {
+        return Err(HandlerErrorKind::BadImage("SVG contains a DOCTYPE/entity declaration").into());
+    }
+    if EVENT_HANDLER_ATTR.is_match(&lower) {
+        return Err(HandlerErrorKind::BadImage("SVG contains an on* event-handler attribute").into());
+    }
+    for attr in ["href=\"", "href='"] {
+        let mut rest = lower.as_str();
+        while let Some(pos) = rest.find(attr) {
+            rest = &rest[pos + attr.len()..];
+            let quote = attr.as_bytes()[attr.len() - 1] as char;
+            let value = rest.split(quote).next().unwrap_or_default();
+            if !value.is_empty() && !value.starts_with('#') {
+                return Err(HandlerErrorKind::BadImage("SVG references an external href").into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sanitize and measure an SVG document, returning its intrinsic
+/// `(width, height)` in pixels as computed by `usvg` from the `viewBox` (or
+/// explicit `width`/`height`), so [crate::server::img_storage::ImageStore::validate]'s
+/// symmetric/min/max checks apply to SVGs the same as any raster format.
+pub fn sanitize(bytes: &Bytes) -> HandlerResult<(u32, u32)> {
+    let text: &str = std::str::from_utf8(bytes).map_err(|_| -> HandlerError {
+        HandlerErrorKind::BadImage("SVG is not valid UTF-8").into()
+    })?;
+    reject_unsafe_constructs(text)?;
+
+    let tree = usvg::Tree::from_data(bytes, &usvg::Options::default())
+        .map_err(|e| HandlerError::internal(&format!("Could not parse SVG: {:?}", e)))?;
+    let size = tree.size();
+    Ok((size.width().round() as u32, size.height().round() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> Bytes {
+        Bytes::from(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 96 96">{}</svg>"#,
+            body
+        ))
+    }
+
+    #[test]
+    fn test_rejects_script() {
+        assert!(sanitize(&svg("<script>alert(1)</script>")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_doctype() {
+        let doc = Bytes::from_static(
+            br#"<!DOCTYPE svg [<!ENTITY xxe SYSTEM "file:///etc/passwd">]><svg></svg>"#,
+        );
+        assert!(sanitize(&doc).is_err());
+    }
+
+    #[test]
+    fn test_rejects_external_href() {
+        assert!(sanitize(&svg(r#"<use href="https://evil.example/x.svg"/>"#)).is_err());
+    }
+
+    #[test]
+    fn test_rejects_event_handler_attribute() {
+        assert!(sanitize(&svg(r#"<rect onload="alert(1)"/>"#)).is_err());
+        assert!(sanitize(&svg(r#"<rect onerror='alert(1)'/>"#)).is_err());
+    }
+
+    #[test]
+    fn test_allows_internal_href() {
+        let result = sanitize(&svg(
+            r##"<defs><rect id="r" width="1" height="1"/></defs><use href="#r"/>"##,
+        ));
+        assert!(result.is_ok());
+        let (width, height) = result.unwrap();
+        assert_eq!((width, height), (96, 96));
+    }
+}
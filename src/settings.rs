@@ -34,6 +34,31 @@ impl std::fmt::Display for TestModes {
     }
 }
 
+/// Which [crate::server::cache::TileStore] implementation backs the tile
+/// cache.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TileCacheBackend {
+    /// In-process only, see [crate::server::cache::InMemoryTileStore]
+    /// (cold-starts on every restart)
+    Memory,
+    /// Persists to `tiles_cache_disk_path`, see
+    /// [crate::server::cache::DiskTileStore]
+    Disk,
+}
+
+impl std::fmt::Display for TileCacheBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Memory => "Memory",
+                Self::Disk => "Disk",
+            }
+        )
+    }
+}
+
 /// Configuration settings and options
 ///
 /// Each of these can be specified as an environment variable by
@@ -66,6 +91,24 @@ pub struct Settings {
     pub tiles_fallback_ttl: u32,
     /// path to MaxMind location database
     pub maxminddb_loc: Option<PathBuf>,
+    /// Ordered, non-test location providers to wire in, tried in turn until
+    /// one resolves a [actix_web_location::Location]: `"maxmind"` (reads
+    /// `maxminddb_loc`), `"header"` (trust an upstream load-balancer's geo
+    /// header, see `geo_header`), `"cloudfront_header"` (parse standard
+    /// CDN/edge geo headers, e.g. CloudFront's `CloudFront-Viewer-Country`
+    /// family or an `X-Client-Geo` CSV header), `"http"` (look up each
+    /// request's IP against `geo_api_url`), or `"fallback"` (always resolves
+    /// to `fallback_country`). (default: `["maxmind", "fallback"]`)
+    pub location_providers: Vec<String>,
+    /// Upstream load-balancer header carrying `country,region,dma` for the
+    /// `"header"` location provider (default: None)
+    pub geo_header: Option<String>,
+    /// URL template for the `"http"` location provider; `{ip}` is replaced
+    /// with the request's remote IP (default: None)
+    pub geo_api_url: Option<String>,
+    /// How long a `"http"` location provider lookup is cached per-IP, in
+    /// seconds (default: 60)
+    pub geo_api_ttl: u64,
     /// A JSON formatted string of [StorageSettings] related to
     /// the Google Cloud Storage
     pub storage: String,
@@ -83,6 +126,13 @@ pub struct Settings {
     pub documentation_url: String,
     /// Operational trace header
     pub trace_header: Option<String>,
+    /// OTLP/gRPC collector endpoint (e.g. `http://localhost:4317`) spans are
+    /// exported to (default: None, disabled)
+    pub otlp_endpoint: Option<String>,
+    /// Fraction (0.0-1.0) of requests sampled for OTLP tracing (default: 1.0)
+    pub otlp_sample_ratio: f32,
+    /// `service.name` resource attribute reported to the OTLP collector
+    pub otlp_service_name: String,
     /// a JSON list of location DMAs to never return (population less than 15K)
     pub exclude_dma: Option<String>,
     /// Timeout (in seconds) for only the connect phase of all outbound HTTP requests
@@ -91,6 +141,107 @@ pub struct Settings {
     pub request_timeout: u64,
     /// Whether Tiles responses may include a `Cache-Control` header
     pub cache_control_header: bool,
+    /// Enable per-client rate limiting of `/v1/tiles` (default: false)
+    pub rate_limit_enabled: bool,
+    /// Sustained requests/sec allowed per client (default: 5)
+    pub rate_limit_rps: f64,
+    /// Burst capacity (in requests) allowed per client (default: 20)
+    pub rate_limit_burst: f64,
+    /// Also key the rate limit bucket on the request's `User-Agent`, in
+    /// addition to the client IP (default: false)
+    pub rate_limit_key_ua: bool,
+    /// Key the rate limit bucket on the request's resolved country instead
+    /// of its IP -- a coarser key useful for capping aggregate traffic from
+    /// a single region rather than per-client (default: false)
+    pub rate_limit_key_country: bool,
+    /// Evict a client's bucket after this many idle seconds (default: 300)
+    pub rate_limit_bucket_ttl_secs: u64,
+    /// A JSON list of regexes matched against the request `User-Agent` to
+    /// identify known web crawlers (default: None, disabled)
+    pub crawler_ua_patterns: Option<String>,
+    /// A JSON list of CIDR ranges (e.g. `["203.0.113.0/24"]`) whose traffic
+    /// is dropped before the ADM call (default: None, disabled)
+    pub blocked_cidrs: Option<String>,
+    /// A JSON map of response header name to value (e.g.
+    /// `{"X-Content-Type-Options": "nosniff"}`) applied to every Tiles
+    /// response (default: None, disabled)
+    pub response_headers: Option<String>,
+    /// Convenience flag adding a `Strict-Transport-Security` response
+    /// header with this `max-age` (in seconds) (default: None, disabled)
+    pub hsts_max_age: Option<u64>,
+    /// `Content-Security-Policy` header value baked onto every outbound
+    /// response (alongside the configurable `X-Content-Type-Options`,
+    /// `Permissions-Policy`, `X-Frame-Options` and `Referrer-Policy`
+    /// hardening headers), so contile has a safe security posture even when
+    /// run without a wrapping proxy. Set to `""` to omit the CSP header
+    /// entirely.
+    pub content_security_policy: String,
+    /// `X-Content-Type-Options` header value (default: `Some("nosniff")`).
+    /// Set to `None` to omit the header entirely.
+    pub x_content_type_options: Option<String>,
+    /// `Permissions-Policy` header value (default: a locked-down set of
+    /// browser features). Set to `None` to omit the header entirely.
+    pub permissions_policy: Option<String>,
+    /// `X-Frame-Options` header value (default: `Some("DENY")`). Set to
+    /// `None` to omit the header entirely.
+    pub x_frame_options: Option<String>,
+    /// `Referrer-Policy` header value (default: `Some("no-referrer")`). Set
+    /// to `None` to omit the header entirely.
+    pub referrer_policy: Option<String>,
+    /// A JSON list of request paths (e.g. `["/__loc_test__"]`) that never
+    /// get the hardening headers above -- on top of the dockerflow
+    /// endpoints, which are always exempt (default: a JSON list containing
+    /// just `/__loc_test__`, an internal debugging endpoint not meant to be
+    /// rendered as a document)
+    pub security_headers_skip_paths: String,
+    /// A JSON list of origins (e.g. `["https://example.com"]`) allowed to
+    /// fetch `/v1/tiles` cross-origin. A request from an origin not on this
+    /// list simply gets no CORS headers back, rather than being rejected
+    /// (default: `[]`, no origin gets CORS headers)
+    pub cors_allowed_origins: String,
+    /// `Access-Control-Max-Age` (in seconds) advertised on a CORS preflight
+    /// response (default: 3600)
+    pub cors_max_age_secs: usize,
+    /// Consider a cache entry's `Populating` state stuck after this many
+    /// seconds and retry instead of waiting on it forever (default: 10)
+    pub tiles_populating_deadline_secs: u64,
+    /// Consider a cache entry's `Refreshing` state stuck after this many
+    /// seconds and retry instead of serving stale tiles forever (default: 10)
+    pub tiles_refreshing_deadline_secs: u64,
+    /// Grace window (in seconds) after a `Fresh` entry's `max-age` expires
+    /// during which `get_tiles` serves the stale response immediately and
+    /// refreshes it in the background, rather than blocking the request on
+    /// the ADM fetch. Also emitted as the `Cache-Control`
+    /// `stale-while-revalidate` directive (default: 30)
+    pub tiles_stale_while_revalidate_secs: u64,
+    /// Cap the tile cache at this many entries: once exceeded, the periodic
+    /// reporter evicts the least-recently-used entries (never a `Populating`
+    /// or `Refreshing` one) until back under budget (default: None,
+    /// unbounded)
+    pub tiles_cache_max_entries: Option<usize>,
+    /// Cap the tile cache's approximate total byte footprint (summed
+    /// [crate::server::cache::TilesContent] size): once exceeded, the
+    /// periodic reporter evicts the least-recently-used entries the same way
+    /// as `tiles_cache_max_entries` (default: None, unbounded)
+    pub tiles_cache_max_bytes: Option<usize>,
+    /// Which [crate::server::cache::TileStore] backs the tile cache
+    /// (default: Memory)
+    pub tiles_cache_backend: TileCacheBackend,
+    /// Directory the disk-backed [crate::server::cache::TileStore] persists
+    /// entries under. Required when `tiles_cache_backend` is `Disk`
+    /// (default: None)
+    pub tiles_cache_disk_path: Option<PathBuf>,
+    /// Bearer token required by the `/__dump__/*` introspection endpoints.
+    /// The endpoints are disabled entirely (404) when this is unset
+    /// (default: None)
+    pub admin_dump_token: Option<String>,
+    /// Bcrypt hash of the token required by the `/admin/advertisers` and
+    /// `/admin/reload` mutation endpoints (see
+    /// [crate::adm::admin_api]) -- callers present the raw token in an
+    /// `X-Api-Token` header, which is verified against this hash with
+    /// constant-time bcrypt comparison. The endpoints reject every request
+    /// with 401 when this is unset (default: None)
+    pub admin_token_hash: Option<String>,
 
     // TODO: break these out into a PartnerSettings?
     /// Adm partner ID (default: "demofeed")
@@ -111,12 +262,40 @@ pub struct Settings {
     pub adm_query_tile_count: u8,
     /// Timeout requests to the ADM server after this many seconds (default: 5)
     pub adm_timeout: u64,
-    /// ADM tile settings (either as JSON, a path to a JSON file, or a Google Storage url)
+    /// Max number of tile images to fetch/store concurrently per `get_tiles`
+    /// request (default: 5)
+    pub adm_image_concurrency: usize,
+    /// Max number of retries for a transient ADM request failure (connection
+    /// error, timeout, or a 502/503/504) before giving up (default: 3)
+    pub adm_max_retries: u32,
+    /// Base delay, in milliseconds, for the exponential backoff between ADM
+    /// retries (default: 100)
+    pub adm_retry_base_ms: u64,
+    /// Max number of redirect hops to follow on an outbound ADM or image
+    /// fetch before giving up -- see [crate::server::redirect::safe_policy]
+    /// (default: 5)
+    pub redirect_max_hops: usize,
+    /// Overall deadline (in seconds) for the ADM partner fetch itself,
+    /// independent of `tiles_populating_deadline_secs`/
+    /// `tiles_refreshing_deadline_secs` (which bound how long a request
+    /// waits on the *cache entry's* state). A slow-but-not-erroring ADM
+    /// response that blows this deadline is treated like
+    /// [crate::error::HandlerErrorKind::Reqwest] timeout: serve stale/
+    /// fallback tiles if any are cached, else 204/503 (default: 5)
+    pub adm_request_timeout: u64,
+    /// ADM tile settings (either as JSON, a path to a JSON file, or a `gs://`,
+    /// `s3://`, or `file://` bucket url)
     /// This consists of an advertiser name, and the associated filter settings
     /// (e.g. ```{"Example":{"advertizer_hosts":["example.com"."example.org"]}})```)
     /// Unspecfied [crate::adm::AdmAdvertiserFilterSettings] will use Default values specified
     /// in `Default` (or the application default if not specified)
     pub adm_settings: String,
+    /// A per-environment override document (JSON or YAML, either inline or
+    /// a path, same conventions as `adm_settings`) overlaid on top of
+    /// `adm_settings`. An advertiser entry replaces the base's key-by-key;
+    /// a `{"deleted": true}` entry removes the advertiser instead. See
+    /// [crate::adm::settings::Merge].
+    pub adm_settings_override: Option<String>,
     /// Number of seconds to wait between polling ADM settings updates
     pub adm_refresh_rate_secs: u64,
     /// Check ADM settings on new tile requests.
@@ -125,8 +304,29 @@ pub struct Settings {
     pub adm_ignore_advertisers: Option<String>,
     /// a JSON list of advertisers to allow for versions of firefox less than 91.
     pub adm_has_legacy_image: Option<String>,
-    /// a JSON structure of the default ADM settings
+    /// a JSON or YAML structure of the default ADM settings
     pub adm_defaults: Option<String>,
+    /// A per-environment override document (JSON or YAML) overlaid on top
+    /// of `adm_defaults` -- non-empty lists and `Some` scalars win, empty
+    /// lists and `None` leave the base untouched. See
+    /// [crate::adm::settings::Merge].
+    pub adm_defaults_override: Option<String>,
+    /// Outbound proxy configuration for ADM partner requests (and, where
+    /// feasible, `gs://` settings fetches), as JSON. `None`/unset means
+    /// connect directly. See [crate::adm::settings::ProxyConfig] for the
+    /// accepted shapes (a single proxy for all hosts, or a per-host list).
+    pub adm_proxy: Option<String>,
+    /// S3 region for an `s3://` `adm_settings` bucket (default: `us-east-1`).
+    /// Unused for `gs://`/`file://`/inline settings.
+    pub adm_settings_s3_region: Option<String>,
+    /// Name of the environment variable holding the S3 access key for an
+    /// `s3://` `adm_settings` bucket (same convention as the tile-image
+    /// storage bucket's `s3_access_key_env`), matching the AWS default
+    /// credential chain when unset
+    pub adm_settings_s3_access_key_env: Option<String>,
+    /// Name of the environment variable holding the S3 secret key for an
+    /// `s3://` `adm_settings` bucket, matching the AWS convention when unset
+    pub adm_settings_s3_secret_key_env: Option<String>,
     /// Number of seconds to watch between polling SOV settings updates
     pub sov_refresh_rate_secs: u64,
     /// SOV settings (either as JSON, a path to a JSON file, or a Google Storage url)
@@ -135,6 +335,30 @@ pub struct Settings {
     pub sov_source: String,
     /// Percentage of overall time for fetch "jitter" (applied to `tiles_ttl` and tiles_fallback_ttl`)
     pub jitter: u8,
+    /// Redis connection URL backing [crate::server::remote_cache::RemoteImageCache]
+    /// (default: `"redis://127.0.0.1/"`)
+    pub redis_server: String,
+    /// Max number of pooled Redis connections held open by
+    /// [crate::server::remote_cache::RemoteImageCache] (default: 10)
+    pub redis_pool_max_size: usize,
+    /// Timeout (in seconds) for establishing or recycling a pooled Redis
+    /// connection before giving up (default: 5)
+    pub redis_connection_timeout_secs: u64,
+    /// TTL (in seconds) for a [crate::server::remote_cache::CacheState::Pending]
+    /// lock claimed via `put_with_ttl` -- short enough (e.g. matching the
+    /// image upload timeout) that a worker crashing mid-upload leaves behind
+    /// a lock that self-heals instead of wedging the key forever (default: 30)
+    pub cache_image_pending_ttl_secs: u64,
+    /// TTL (in seconds) for a [crate::server::remote_cache::CacheState::Available]
+    /// entry (default: 3600)
+    pub cache_image_available_ttl_secs: u64,
+    /// Coordinate tile image uploads across instances via
+    /// [crate::server::remote_cache::RemoteImageCache] instead of only the
+    /// per-process `in_flight` lock in [crate::server::img_storage::ImageStore]
+    /// (default: false, disabled). Off by default so a deployment that
+    /// hasn't provisioned a Redis instance never pays a connection attempt
+    /// on the `get_tiles` image-store path.
+    pub cache_image_remote_cache_enabled: bool,
 }
 
 impl Default for Settings {
@@ -154,6 +378,10 @@ impl Default for Settings {
             /// 3 hours
             tiles_fallback_ttl: 3 * 60 * 60,
             maxminddb_loc: None,
+            location_providers: vec!["maxmind".to_owned(), "fallback".to_owned()],
+            geo_header: None,
+            geo_api_url: None,
+            geo_api_ttl: 60,
             storage: "".to_owned(),
             test_mode: TestModes::NoTest,
             test_file_path: "./tools/test/test_data/".to_owned(),
@@ -161,11 +389,45 @@ impl Default for Settings {
             fallback_country: "US".to_owned(),
             documentation_url: "https://developer.mozilla.org/".to_owned(),
             trace_header: Some("X-Cloud-Trace-Context".to_owned()),
+            otlp_endpoint: None,
+            otlp_sample_ratio: 1.0,
+            otlp_service_name: PREFIX.to_owned(),
             // exclude for: Glendive, MT(798); Alpena, MI(583); North Platte, NE (740)
             exclude_dma: Some("[798, 583, 740]".to_owned()),
             connect_timeout: 2,
             request_timeout: 5,
             cache_control_header: true,
+            rate_limit_enabled: false,
+            rate_limit_rps: 5.0,
+            rate_limit_burst: 20.0,
+            rate_limit_key_ua: false,
+            rate_limit_key_country: false,
+            rate_limit_bucket_ttl_secs: 300,
+            crawler_ua_patterns: None,
+            blocked_cidrs: None,
+            response_headers: None,
+            hsts_max_age: None,
+            content_security_policy: "default-src 'none'; frame-ancestors 'none'".to_owned(),
+            x_content_type_options: Some("nosniff".to_owned()),
+            permissions_policy: Some(
+                "accelerometer=(), camera=(), geolocation=(), gyroscope=(), \
+                 magnetometer=(), microphone=(), payment=(), usb=()"
+                    .to_owned(),
+            ),
+            x_frame_options: Some("DENY".to_owned()),
+            referrer_policy: Some("no-referrer".to_owned()),
+            security_headers_skip_paths: r#"["/__loc_test__"]"#.to_owned(),
+            cors_allowed_origins: "[]".to_owned(),
+            cors_max_age_secs: 3600,
+            tiles_populating_deadline_secs: 10,
+            tiles_refreshing_deadline_secs: 10,
+            tiles_stale_while_revalidate_secs: 30,
+            tiles_cache_max_entries: None,
+            tiles_cache_max_bytes: None,
+            tiles_cache_backend: TileCacheBackend::Memory,
+            tiles_cache_disk_path: None,
+            admin_dump_token: None,
+            admin_token_hash: None,
             // ADM specific settings
             adm_endpoint_url: "".to_owned(),
             adm_partner_id: None,
@@ -177,7 +439,13 @@ impl Default for Settings {
             adm_mobile_max_tiles: Some(2),
             adm_query_tile_count: 10,
             adm_timeout: 5,
+            adm_image_concurrency: 5,
+            adm_max_retries: 3,
+            adm_retry_base_ms: 100,
+            redirect_max_hops: 5,
+            adm_request_timeout: 5,
             adm_settings: "".to_owned(),
+            adm_settings_override: None,
             adm_refresh_rate_secs: 300,
             adm_live_update: false,
             adm_ignore_advertisers: None,
@@ -185,10 +453,21 @@ impl Default for Settings {
                 r#"["adidas","amazon","ebay","etsy","geico","nike","samsung","wix"]"#.to_owned(),
             ),
             adm_defaults: None,
+            adm_defaults_override: None,
+            adm_proxy: None,
+            adm_settings_s3_region: None,
+            adm_settings_s3_access_key_env: None,
+            adm_settings_s3_secret_key_env: None,
             sov_refresh_rate_secs: 300,
             sov_source: "".to_owned(),
             // +/- 10% of time for jitter.
             jitter: 10,
+            redis_server: "redis://127.0.0.1/".to_owned(),
+            redis_pool_max_size: 10,
+            redis_connection_timeout_secs: 5,
+            cache_image_pending_ttl_secs: 30,
+            cache_image_available_ttl_secs: 3600,
+            cache_image_remote_cache_enabled: false,
         }
     }
 }
@@ -206,6 +485,30 @@ impl Settings {
         }
         self.fallback_country = self.fallback_country.to_uppercase();
 
+        for provider in &self.location_providers {
+            match provider.as_str() {
+                "maxmind" | "cloudfront_header" | "fallback" => (),
+                "header" if self.geo_header.is_some() => (),
+                "header" => {
+                    return Err(ConfigError::Message(
+                        "location_providers \"header\" requires geo_header".to_owned(),
+                    ))
+                }
+                "http" if self.geo_api_url.is_some() => (),
+                "http" => {
+                    return Err(ConfigError::Message(
+                        "location_providers \"http\" requires geo_api_url".to_owned(),
+                    ))
+                }
+                other => {
+                    return Err(ConfigError::Message(format!(
+                        "Invalid location_providers entry: {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+
         // preflight check the storage
         let _ = StorageSettings::from(&*self);
         Ok(())
@@ -276,6 +579,22 @@ impl Settings {
         Duration::from_secs(self.add_jitter(self.tiles_fallback_ttl) as u64)
     }
 
+    pub fn tiles_populating_deadline(&self) -> Duration {
+        Duration::from_secs(self.tiles_populating_deadline_secs)
+    }
+
+    pub fn tiles_refreshing_deadline(&self) -> Duration {
+        Duration::from_secs(self.tiles_refreshing_deadline_secs)
+    }
+
+    pub fn tiles_stale_while_revalidate(&self) -> Duration {
+        Duration::from_secs(self.tiles_stale_while_revalidate_secs)
+    }
+
+    pub fn adm_request_timeout(&self) -> Duration {
+        Duration::from_secs(self.adm_request_timeout)
+    }
+
     /// Calculate the ttl from the settings by taking the tiles_ttl and
     /// calculating a jitter that is no more than 50% of the total TTL. It is
     /// recommended that "jitter" be 10%.
@@ -2,8 +2,9 @@ use actix_web::rt;
 use base64::Engine;
 use cadence::{CountedExt, StatsdClient};
 use chrono::Utc;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{fs::read_to_string, path::Path, sync::Arc, time::Duration};
+use std::{collections::HashMap, fs::read_to_string, path::Path, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 use crate::{
@@ -62,6 +63,40 @@ impl SOVManager {
             Some(base64::engine::general_purpose::STANDARD_NO_PAD.encode(json_string.as_bytes()));
         self.last_response = Some(last_response);
     }
+
+    /// Resolve which partner wins each tile position, via weighted random
+    /// selection over that position's `Allocation` percentages: draw `r` in
+    /// `[0, total)` (where `total` is the sum of percentages for that
+    /// position, not assumed to be 100) and pick the first partner whose
+    /// running cumulative percentage exceeds `r`. Positions with no
+    /// allocations, or whose percentages sum to 0, are omitted.
+    pub fn select_partners(&self) -> HashMap<i64, String> {
+        let mut selected = HashMap::new();
+        let Some(last_response) = &self.last_response else {
+            return selected;
+        };
+        let mut rng = rand::thread_rng();
+        for position_allocation in &last_response.response.allocations {
+            let total: i64 = position_allocation
+                .allocation
+                .iter()
+                .map(|allocation| allocation.percentage)
+                .sum();
+            if total <= 0 {
+                continue;
+            }
+            let draw = rng.gen_range(0..total);
+            let mut cumulative = 0;
+            for allocation in &position_allocation.allocation {
+                cumulative += allocation.percentage;
+                if cumulative > draw {
+                    selected.insert(position_allocation.position, allocation.partner.clone());
+                    break;
+                }
+            }
+        }
+        selected
+    }
 }
 
 /// Background updater.
@@ -294,4 +329,100 @@ mod test {
         let sov_manager = HandlerResult::<SOVManager>::from(&mut settings);
         assert_eq!(sov_manager.unwrap().encoded_sov.as_deref(), Some(MOCK_SOV));
     }
+
+    fn test_manager(allocations: Vec<PositionAllocation>) -> SOVManager {
+        SOVManager {
+            refresh_rate: Duration::from_secs(300),
+            source_url: None,
+            encoded_sov: None,
+            last_response: Some(LastResponse {
+                updated: Utc::now(),
+                response: SOVResponse {
+                    name: "test".to_owned(),
+                    allocations,
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_select_partners_distribution() {
+        let manager = test_manager(vec![PositionAllocation {
+            position: 1,
+            allocation: vec![
+                Allocation {
+                    partner: "amp".to_owned(),
+                    percentage: 80,
+                },
+                Allocation {
+                    partner: "moz-sales".to_owned(),
+                    percentage: 20,
+                },
+            ],
+        }]);
+
+        let trials = 20_000;
+        let mut amp_wins = 0;
+        for _ in 0..trials {
+            if manager.select_partners().get(&1).map(String::as_str) == Some("amp") {
+                amp_wins += 1;
+            }
+        }
+        let amp_ratio = amp_wins as f64 / trials as f64;
+        assert!(
+            (amp_ratio - 0.8).abs() < 0.02,
+            "empirical amp ratio {} too far from configured 0.8",
+            amp_ratio
+        );
+    }
+
+    #[test]
+    fn test_select_partners_uses_actual_total_not_100() {
+        // Percentages don't sum to 100; the actual total (30) should be used
+        // as the denominator, so "only" should win every draw.
+        let manager = test_manager(vec![PositionAllocation {
+            position: 1,
+            allocation: vec![Allocation {
+                partner: "only".to_owned(),
+                percentage: 30,
+            }],
+        }]);
+
+        for _ in 0..100 {
+            assert_eq!(
+                manager.select_partners().get(&1).map(String::as_str),
+                Some("only")
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_partners_skips_empty_or_zero_total() {
+        let manager = test_manager(vec![
+            PositionAllocation {
+                position: 1,
+                allocation: vec![],
+            },
+            PositionAllocation {
+                position: 2,
+                allocation: vec![Allocation {
+                    partner: "amp".to_owned(),
+                    percentage: 0,
+                }],
+            },
+        ]);
+
+        assert!(manager.select_partners().is_empty());
+    }
+
+    #[test]
+    fn test_select_partners_no_response() {
+        let manager = SOVManager {
+            refresh_rate: Duration::from_secs(300),
+            source_url: None,
+            encoded_sov: None,
+            last_response: None,
+        };
+        assert!(manager.select_partners().is_empty());
+    }
 }
@@ -77,6 +77,14 @@ impl Tags {
                 }
             }
         }
+        // Parse the W3C `traceparent` header (not `Settings::trace_header`)
+        // into a parent trace/span context (if any)
+        // so the OTLP span opened in `web::middleware::otlp` stitches into
+        // the caller's trace instead of starting a new one.
+        let parent_context = crate::tracing::parent_context_from_head(req_head);
+        if let Some(trace_id) = crate::tracing::trace_id_tag(&parent_context) {
+            tags.insert("trace.id".to_owned(), trace_id);
+        }
         tags.insert("uri.method".to_owned(), req_head.method.to_string());
         // `uri.path` causes too much cardinality for influx but keep it in
         // extra for sentry
@@ -158,6 +166,18 @@ impl Tags {
         }
     }
 
+    /// Record a request's resolved [actix_web_location::Location] as low-cardinality searchable
+    /// tags (`loc.country`, `loc.region`, `loc.dma`), so a Sentry event
+    /// automatically surfaces *where* a failure happened.
+    pub fn add_location(&mut self, location: &actix_web_location::Location) {
+        self.add_tag("loc.country", &location.country());
+        self.add_tag("loc.region", &location.region());
+        let dma = location.dma();
+        if dma != 0 {
+            self.add_tag("loc.dma", &dma.to_string());
+        }
+    }
+
     /// Add an element to the "extra" data.
     ///
     /// Extra data is non-key storage used by sentry. It is not
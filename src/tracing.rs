@@ -0,0 +1,100 @@
+//! OpenTelemetry OTLP distributed tracing
+//!
+//! Contile otherwise only emits statsd metrics ([crate::metrics]) and slog
+//! output. This installs a batched OTLP/gRPC span exporter so requests can
+//! be stitched into a caller's end-to-end trace (via a W3C `traceparent`
+//! header, parsed in [crate::tags::Tags::from_head]) instead of only
+//! producing flat metrics. [`Settings::trace_header`] is not consulted when
+//! picking the parent trace.
+use std::collections::HashMap;
+
+use actix_web::dev::RequestHead;
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    sdk::{propagation::TraceContextPropagator, trace as sdktrace, Resource},
+    trace::{TraceContextExt, TraceError},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+
+use crate::{error::HandlerError, settings::Settings};
+
+/// Name of the tracer installed by [init] and looked up by
+/// [crate::web::middleware::otlp].
+pub const TRACER_NAME: &str = "contile";
+
+/// Build and install a batched OTLP/gRPC span exporter, sampled at
+/// [`Settings::otlp_sample_ratio`], and install the W3C tracecontext
+/// propagator globally. A no-op if [`Settings::otlp_endpoint`] isn't
+/// configured.
+pub fn init(settings: &Settings) -> Result<(), HandlerError> {
+    let endpoint = match &settings.otlp_endpoint {
+        Some(endpoint) => endpoint.clone(),
+        None => return Ok(()),
+    };
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config()
+                .with_sampler(sdktrace::Sampler::TraceIdRatioBased(
+                    settings.otlp_sample_ratio as f64,
+                ))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    settings.otlp_service_name.clone(),
+                )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(|e: TraceError| HandlerError::internal(&format!("OTLP init error: {:?}", e)))?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(())
+}
+
+/// Adapts actix's [`RequestHead`] headers to opentelemetry's [`Extractor`]
+/// so the global propagator (W3C tracecontext, installed by [init]) can read
+/// them.
+struct HeaderExtractor<'a>(&'a RequestHead);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.headers().get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.headers().keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Parse the request's headers with the global propagator (the W3C
+/// `traceparent` header, see [init]) into a parent [`Context`], so the span
+/// opened for this request stitches into the caller's trace instead of
+/// starting a new one. [`Settings::trace_header`] is not read here. Returns
+/// the current (empty) context if the header is absent, the propagator
+/// doesn't recognize it, or [init] installed no propagator (no
+/// `otlp_endpoint`).
+pub fn parent_context_from_head(req_head: &RequestHead) -> Context {
+    global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req_head))
+    })
+}
+
+/// A stable string identifying the trace stitched together by
+/// [parent_context_from_head], suitable for [crate::tags::Tags].
+pub fn trace_id_tag(cx: &Context) -> Option<String> {
+    let span_context = cx.span().span_context().clone();
+    span_context.is_valid().then(|| span_context.trace_id().to_string())
+}
+
+/// Convert [crate::tags::Tags]' low-cardinality `tags` map into span
+/// attributes, one [KeyValue] per entry.
+pub fn tags_to_attributes(tags: &HashMap<String, String>) -> Vec<KeyValue> {
+    tags.iter()
+        .map(|(k, v)| KeyValue::new(k.clone(), v.clone()))
+        .collect()
+}
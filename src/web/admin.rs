@@ -0,0 +1,83 @@
+//! Operational introspection endpoints
+//!
+//! These are internal-only endpoints (guarded by [crate::settings::Settings::admin_dump_token])
+//! that let on-call engineers inspect the live `tiles_cache` and `sov_manager`
+//! state without attaching a debugger, e.g. after a `BadAdmResponse` caches
+//! an unexpectedly empty tile set for some `AudienceKey`.
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    server::{cache::AudienceKey, ServerState},
+    web::{FormFactor, OsFamily},
+};
+
+/// Handles the admin introspection endpoints
+pub fn service(config: &mut web::ServiceConfig) {
+    config.service(web::resource("/__dump__/tiles").route(web::get().to(dump_tiles)));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DumpTilesParams {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub dma: Option<u16>,
+    pub form_factor: Option<String>,
+    pub os_family: Option<String>,
+    pub legacy_only: Option<bool>,
+}
+
+impl DumpTilesParams {
+    /// Build the `AudienceKey` to filter the dump by, if enough fields were
+    /// given to construct a valid one.
+    fn audience_key(&self) -> Option<AudienceKey> {
+        let country_code = self.country.clone()?;
+        let form_factor = self.form_factor.as_deref()?.parse::<FormFactor>().ok()?;
+        let os_family = self.os_family.as_deref()?.parse::<OsFamily>().ok()?;
+        Some(AudienceKey {
+            country_code,
+            region_code: self.region.clone().filter(|r| !r.is_empty()),
+            dma_code: self.dma,
+            form_factor,
+            os_family,
+            legacy_only: self.legacy_only.unwrap_or(false),
+        })
+    }
+}
+
+/// Dump the live `tiles_cache` (and, for convenience, the current SOV
+/// allocation) as JSON. Guarded by a bearer token since this exposes
+/// operational detail about live traffic; 404s (rather than 401/403) when
+/// disabled or the token doesn't match, so the endpoint's existence isn't
+/// disclosed to unauthenticated callers.
+async fn dump_tiles(
+    req: HttpRequest,
+    state: web::Data<ServerState>,
+    params: web::Query<DumpTilesParams>,
+) -> HttpResponse {
+    let Some(expected) = &state.settings.admin_dump_token else {
+        return HttpResponse::NotFound().finish();
+    };
+    let authorized = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token == expected);
+    if !authorized {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let audience_key = params.audience_key();
+    let tiles = state.tiles_cache.dump(audience_key.as_ref());
+    let sov_manager = state.sov_manager.read().await;
+    HttpResponse::Ok().json(json!({
+        "tiles": tiles,
+        "sov": {
+            "encoded_sov": sov_manager.encoded_sov,
+            "last_updated": sov_manager.last_response.as_ref().map(|r| r.updated),
+        },
+    }))
+}
@@ -1,14 +1,17 @@
 //! API Handlers
-use actix_web::{web, HttpRequest, HttpResponse};
+use std::{net::IpAddr, time::Instant};
+
+use actix_web::{rt, web, HttpRequest, HttpResponse};
 use actix_web_location::Location;
 use serde::Serialize;
 
 use crate::{
-    adm,
+    adm::{self, GetTilesOutcome},
     error::{HandlerErrorKind, HandlerResult},
     metrics::Metrics,
     server::{
         cache::{self, Tiles, TilesState},
+        rate_limit::ClientKey,
         ServerState,
     },
     settings::Settings,
@@ -38,7 +41,20 @@ pub async fn get_tiles(
     trace!("get_tiles");
     metrics.incr("tiles.get");
 
-    if let Some(response) = maybe_early_respond(&state, &location, &device_info).await {
+    // Time the whole handler regardless of which branch below returns, so we
+    // can graph end-to-end latency without scanning every request path for
+    // a return statement.
+    let _handler_timer = {
+        let start = Instant::now();
+        let metrics = metrics.clone();
+        scopeguard::guard((), move |_| {
+            metrics.timing("tiles.get.duration_ms", start.elapsed().as_millis() as u64);
+        })
+    };
+
+    if let Some(response) =
+        maybe_early_respond(&state, &location, &device_info, &request, &metrics).await
+    {
         return Ok(response);
     }
     let audience_key = cache::AudienceKey {
@@ -57,44 +73,94 @@ pub async fn get_tiles(
     let settings = &state.settings;
     let mut tags = Tags::from_head(request.head(), settings);
     {
+        tags.add_location(&location);
         tags.add_extra("audience_key", &format!("{:#?}", audience_key));
         // Add/modify the existing request tags.
         // tags.clone().commit(&mut request.extensions_mut());
     }
 
     let mut expired = false;
+    // Previous Tiles (if any) we fell through to refresh from, carried along
+    // so we can reuse its `content`/`adm_revalidation` on a `NotModified`
+    // outcome instead of treating ADM's `304` as if it had nothing to give us.
+    let mut prior_tiles: Option<Tiles> = None;
 
     if settings.test_mode != crate::settings::TestModes::TestFakeResponse {
         // First make a cheap read from the cache
-        if let Some(tiles_state) = state.tiles_cache.get(&audience_key) {
-            match &*tiles_state {
-                TilesState::Populating => {
+        if let Some(cache_entry) = state.tiles_cache.get(&audience_key) {
+            match &cache_entry.state {
+                TilesState::Populating { since } => {
                     // Another task is currently populating this entry and will
-                    // complete shortly. 304 until then instead of queueing
-                    // more redundant requests
-                    trace!("get_tiles: Another task Populating");
-                    metrics.incr("tiles_cache.miss.populating");
-                    return Ok(HttpResponse::NotModified().finish());
+                    // complete shortly. 304 until then instead of queueing more
+                    // redundant requests, unless it's been populating for
+                    // longer than the deadline, in which case it's likely
+                    // stuck and we should try again ourselves.
+                    if !cache_entry
+                        .state
+                        .stalled(settings.tiles_populating_deadline())
+                    {
+                        trace!("get_tiles: Another task Populating");
+                        metrics.incr("tiles_cache.miss.populating");
+                        return Ok(HttpResponse::NotModified().finish());
+                    }
+                    trace!("get_tiles: Populating entry stalled since {:?}", since);
+                    metrics.incr("tiles_cache.miss.populating.stalled");
                 }
                 TilesState::Fresh { tiles } => {
                     expired = tiles.expired();
                     if !expired {
                         trace!("get_tiles: cache hit: {:?}", audience_key);
                         metrics.incr("tiles_cache.hit");
-                        return Ok(tiles.to_response(settings.cache_control_header));
+                        return Ok(
+                            tiles.to_response(settings.cache_control_header, request.headers())
+                        );
+                    }
+                    if !tiles.stale_while_revalidate_expired() {
+                        // Still within the grace window: serve the stale
+                        // response immediately and refresh this entry out of
+                        // band instead of blocking this request on the ADM
+                        // fetch.
+                        trace!(
+                            "get_tiles: cache hit (expired, stale-while-revalidate): {:?}",
+                            audience_key
+                        );
+                        metrics.incr("tiles_cache.hit.stale_while_revalidate");
+                        let response =
+                            tiles.to_response(settings.cache_control_header, request.headers());
+                        spawn_stale_while_revalidate_refresh(
+                            state.clone(),
+                            audience_key,
+                            location,
+                            device_info,
+                            tags,
+                            metrics.clone(),
+                            tiles.clone(),
+                        );
+                        return Ok(response);
                     }
                     // Needs refreshing
+                    prior_tiles = Some(tiles.clone());
                 }
-                TilesState::Refreshing { tiles } => {
+                TilesState::Refreshing { tiles, since } => {
                     // Another task is currently refreshing this entry, just
-                    // return the stale Tiles until it's completed
-                    trace!(
-                        "get_tiles: cache hit (expired, Refreshing): {:?}",
-                        audience_key
-                    );
-                    metrics.incr("tiles_cache.hit.refreshing");
-                    // expired() and maybe fallback_expired()
-                    return Ok(fallback_response(settings, tiles));
+                    // return the stale Tiles until it's completed, unless
+                    // it's been refreshing for longer than the deadline, in
+                    // which case fall through and refresh it ourselves.
+                    if !cache_entry
+                        .state
+                        .stalled(settings.tiles_refreshing_deadline())
+                    {
+                        trace!(
+                            "get_tiles: cache hit (expired, Refreshing): {:?}",
+                            audience_key
+                        );
+                        metrics.incr("tiles_cache.hit.refreshing");
+                        // expired() and maybe fallback_expired()
+                        return Ok(fallback_response(settings, tiles, &request));
+                    }
+                    trace!("get_tiles: Refreshing entry stalled since {:?}", since);
+                    metrics.incr("tiles_cache.hit.refreshing.stalled");
+                    prior_tiles = Some(tiles.clone());
                 }
             }
         }
@@ -110,7 +176,7 @@ pub async fn get_tiles(
     // temporary state if no write occurs (due to errors/panics)
     let handle = state.tiles_cache.prepare_write(&audience_key, expired);
 
-    let result = adm::get_tiles(
+    let fetch = adm::get_tiles(
         &state,
         &location,
         &device_info,
@@ -122,14 +188,87 @@ pub async fn get_tiles(
         } else {
             None
         },
-    )
-    .await;
+        prior_tiles
+            .as_ref()
+            .and_then(|tiles| tiles.adm_revalidation.as_ref()),
+    );
+    let cache_state_deadline = if expired {
+        settings.tiles_refreshing_deadline()
+    } else {
+        settings.tiles_populating_deadline()
+    };
+    // Bound the fetch by whichever is tighter: the cache entry's
+    // Populating/Refreshing deadline, or `adm_request_timeout` -- a
+    // slow-but-not-erroring ADM partner shouldn't be able to hang a request
+    // just because the cache state deadline happens to be generous.
+    let adm_request_timeout = settings.adm_request_timeout();
+    let deadline = cache_state_deadline.min(adm_request_timeout);
+    let adm_start = Instant::now();
+    let result = match tokio::time::timeout(deadline, fetch).await {
+        Ok(result) => result,
+        Err(_) => {
+            // We blew our deadline: give up on this fetch and cancel any
+            // cleanup tied to it, rather than let the cache entry stay in
+            // Populating/Refreshing indefinitely.
+            trace!("get_tiles: adm fetch exceeded deadline: {:?}", &deadline);
+            if adm_request_timeout <= cache_state_deadline {
+                metrics.incr("tiles.adm.timeout");
+            } else {
+                metrics.incr("tiles_cache.deadline_exceeded");
+            }
+            handle.cancel.cancel();
+            Err(HandlerErrorKind::AdmLoadError().into())
+        }
+    };
+    {
+        // Kept separate from `tags` (which later picks up error-specific
+        // tags/reasons) so this histogram's cardinality stays fixed to the
+        // outcomes below.
+        let mut duration_tags = Tags::default();
+        duration_tags.add_tag("outcome", adm_outcome(&result));
+        metrics.timing_with_tags(
+            "tiles.adm.duration_ms",
+            adm_start.elapsed().as_millis() as u64,
+            Some(&duration_tags),
+        );
+    }
 
     match result {
-        Ok(response) => {
+        Ok(GetTilesOutcome::NotModified {
+            ttl,
+            adm_revalidation,
+        }) => {
+            let tiles = prior_tiles
+                .expect("GetTilesOutcome::NotModified without a prior cached Tiles")
+                .revalidated(
+                    ttl,
+                    settings.tiles_fallback_ttl_with_jitter(),
+                    settings.tiles_stale_while_revalidate(),
+                    adm_revalidation,
+                );
+            trace!("get_tiles: adm not modified: {:?}", &audience_key);
+            metrics.incr("tiles_cache.miss.not_modified");
+            handle.insert(TilesState::Fresh {
+                tiles: tiles.clone(),
+            });
+            Ok(tiles.to_response(settings.cache_control_header, request.headers()))
+        }
+        Ok(GetTilesOutcome::Modified {
+            response,
+            ttl,
+            adm_revalidation,
+        }) => {
             // SOV is for Desktop only for now.
             let sov_response = if matches!(device_info.form_factor, FormFactor::Desktop) {
-                state.sov_manager.read().await.encoded_sov.clone()
+                let sov_manager = state.sov_manager.read().await;
+                // Resolve (but don't yet filter on) this request's weighted
+                // partner selection, so it's available for debugging/metrics
+                // rather than treating the SOV blob as wholly opaque.
+                let selected_partners = sov_manager.select_partners();
+                if !selected_partners.is_empty() {
+                    tags.add_extra("sov.selected_partners", &format!("{:?}", selected_partners));
+                }
+                sov_manager.encoded_sov.clone()
             } else {
                 None
             };
@@ -138,8 +277,10 @@ pub async fn get_tiles(
                     tile_response: response,
                     sov_response,
                 },
-                settings.tiles_ttl_with_jitter(),
+                ttl,
                 settings.tiles_fallback_ttl_with_jitter(),
+                settings.tiles_stale_while_revalidate(),
+                adm_revalidation,
             )?;
             trace!(
                 "get_tiles: cache miss{}: {:?}",
@@ -150,7 +291,7 @@ pub async fn get_tiles(
             handle.insert(TilesState::Fresh {
                 tiles: tiles.clone(),
             });
-            Ok(tiles.to_response(settings.cache_control_header))
+            Ok(tiles.to_response(settings.cache_control_header, request.headers()))
         }
         Err(e) => {
             if matches!(e.kind(), HandlerErrorKind::BadAdmResponse(_)) {
@@ -168,6 +309,8 @@ pub async fn get_tiles(
                     tiles: Tiles::empty(
                         settings.tiles_ttl_with_jitter(),
                         settings.tiles_fallback_ttl_with_jitter(),
+                        settings.tiles_stale_while_revalidate(),
+                        None,
                     ),
                 });
                 // Report the error directly to sentry
@@ -189,25 +332,152 @@ pub async fn get_tiles(
 
             // A general error occurred, try rendering fallback Tiles
             if let Some(tiles) = handle.fallback_tiles {
-                return Ok(fallback_response(settings, &tiles));
+                return Ok(fallback_response(settings, &tiles, &request));
             }
             Err(e)
         }
     }
 }
+
+/// Refresh an expired-but-within-`tiles_stale_while_revalidate` cache entry
+/// out of band, so the request that found it stale can return the cached
+/// response immediately instead of blocking on the ADM fetch. Mirrors
+/// `get_tiles`'s own refresh branch, minus anything that needs the original
+/// request (e.g. conditional headers -- this has no response to render).
+fn spawn_stale_while_revalidate_refresh(
+    state: web::Data<ServerState>,
+    audience_key: cache::AudienceKey,
+    location: Location,
+    device_info: DeviceInfo,
+    mut tags: Tags,
+    metrics: Metrics,
+    prior_tiles: Tiles,
+) {
+    rt::spawn(async move {
+        let settings = &state.settings;
+        let handle = state.tiles_cache.prepare_write(&audience_key, true);
+        let result = adm::get_tiles(
+            &state,
+            &location,
+            &device_info,
+            &mut tags,
+            &metrics,
+            None,
+            prior_tiles.adm_revalidation.as_ref(),
+        )
+        .await;
+        match result {
+            Ok(GetTilesOutcome::NotModified {
+                ttl,
+                adm_revalidation,
+            }) => {
+                let tiles = prior_tiles.revalidated(
+                    ttl,
+                    settings.tiles_fallback_ttl_with_jitter(),
+                    settings.tiles_stale_while_revalidate(),
+                    adm_revalidation,
+                );
+                trace!("stale-while-revalidate: not modified {:?}", audience_key);
+                metrics.incr("tiles_cache.stale_while_revalidate.not_modified");
+                handle.insert(TilesState::Fresh { tiles });
+            }
+            Ok(GetTilesOutcome::Modified {
+                response,
+                ttl,
+                adm_revalidation,
+            }) => {
+                // SOV is for Desktop only for now.
+                let sov_response = if matches!(device_info.form_factor, FormFactor::Desktop) {
+                    let sov_manager = state.sov_manager.read().await;
+                    sov_manager.encoded_sov.clone()
+                } else {
+                    None
+                };
+                match cache::Tiles::new(
+                    TilesHandlerResponse {
+                        tile_response: response,
+                        sov_response,
+                    },
+                    ttl,
+                    settings.tiles_fallback_ttl_with_jitter(),
+                    settings.tiles_stale_while_revalidate(),
+                    adm_revalidation,
+                ) {
+                    Ok(tiles) => {
+                        trace!("stale-while-revalidate: refreshed {:?}", audience_key);
+                        metrics.incr("tiles_cache.stale_while_revalidate.refreshed");
+                        handle.insert(TilesState::Fresh { tiles });
+                    }
+                    Err(e) => {
+                        warn!(
+                            "stale-while-revalidate: refreshed response failed to serialize: {:?}",
+                            e
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                if matches!(e.kind(), HandlerErrorKind::BadAdmResponse(_)) {
+                    warn!("stale-while-revalidate: bad response from ADM: {:?}", e);
+                    tags.extend(e.tags.as_ref().clone());
+                    tags.add_tag("level", "warning");
+                    metrics.incr_with_tags("tiles.invalid", Some(&tags));
+                    handle.insert(TilesState::Fresh {
+                        tiles: Tiles::empty(
+                            settings.tiles_ttl_with_jitter(),
+                            settings.tiles_fallback_ttl_with_jitter(),
+                            settings.tiles_stale_while_revalidate(),
+                            None,
+                        ),
+                    });
+                    l_sentry::report(&e, &tags);
+                } else {
+                    warn!("stale-while-revalidate: refresh failed: {:?}", e);
+                    metrics.incr_with_tags("tiles.get.error", Some(&tags));
+                    // Leave `handle` to drop here: its ScopeGuard resets the
+                    // entry back to Fresh (still serving the same stale
+                    // tiles) since no `insert` occurred.
+                }
+            }
+        }
+    });
+}
+
+/// Classify an adm fetch result for the `tiles.adm.duration_ms` histogram
+fn adm_outcome(result: &HandlerResult<GetTilesOutcome>) -> &'static str {
+    match result {
+        Ok(GetTilesOutcome::Modified { .. }) => "hit",
+        Ok(GetTilesOutcome::NotModified { .. }) => "not-modified",
+        Err(e) => match e.kind() {
+            HandlerErrorKind::AdmLoadError() => "deadline-exceeded",
+            HandlerErrorKind::BadAdmResponse(_) => "bad-adm-response",
+            HandlerErrorKind::Reqwest(re) if re.is_timeout() => "timeout",
+            HandlerErrorKind::Reqwest(re) if re.is_connect() => "connect-error",
+            _ => "error",
+        },
+    }
+}
+
 /// Render stale (`expired`) fallback tiles
-fn fallback_response(settings: &Settings, tiles: &cache::Tiles) -> HttpResponse {
+fn fallback_response(
+    settings: &Settings,
+    tiles: &cache::Tiles,
+    request: &HttpRequest,
+) -> HttpResponse {
     if tiles.fallback_expired() {
         // Totally expired so no `Cache-Control` header
         HttpResponse::NoContent().finish()
     } else {
-        tiles.to_response(settings.cache_control_header)
+        tiles.to_response(settings.cache_control_header, request.headers())
     }
 }
 
 /// Check if the tile request should be responded early.
 ///
 /// This allows us to short circuit requests if:
+///   - their `User-Agent` matches a known web crawler
+///   - their source IP falls within a blocklisted CIDR range
+///   - the client has exceeded its per-client rate limit budget
 ///   - they are not sent from regions of the live markets
 ///   - they are sent from unknown device types (`form_factor == "other"`)
 ///
@@ -217,7 +487,56 @@ async fn maybe_early_respond(
     state: &web::Data<ServerState>,
     location: &Location,
     device_info: &DeviceInfo,
+    request: &HttpRequest,
+    metrics: &Metrics,
 ) -> Option<HttpResponse> {
+    let conn = request.connection_info();
+    let ua = request
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if let Some(crawler_filter) = &state.crawler_filter {
+        if crawler_filter.is_crawler(ua) {
+            trace!("get_tiles: filtered crawler UA: {:?}", ua);
+            metrics.incr("tiles.filtered.crawler");
+            return Some(HttpResponse::NoContent().finish());
+        }
+    }
+
+    if let Some(ip_blocklist) = &state.ip_blocklist {
+        if let Some(ip) = conn
+            .realip_remote_addr()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+        {
+            if ip_blocklist.contains(&ip) {
+                trace!("get_tiles: filtered blocklisted IP: {:?}", ip);
+                metrics.incr("tiles.filtered.blocked_ip");
+                return Some(HttpResponse::NoContent().finish());
+            }
+        }
+    }
+
+    if let Some(rate_limiter) = &state.rate_limiter {
+        let key_ip = if rate_limiter.key_on_country {
+            location.country()
+        } else {
+            conn.realip_remote_addr().unwrap_or("").to_owned()
+        };
+        let key_ua = rate_limiter.key_on_ua.then(|| ua.to_owned());
+        let key = ClientKey::new(key_ip, key_ua);
+        if let Some(retry_after) = rate_limiter.check(&key) {
+            trace!("get_tiles: rate limit exceeded: {:?}", &key);
+            metrics.incr("tiles.ratelimit.reject");
+            return Some(
+                HttpResponse::TooManyRequests()
+                    .insert_header(("Retry-After", retry_after.as_secs().to_string()))
+                    .finish(),
+            );
+        }
+    }
+
     if matches!(&device_info.form_factor, FormFactor::Other) {
         trace!("get_tiles: unknown form factor");
         return Some(HttpResponse::NoContent().finish());
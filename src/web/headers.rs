@@ -0,0 +1,191 @@
+//! Security/response-header middleware
+//!
+//! Stamps a baseline of hardening headers (`X-Content-Type-Options`,
+//! `Permissions-Policy`, `X-Frame-Options`, `Referrer-Policy`, and a
+//! configurable `Content-Security-Policy`) onto every outbound response, so
+//! contile has a sane security posture even when run without a wrapping
+//! proxy. Each of these is independently settable/omittable (`None` drops
+//! it). Operators can further extend or override the header set via
+//! `Settings::response_headers`, plus the `Settings::hsts_max_age`
+//! convenience flag. Skips upgrade/websocket-style requests and dockerflow
+//! endpoints, and never touches the `Cache-Control` header already managed
+//! by [crate::server::cache::Tiles::to_response].
+
+use std::task::{Context, Poll};
+use std::{cell::RefCell, rc::Rc};
+
+use actix_http::http::header::{HeaderName, HeaderValue, CACHE_CONTROL, CONNECTION, UPGRADE};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::{self, LocalBoxFuture, TryFutureExt};
+
+use crate::{settings::Settings, web::dockerflow::DOCKER_FLOW_ENDPOINTS};
+
+pub struct ResponseHeaders;
+
+impl ResponseHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for ResponseHeaders {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S> for ResponseHeaders
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseHeadersMiddleware<S>;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Box::pin(future::ok(ResponseHeadersMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        }))
+    }
+}
+
+pub struct ResponseHeadersMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+/// Parse `Settings::response_headers` (a JSON map of header name -> value)
+/// into header name/value pairs, skipping anything that doesn't parse as a
+/// valid header. A malformed setting shouldn't take the service down.
+fn configured_headers(settings: &Settings) -> Vec<(HeaderName, HeaderValue)> {
+    let raw = match settings.response_headers.as_deref() {
+        Some(raw) if !raw.is_empty() => raw,
+        _ => return Vec::new(),
+    };
+    let parsed: std::collections::HashMap<String, String> = match serde_json::from_str(raw) {
+        Ok(map) => map,
+        Err(e) => {
+            warn!("Invalid response_headers, ignoring: {:?}", e);
+            return Vec::new();
+        }
+    };
+    parsed
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+            // Never let config clobber the Cache-Control header that
+            // `Tiles::to_response` already manages.
+            if name == CACHE_CONTROL {
+                return None;
+            }
+            let value = HeaderValue::from_str(&value).ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// The hardening headers applied to every non-upgrade, non-dockerflow
+/// response. Each is independently configurable/omittable via `Settings`
+/// (`None` drops the header entirely).
+fn security_headers(settings: &Settings) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+    let configurable = [
+        ("x-content-type-options", &settings.x_content_type_options),
+        ("permissions-policy", &settings.permissions_policy),
+        ("x-frame-options", &settings.x_frame_options),
+        ("referrer-policy", &settings.referrer_policy),
+    ];
+    for (name, value) in configurable {
+        if let Some(value) = value {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.push((HeaderName::from_static(name), value));
+            }
+        }
+    }
+    if !settings.content_security_policy.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&settings.content_security_policy) {
+            headers.push((HeaderName::from_static("content-security-policy"), value));
+        }
+    }
+    headers
+}
+
+/// Parse `Settings::security_headers_skip_paths` (a JSON list of request
+/// paths) -- malformed settings just mean nothing extra is skipped, same
+/// tolerance as [configured_headers].
+fn skip_paths(settings: &Settings) -> Vec<String> {
+    serde_json::from_str(&settings.security_headers_skip_paths).unwrap_or_else(|e| {
+        warn!("Invalid security_headers_skip_paths, ignoring: {:?}", e);
+        Vec::new()
+    })
+}
+
+/// Whether this looks like an upgrade (e.g. WebSocket) request, where
+/// security headers aimed at browser document responses don't apply.
+fn is_upgrade_request(req: &ServiceRequest) -> bool {
+    req.headers().contains_key(UPGRADE)
+        || req
+            .headers()
+            .get(CONNECTION)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_lowercase().contains("upgrade"))
+            .unwrap_or(false)
+}
+
+impl<S, B> Service for ResponseHeadersMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, sreq: ServiceRequest) -> Self::Future {
+        let settings: &Settings = (&sreq).into();
+        // Dockerflow/health endpoints are polled by monitoring, not
+        // rendered by a browser -- don't risk tripping up whatever's
+        // scraping them with headers aimed at document responses. Operators
+        // can exempt additional paths (e.g. `/__loc_test__`) via
+        // `Settings::security_headers_skip_paths`.
+        if is_upgrade_request(&sreq)
+            || DOCKER_FLOW_ENDPOINTS.contains(&sreq.path())
+            || skip_paths(settings).iter().any(|path| path == sreq.path())
+        {
+            return Box::pin(self.service.call(sreq));
+        }
+
+        let mut headers = security_headers(settings);
+        headers.extend(configured_headers(settings));
+        let hsts_header = settings.hsts_max_age.and_then(|max_age| {
+            HeaderValue::from_str(&format!("max-age={}", max_age)).ok()
+        });
+
+        Box::pin(self.service.call(sreq).map_ok(move |mut sresp| {
+            let out_headers = sresp.headers_mut();
+            for (name, value) in &headers {
+                out_headers.insert(name.clone(), value.clone());
+            }
+            if let Some(value) = &hsts_header {
+                out_headers.insert(
+                    HeaderName::from_static("strict-transport-security"),
+                    value.clone(),
+                );
+            }
+            sresp
+        }))
+    }
+}
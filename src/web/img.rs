@@ -0,0 +1,124 @@
+//! Range-capable cached-image serving endpoint
+//!
+//! Streams a previously-uploaded tile image straight out of
+//! `ServerState::img_store`'s backend, in bounded ~64 KiB chunks rather than
+//! buffering the whole response body in one `Content-Length` write, honoring
+//! HTTP `Range` requests with `206 Partial Content`, and returning
+//! `304 Not Modified` when `If-None-Match` matches (`If-Modified-Since` is
+//! not consulted). This lets contile front its own processed tile images with proper
+//! caching and resumable downloads instead of relying entirely on an
+//! external CDN.
+//!
+//! Note: the storage key already embeds the image's content hash (see
+//! `ImageStore::as_hash`), so it doubles as a strong `ETag` without needing
+//! to consult the backend's own object metadata.
+
+use actix_web::{
+    http::header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE},
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+
+use crate::{error::HandlerResult, server::ServerState};
+
+/// ~64 KiB, matching actix-files' default chunk size for streamed bodies.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Handler for `GET /v1/img/{key}`
+pub async fn get_image(
+    path: web::Path<String>,
+    request: HttpRequest,
+    state: web::Data<ServerState>,
+) -> HandlerResult<HttpResponse> {
+    let key = path.into_inner();
+    let Some(img_store) = &state.img_store else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let etag = format!("\"{}\"", key);
+    if request
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == etag)
+    {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((ETAG, etag))
+            .finish());
+    }
+
+    let image = img_store.fetch_stored(&key).await?;
+    let total_len = image.len() as u64;
+    let content_type = content_type_for_key(&key);
+
+    if let Some(range) = request
+        .headers()
+        .get(RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_range(v, total_len))
+    {
+        let (start, end) = range;
+        let body = image.slice(start as usize..=end as usize);
+        return Ok(HttpResponse::PartialContent()
+            .insert_header((CONTENT_TYPE, content_type))
+            .insert_header((ETAG, etag.clone()))
+            .insert_header((ACCEPT_RANGES, "bytes"))
+            .insert_header((CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total_len)))
+            .streaming(chunked_stream(body)));
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, content_type))
+        .insert_header((ETAG, etag))
+        .insert_header((ACCEPT_RANGES, "bytes"))
+        .streaming(chunked_stream(image)))
+}
+
+/// Infer the `Content-Type` from the storage key's extension (see
+/// `ImageStore::upload`, which appends one of these when building the key).
+fn content_type_for_key(key: &str) -> &'static str {
+    match key.rsplit('.').next().unwrap_or("") {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parse a single-range `bytes=start-end` `Range` header value against
+/// `total_len`, clamping an open-ended end to the final byte. Multi-range
+/// requests and unsatisfiable ranges aren't supported; the caller falls back
+/// to a full `200` response in either case.
+fn parse_range(value: &str, total_len: u64) -> Option<(u64, u64)> {
+    let spec = value.strip_prefix("bytes=")?;
+    if spec.contains(',') || total_len == 0 {
+        return None;
+    }
+    let (start_str, end_str) = spec.split_once('-')?;
+    let start: u64 = if start_str.is_empty() {
+        0
+    } else {
+        start_str.parse().ok()?
+    };
+    let end: u64 = if end_str.is_empty() {
+        total_len - 1
+    } else {
+        end_str.parse::<u64>().ok()?.min(total_len - 1)
+    };
+    if start > end || start >= total_len {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Split an already-fetched buffer into a stream of ~[CHUNK_SIZE] pieces, so
+/// the response body is written out incrementally rather than as one frame.
+fn chunked_stream(
+    data: Bytes,
+) -> impl futures::Stream<Item = Result<Bytes, actix_web::Error>> {
+    let chunks: Vec<Bytes> = (0..data.len())
+        .step_by(CHUNK_SIZE)
+        .map(|start| data.slice(start..(start + CHUNK_SIZE).min(data.len())))
+        .collect();
+    futures::stream::iter(chunks.into_iter().map(Ok))
+}
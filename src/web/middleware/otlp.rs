@@ -0,0 +1,110 @@
+//! Per-request OpenTelemetry span
+//!
+//! Opens a server span for every request, parented to the caller's trace
+//! via [crate::tracing::parent_context_from_head], and promotes the
+//! low-cardinality tags already collected by [Tags] onto the span as
+//! attributes (keeping high-cardinality `uri.path` as a span event, same
+//! tags/extra split [Tags] already makes for metrics vs sentry).
+
+use std::task::{Context as TaskContext, Poll};
+use std::{cell::RefCell, rc::Rc};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::{self, LocalBoxFuture, TryFutureExt};
+use opentelemetry::trace::{SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{global, Context};
+
+use crate::settings::Settings;
+use crate::tags::Tags;
+use crate::tracing::{parent_context_from_head, tags_to_attributes, TRACER_NAME};
+
+pub struct OtlpTracing;
+
+impl OtlpTracing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for OtlpTracing {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S> for OtlpTracing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = OtlpTracingMiddleware<S>;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Box::pin(future::ok(OtlpTracingMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        }))
+    }
+}
+
+pub struct OtlpTracingMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for OtlpTracingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, sreq: ServiceRequest) -> Self::Future {
+        let settings: &Settings = (&sreq).into();
+        if settings.otlp_endpoint.is_none() {
+            return Box::pin(self.service.call(sreq));
+        }
+
+        let tags = match sreq.extensions().get::<Tags>() {
+            Some(t) => t.clone(),
+            None => Tags::from_head(sreq.head(), settings),
+        };
+        let parent_cx = parent_context_from_head(sreq.head());
+        let tracer = global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder(format!("{} {}", sreq.method(), sreq.path()))
+            .with_kind(SpanKind::Server)
+            .with_attributes(tags_to_attributes(&tags.tags))
+            .start_with_context(&tracer, &parent_cx);
+        span.add_event(
+            "request.uri",
+            vec![opentelemetry::KeyValue::new(
+                "uri.path",
+                sreq.uri().to_string(),
+            )],
+        );
+
+        let span_cx = Context::current_with_span(span);
+        let fut = self.service.call(sreq);
+
+        Box::pin(
+            opentelemetry::trace::FutureExt::with_context(fut, span_cx.clone())
+                .inspect_ok(move |_| span_cx.span().end()),
+        )
+    }
+}
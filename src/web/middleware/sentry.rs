@@ -69,7 +69,8 @@ pub fn queue_report(mut ext: RefMut<'_, Extensions>, err: &Error) {
             return;
         }
         */
-        let event = sentry::event_from_error(herr);
+        let mut event = sentry::event_from_error(herr);
+        event.extra.extend(herr.sentry_context());
         if let Some(events) = ext.get_mut::<Vec<Event<'static>>>() {
             events.push(event);
         } else {
@@ -161,7 +162,9 @@ where
                             return future::ok(sresp);
                         }
                         */
-                        report(&tags, sentry::event_from_error(herr));
+                        let mut event = sentry::event_from_error(herr);
+                        event.extra.extend(herr.sentry_context());
+                        report(&tags, event);
                     }
                 }
             }
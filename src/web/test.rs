@@ -5,6 +5,7 @@ use std::time::Duration;
 use actix_cors::Cors;
 use actix_web::{
     http::header,
+    http::Method,
     http::StatusCode,
     middleware::ErrorHandlers,
     rt, test,
@@ -54,13 +55,9 @@ pub fn get_test_settings() -> Settings {
         host: treq.uri().host().unwrap_or("localhost").to_owned(),
         adm_defaults: Some(
             json!(crate::adm::AdmDefaults {
-                click_hosts: [crate::adm::settings::break_hosts("example.com".to_owned())].to_vec(),
-                image_hosts: [crate::adm::settings::break_hosts(
-                    "cdn.example.com".to_owned()
-                )]
-                .to_vec(),
-                impression_hosts: [crate::adm::settings::break_hosts("example.net".to_owned())]
-                    .to_vec(),
+                click_hosts: vec![crate::adm::settings::HostFilter::new("example.com")],
+                image_hosts: vec![crate::adm::settings::HostFilter::new("cdn.example.com")],
+                impression_hosts: vec![crate::adm::settings::HostFilter::new("example.net")],
                 ..Default::default()
             })
             .to_string(),
@@ -119,9 +116,26 @@ macro_rules! init_app_with_spy {
                 metrics: Arc::clone(&metrics),
                 reqwest_client: reqwest::Client::builder()
                     .connect_timeout(Duration::from_secs(3))
+                    .gzip(true)
+                    .deflate(true)
                     .build()
                     .unwrap(),
-                tiles_cache: cache::TilesCache::new(10),
+                adm_requester: Arc::new(crate::adm::ReqwestAdmRequester::new(
+                    reqwest::Client::builder()
+                        .connect_timeout(Duration::from_secs(3))
+                        .gzip(true)
+                        .deflate(true)
+                        .build()
+                        .unwrap(),
+                    Duration::from_secs($settings.adm_timeout),
+                    $settings.adm_max_retries,
+                    $settings.adm_retry_base_ms,
+                )),
+                tiles_cache: cache::TilesCache::new(
+                    Arc::new(cache::InMemoryTileStore::new(10)),
+                    None,
+                    None,
+                ),
                 settings: $settings.clone(),
                 partner_filter: Arc::new(RwLock::new(
                     HandlerResult::<AdmFilter>::from(&mut $settings).unwrap(),
@@ -132,6 +146,9 @@ macro_rules! init_app_with_spy {
                 img_store: None,
                 excluded_dmas,
                 start_up: std::time::Instant::now(),
+                rate_limiter: None,
+                crawler_filter: None,
+                ip_blocklist: None,
             };
             let location_config = location_config_from_settings(&$settings, metrics);
 
@@ -151,9 +168,45 @@ macro_rules! init_app {
     }
 }
 
+/// Knobs the mock AdM should apply to its next response(s), shared between
+/// the running mock server and the test-side `MockAdm` handle so a test can
+/// flip these on after the server's spawned (see `MockAdm::set_revalidation`,
+/// `MockAdm::set_gzip`).
+#[derive(Clone, Default)]
+struct MockAdmConfig {
+    etag: Option<String>,
+    cache_control: Option<String>,
+    /// Gzip-encode the response body and set `Content-Encoding: gzip`,
+    /// exercising `get_tiles`'s transparent decompression of the ADM
+    /// response.
+    gzip: bool,
+    /// Respond with this status instead of `200 OK`, to exercise
+    /// `ReqwestAdmRequester`'s handling of a non-2xx upstream.
+    status: Option<u16>,
+    /// Sleep this long before responding, to exercise `connect_timeout`/
+    /// read-timeout behavior.
+    delay: Option<Duration>,
+    /// Respond `200 OK` with a truncated, invalid-JSON body, to exercise
+    /// deserialization-failure handling.
+    malformed_body: bool,
+}
+
+/// A single request the mock AdM received, captured in arrival order so
+/// tests can assert exactly what Contile sent upstream (method, path,
+/// headers, query) rather than only the echoed query params.
+#[derive(Clone, Debug)]
+struct CapturedAdmRequest {
+    method: Method,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+}
+
 struct MockAdm {
     pub endpoint_url: String,
     pub request_rx: mpsc::UnboundedReceiver<String>,
+    config: Arc<std::sync::Mutex<MockAdmConfig>>,
+    requests: Arc<std::sync::Mutex<Vec<CapturedAdmRequest>>>,
 }
 
 impl MockAdm {
@@ -167,10 +220,92 @@ impl MockAdm {
             .collect()
     }
 
+    /// All requests captured so far, in arrival order.
+    fn requests(&self) -> Vec<CapturedAdmRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many times the mock AdM endpoint has been hit.
+    fn hit_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    /// The most recently captured request, panicking if the mock was never
+    /// hit.
+    fn last_request(&self) -> CapturedAdmRequest {
+        self.requests()
+            .last()
+            .cloned()
+            .expect("mock AdM was never hit")
+    }
+
+    /// Assert the `User-Agent` Contile forwarded on the most recent request.
+    fn assert_user_agent(&self, expected: &str) {
+        self.assert_header_eq("user-agent", expected);
+    }
+
+    /// Assert a header on the most recent request equals `expected`.
+    fn assert_header_eq(&self, name: &str, expected: &str) {
+        assert_eq!(
+            self.last_request()
+                .headers
+                .get(&name.to_lowercase())
+                .map(String::as_str),
+            Some(expected),
+            "header {:?} didn't match",
+            name
+        );
+    }
+
+    /// Assert a header on the most recent request matches `pattern`.
+    fn assert_header_matches(&self, name: &str, pattern: &str) {
+        let re = Regex::new(pattern).expect("Invalid regex");
+        let request = self.last_request();
+        let value = request
+            .headers
+            .get(&name.to_lowercase())
+            .unwrap_or_else(|| panic!("Missing header {:?}", name));
+        assert!(
+            re.is_match(value),
+            "{:?} doesn't match {:?}",
+            value,
+            pattern
+        );
+    }
+
     /// Set the mock AdM to respond with a 5xx error
     fn set_response_error(&mut self) {
         self.request_rx.close();
     }
+
+    /// Have the mock AdM advertise `etag`/`cache_control`, and reply `304 Not
+    /// Modified` to a request whose `If-None-Match` matches `etag`, so tests
+    /// can assert `adm::get_tiles`'s upstream revalidation behavior.
+    fn set_revalidation(&mut self, etag: Option<&str>, cache_control: Option<&str>) {
+        let mut config = self.config.lock().unwrap();
+        config.etag = etag.map(str::to_owned);
+        config.cache_control = cache_control.map(str::to_owned);
+    }
+
+    /// Have the mock AdM gzip-encode its response body.
+    fn set_gzip(&mut self, enabled: bool) {
+        self.config.lock().unwrap().gzip = enabled;
+    }
+
+    /// Respond with `status` instead of `200 OK`.
+    fn set_status(&mut self, status: u16) {
+        self.config.lock().unwrap().status = Some(status);
+    }
+
+    /// Delay every response by `delay`.
+    fn set_delay(&mut self, delay: Duration) {
+        self.config.lock().unwrap().delay = Some(delay);
+    }
+
+    /// Respond `200 OK` with a truncated, invalid-JSON body.
+    fn set_malformed_body(&mut self, enabled: bool) {
+        self.config.lock().unwrap().malformed_body = enabled;
+    }
 }
 
 /// Bind a mock of the AdM Tiles API to a random port on localhost
@@ -179,6 +314,8 @@ fn init_mock_adm(response: String) -> MockAdm {
         req: HttpRequest,
         resp: web::Data<String>,
         tx: web::Data<futures::channel::mpsc::UnboundedSender<String>>,
+        config: web::Data<std::sync::Mutex<MockAdmConfig>>,
+        requests: web::Data<std::sync::Mutex<Vec<CapturedAdmRequest>>>,
     ) -> actix_web::error::Result<HttpResponse> {
         trace!(
             "mock_adm: path: {:#?} query_string: {:#?} {:#?} {:#?}",
@@ -187,20 +324,92 @@ fn init_mock_adm(response: String) -> MockAdm {
             req.connection_info(),
             req.headers()
         );
-        // TODO: pass more data for validation
+        requests.lock().unwrap().push(CapturedAdmRequest {
+            method: req.method().clone(),
+            path: req.path().to_owned(),
+            query: Url::parse(&format!("http://mock-adm/?{}", req.query_string()))
+                .map(|url| url.query_pairs().into_owned().collect())
+                .unwrap_or_default(),
+            headers: req
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.as_str().to_owned(),
+                        value.to_str().unwrap_or_default().to_owned(),
+                    )
+                })
+                .collect(),
+        });
         tx.unbounded_send(req.query_string().to_owned())
             // set_response_error called
             .map_err(actix_web::error::ErrorServiceUnavailable)?;
-        Ok(HttpResponse::Ok()
-            .content_type("application/json")
-            .body(resp.get_ref().to_owned()))
+        let config = config.lock().unwrap().clone();
+        if let Some(delay) = config.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if let Some(status) = config.status {
+            let code =
+                StatusCode::from_u16(status).map_err(actix_web::error::ErrorInternalServerError)?;
+            return Ok(HttpResponse::build(code).finish());
+        }
+        if config.malformed_body {
+            let body = resp.get_ref();
+            let truncated = &body[..body.len() / 2];
+            return Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(truncated.to_owned()));
+        }
+        let if_none_match = req
+            .headers()
+            .get("if-none-match")
+            .and_then(|v| v.to_str().ok());
+        if let (Some(etag), Some(if_none_match)) = (&config.etag, if_none_match) {
+            if if_none_match == etag {
+                let mut builder = HttpResponse::NotModified();
+                builder.insert_header(("ETag", etag.clone()));
+                if let Some(cache_control) = &config.cache_control {
+                    builder.insert_header(("Cache-Control", cache_control.clone()));
+                }
+                return Ok(builder.finish());
+            }
+        }
+        let mut builder = HttpResponse::Ok();
+        if let Some(etag) = &config.etag {
+            builder.insert_header(("ETag", etag.clone()));
+        }
+        if let Some(cache_control) = &config.cache_control {
+            builder.insert_header(("Cache-Control", cache_control.clone()));
+        }
+        builder.content_type("application/json");
+        if config.gzip {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder
+                .write_all(resp.get_ref().as_bytes())
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            let body = encoder
+                .finish()
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            builder.insert_header(("Content-Encoding", "gzip"));
+            Ok(builder.body(body))
+        } else {
+            Ok(builder.body(resp.get_ref().to_owned()))
+        }
     }
 
     let (tx, request_rx) = mpsc::unbounded::<String>();
+    let config = Arc::new(std::sync::Mutex::new(MockAdmConfig::default()));
+    let config_data = config.clone();
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let requests_data = requests.clone();
     let server = HttpServer::new(move || {
         App::new()
             .app_data(Data::new(response.clone()))
             .app_data(Data::new(tx.clone()))
+            .app_data(Data::from(config_data.clone()))
+            .app_data(Data::from(requests_data.clone()))
             .route("/", web::get().to(adm_tiles))
     });
     let server = server
@@ -211,6 +420,8 @@ fn init_mock_adm(response: String) -> MockAdm {
     MockAdm {
         endpoint_url: format!("http://{}:{}/", addr.ip(), addr.port()),
         request_rx,
+        config,
+        requests,
     }
 }
 
@@ -261,13 +472,12 @@ async fn basic() {
         adm_settings,
         adm_defaults: Some(
             json!(crate::adm::AdmDefaults {
-                click_hosts: [crate::adm::break_hosts("example.com".to_owned())].to_vec(),
-                image_hosts: [crate::adm::break_hosts("cdn.example.com".to_owned())].to_vec(),
-                impression_hosts: [
-                    crate::adm::break_hosts("example.net".to_owned()),
-                    crate::adm::break_hosts("example.com".to_owned())
-                ]
-                .to_vec(),
+                click_hosts: vec![crate::adm::settings::HostFilter::new("example.com")],
+                image_hosts: vec![crate::adm::settings::HostFilter::new("cdn.example.com")],
+                impression_hosts: vec![
+                    crate::adm::settings::HostFilter::new("example.net"),
+                    crate::adm::settings::HostFilter::new("example.com"),
+                ],
                 ..Default::default()
             })
             .to_string(),
@@ -303,6 +513,189 @@ async fn basic() {
     }
 }
 
+/// A gzip-encoded ADM response (with a matching `Content-Encoding`) should
+/// decode to the same tiles as the plaintext path in `basic`.
+#[actix_web::test]
+async fn basic_gzip() {
+    let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    adm.set_gzip(true);
+    let adm_settings = AdmFilter::advertisers_to_string(advertiser_filters());
+    let mut settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings,
+        adm_defaults: Some(
+            json!(crate::adm::AdmDefaults {
+                click_hosts: vec![crate::adm::settings::HostFilter::new("example.com")],
+                image_hosts: vec![crate::adm::settings::HostFilter::new("cdn.example.com")],
+                impression_hosts: vec![
+                    crate::adm::settings::HostFilter::new("example.net"),
+                    crate::adm::settings::HostFilter::new("example.com"),
+                ],
+                ..Default::default()
+            })
+            .to_string(),
+        ),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let result: Value = test::read_body_json(resp).await;
+    let tiles = result["tiles"].as_array().expect("!tiles.is_array()");
+    assert!(tiles.len() > 1);
+    for tile in tiles {
+        let tile = tile.as_object().expect("!tile.is_object()");
+        assert!(tile["url"].is_string());
+        assert!(tile.get("advertiser_url").is_none());
+    }
+}
+
+/// Security headers are on by default (see [Settings::x_content_type_options]
+/// et al) and should be present on `/v1/tiles`, but not on a dockerflow
+/// endpoint.
+#[actix_web::test]
+async fn security_headers_default() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let adm_settings = AdmFilter::advertisers_to_string(advertiser_filters());
+    let mut settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings,
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let headers = resp.headers();
+    assert_eq!(headers.get("x-content-type-options").unwrap(), "nosniff");
+    assert_eq!(headers.get("x-frame-options").unwrap(), "DENY");
+    assert_eq!(headers.get("referrer-policy").unwrap(), "no-referrer");
+    assert!(headers.get("permissions-policy").is_some());
+
+    let req = test::TestRequest::get().uri("/__heartbeat__").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().get("x-content-type-options").is_none());
+}
+
+/// Each security header is independently omittable via `Settings`.
+#[actix_web::test]
+async fn security_headers_disabled() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let adm_settings = AdmFilter::advertisers_to_string(advertiser_filters());
+    let mut settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings,
+        x_content_type_options: None,
+        permissions_policy: None,
+        x_frame_options: None,
+        referrer_policy: None,
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let headers = resp.headers();
+    assert!(headers.get("x-content-type-options").is_none());
+    assert!(headers.get("permissions-policy").is_none());
+    assert!(headers.get("x-frame-options").is_none());
+    assert!(headers.get("referrer-policy").is_none());
+}
+
+/// A request from a configured origin gets that exact origin echoed back
+/// (never a wildcard), plus `Vary: Origin`; a request from an unlisted
+/// origin proceeds without any CORS headers rather than being rejected.
+#[actix_web::test]
+async fn cors_allowed_origins() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        cors_allowed_origins: r#"["https://partner.example"]"#.to_owned(),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header((header::ORIGIN, "https://partner.example"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "https://partner.example"
+    );
+    assert_eq!(resp.headers().get(header::VARY).unwrap(), "Origin");
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header((header::ORIGIN, "https://evil.example"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
+
+/// `/__loc_test__` is exempt from the hardening headers by default (see
+/// `Settings::security_headers_skip_paths`), same as the dockerflow
+/// endpoints.
+#[actix_web::test]
+async fn security_headers_skip_paths_default() {
+    let app = init_app!().await;
+
+    let req = test::TestRequest::get()
+        .uri("/__loc_test__")
+        .insert_header(("X-FORWARDED-FOR", TEST_ADDR))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-content-type-options").is_none());
+}
+
+/// The skip-path allowlist is operator-configurable: an additional path can
+/// be exempted, and the default exemptions stop applying once overridden.
+#[actix_web::test]
+async fn security_headers_skip_paths_configurable() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        security_headers_skip_paths: r#"["/v1/tiles"]"#.to_owned(),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-content-type-options").is_none());
+}
+
 #[actix_web::test]
 async fn basic_old_ua() {
     let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
@@ -608,6 +1001,75 @@ async fn basic_mobile() {
     assert!(result["sov"].is_null());
 }
 
+/// `MockAdm` captures the full forwarded request, not just the query
+/// string, so tests can assert headers Contile sent upstream.
+#[actix_web::test]
+async fn adm_request_capture() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url.clone(),
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(adm.hit_count(), 1);
+    let request = adm.last_request();
+    assert_eq!(request.method, Method::GET);
+    assert_eq!(request.query.get("country-code"), Some(&"US".to_owned()));
+    adm.assert_user_agent(crate::server::REQWEST_USER_AGENT);
+    adm.assert_header_matches("user-agent", r"^contile/\d+\.\d+\.\d+$");
+}
+
+/// A non-retryable error status from ADM should surface as a `503` rather
+/// than the raw upstream status.
+#[actix_web::test]
+async fn adm_fault_injection_status() {
+    let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    adm.set_status(403);
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+/// A truncated/invalid-JSON ADM response is treated as a bad response and
+/// results in an empty tile set rather than a hard failure.
+#[actix_web::test]
+async fn adm_fault_injection_malformed_body() {
+    let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    adm.set_malformed_body(true);
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
 #[actix_web::test]
 async fn fallback_country() {
     let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
@@ -1002,6 +1464,78 @@ async fn cache_header() {
     assert_eq!(tiles.len(), 3);
 }
 
+#[actix_web::test]
+async fn tiles_etag_conditional_request() {
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp
+        .headers()
+        .get(header::ETAG)
+        .expect("No ETag header")
+        .to_str()
+        .expect("Invalid ETag header")
+        .to_owned();
+    assert!(resp.headers().get(header::LAST_MODIFIED).is_some());
+
+    // A matching If-None-Match short-circuits to a bodyless 304, still
+    // carrying the same ETag/Cache-Control.
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+        etag
+    );
+    assert!(resp.headers().get(header::CACHE_CONTROL).is_some());
+    assert!(test::read_body(resp).await.is_empty());
+
+    // A stale If-None-Match still gets the full cached response back.
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header((header::IF_NONE_MATCH, "\"not-the-etag\""))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn tiles_no_content_has_no_etag() {
+    // A 204 (nothing to serve for this audience) never carries an ETag --
+    // there's no body to revalidate.
+    let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let settings = Settings {
+        adm_endpoint_url: adm.endpoint_url,
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        ..get_test_settings()
+    };
+    let app = init_app!(settings).await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_FORM_FACTOR_OTHER))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert!(resp.headers().get(header::ETAG).is_none());
+}
+
 #[actix_web::test]
 async fn fallback_on_error() {
     let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
@@ -1069,6 +1603,67 @@ async fn fallback_on_error() {
     );
 }
 
+/// A slow-but-not-erroring ADM partner should be treated like the error
+/// path (fall back on stale tiles, or 204 if none are cached) rather than
+/// hanging the request -- see [crate::settings::Settings::adm_request_timeout].
+#[actix_web::test]
+async fn adm_request_timeout_fallback() {
+    let mut adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
+    let tiles_ttl = 2;
+    let mut settings = Settings {
+        adm_endpoint_url: adm.endpoint_url.clone(),
+        adm_settings: AdmFilter::advertisers_to_string(advertiser_filters()),
+        location_test_header: Some("x-test-location".to_owned()),
+        tiles_ttl,
+        adm_request_timeout: 1,
+        // No stale-while-revalidate grace window, so the expired request
+        // itself waits on (and times out of) the ADM refresh.
+        tiles_stale_while_revalidate_secs: 0,
+        ..get_test_settings()
+    };
+    let (app, spy) = init_app_with_spy!(settings).await;
+
+    // Load the cache
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header(("X-Forwarded-For", TEST_ADDR))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Make adM respond slower than `adm_request_timeout`, then trigger a
+    // refresh (as the tiles expired)
+    adm.set_delay(Duration::from_secs(3));
+    rt::time::sleep(Duration::from_secs(tiles_ttl as u64)).await;
+    let req = test::TestRequest::get()
+        .uri("/v1/tiles")
+        .insert_header((header::USER_AGENT, UA_91))
+        .insert_header(("X-Forwarded-For", TEST_ADDR))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let cache_header = resp
+        .headers()
+        .get("Cache-Control")
+        .expect("No Cache-Control header")
+        .to_str()
+        .expect("Invalid Cache-Control header");
+    let directives: Vec<_> = cache_header.split(", ").collect();
+    assert_eq!(directives[0], "private");
+    assert!(directives
+        .iter()
+        .any(|directive| directive.starts_with("max-age=0")));
+    assert!(directives
+        .iter()
+        .any(|directive| directive.starts_with("stale-if-error=")));
+
+    assert!(find_metrics(&spy, &["contile.tiles.adm.timeout"])
+        .iter()
+        .any(|m| m.starts_with("contile.tiles.adm.timeout:1")));
+}
+
 #[actix_web::test]
 async fn no_sov() {
     let adm = init_mock_adm(MOCK_RESPONSE1.to_owned());
@@ -0,0 +1,100 @@
+//! Synthetic Firefox User-Agent string generator.
+//!
+//! Hand-pasted UA strings only cover the handful of devices someone thought
+//! to paste; this builds valid Firefox UAs from structured inputs instead,
+//! modeled on the template-driven builders in ronin-web-user_agents and
+//! fake_useragent. Useful for round-trip property tests against
+//! [super::get_device_info], and for `test_mode` traffic that wants to
+//! simulate a diverse device population rather than hammering the cache
+//! with a single UA.
+
+use super::OsFamily;
+
+/// Structured input to [generate] describing the synthetic device. Use
+/// [DeviceSpec::new] for the common case and override `os_version`/`arch`
+/// only when a test needs a specific value.
+#[derive(Clone, Debug)]
+pub struct DeviceSpec {
+    pub os_family: OsFamily,
+    /// OS version, e.g. `"10.15.4"` (macOS) or `"13"` (Android/iOS).
+    /// Defaults to a representative current version per OS family.
+    pub os_version: Option<String>,
+    /// CPU architecture token for the Windows/Linux templates, e.g.
+    /// `"Win64; x64"` or `"x86_64"`. Defaults to a representative 64-bit
+    /// value.
+    pub arch: Option<String>,
+    /// Firefox major version, e.g. `91`.
+    pub firefox_version: u32,
+}
+
+impl DeviceSpec {
+    pub fn new(os_family: OsFamily, firefox_version: u32) -> Self {
+        Self {
+            os_family,
+            os_version: None,
+            arch: None,
+            firefox_version,
+        }
+    }
+}
+
+/// Build a synthetic, valid Firefox User-Agent string from `spec`.
+pub fn generate(spec: &DeviceSpec) -> String {
+    let v = spec.firefox_version;
+    match spec.os_family {
+        OsFamily::Windows => {
+            let nt = spec.os_version.as_deref().unwrap_or("10.0");
+            let arch = spec.arch.as_deref().unwrap_or("Win64; x64");
+            format!("Mozilla/5.0 (Windows NT {nt}; {arch}; rv:{v}) Gecko/20100101 Firefox/{v}")
+        }
+        OsFamily::MacOs => {
+            let osv = spec.os_version.as_deref().unwrap_or("10.15");
+            format!(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X {osv}; rv:{v}) Gecko/20100101 Firefox/{v}"
+            )
+        }
+        OsFamily::Linux => {
+            let arch = spec.arch.as_deref().unwrap_or("x86_64");
+            format!("Mozilla/5.0 (X11; Linux {arch}; rv:{v}) Gecko/20100101 Firefox/{v}")
+        }
+        OsFamily::Android => {
+            let osv = spec.os_version.as_deref().unwrap_or("13");
+            format!("Mozilla/5.0 (Android {osv}; Mobile; rv:{v}) Gecko/{v} Firefox/{v}")
+        }
+        OsFamily::IOs => {
+            let osv = spec.os_version.as_deref().unwrap_or("16_5");
+            format!(
+                "Mozilla/5.0 (iPhone; CPU iPhone OS {osv} like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) FxiOS/{v} Mobile/15E148 Safari/605.1.15"
+            )
+        }
+        OsFamily::ChromeOs | OsFamily::BlackBerry | OsFamily::Other => {
+            format!("Mozilla/5.0 (rv:{v}) Gecko/20100101 Firefox/{v}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, DeviceSpec};
+    use crate::web::user_agent::{get_device_info, OsFamily};
+
+    #[test]
+    fn round_trips_through_get_device_info() {
+        for os_family in [
+            OsFamily::Windows,
+            OsFamily::MacOs,
+            OsFamily::Linux,
+            OsFamily::Android,
+            OsFamily::IOs,
+        ] {
+            let spec = DeviceSpec::new(os_family, 115);
+            let ua = generate(&spec);
+            let info = get_device_info(&ua)
+                .unwrap_or_else(|e| panic!("generated UA {:?} should parse: {:?}", ua, e));
+            assert_eq!(info.os_family, os_family, "for generated UA {:?}", ua);
+            assert_eq!(info.ff_version, 115, "for generated UA {:?}", ua);
+            assert!(!info.bot);
+        }
+    }
+}
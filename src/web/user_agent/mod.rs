@@ -0,0 +1,628 @@
+//! Simple UserAgent parser/stripper
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use woothee::parser::Parser;
+
+use crate::error::{HandlerError, HandlerErrorKind, HandlerResult};
+
+pub mod generate;
+
+/// ADM required browser format form
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum FormFactor {
+    Desktop,
+    Phone,
+    Tablet,
+    Other,
+}
+
+impl fmt::Display for FormFactor {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = format!("{:?}", self).to_lowercase();
+        write!(fmt, "{}", name)
+    }
+}
+
+impl FromStr for FormFactor {
+    type Err = HandlerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "desktop" => Ok(FormFactor::Desktop),
+            "phone" => Ok(FormFactor::Phone),
+            "tablet" => Ok(FormFactor::Tablet),
+            "other" => Ok(FormFactor::Other),
+            _ => Err(HandlerErrorKind::Validation(format!("Unknown form_factor: {:?}", s)).into()),
+        }
+    }
+}
+
+/// Simplified Operating System Family
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum OsFamily {
+    Windows,
+    MacOs,
+    Linux,
+    IOs,
+    Android,
+    ChromeOs,
+    BlackBerry,
+    Other,
+}
+
+impl fmt::Display for OsFamily {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // XXX: could use "correct" case (rendering this w/ serde will make
+        // that easier)
+        let name = format!("{:?}", self).to_lowercase();
+        write!(fmt, "{}", name)
+    }
+}
+
+impl FromStr for OsFamily {
+    type Err = HandlerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "windows" => Ok(OsFamily::Windows),
+            "macos" => Ok(OsFamily::MacOs),
+            "linux" => Ok(OsFamily::Linux),
+            "ios" => Ok(OsFamily::IOs),
+            "android" => Ok(OsFamily::Android),
+            "chromeos" => Ok(OsFamily::ChromeOs),
+            "blackberry" => Ok(OsFamily::BlackBerry),
+            "other" => Ok(OsFamily::Other),
+            _ => Err(HandlerErrorKind::Validation(format!("Unknown os_family: {:?}", s)).into()),
+        }
+    }
+}
+
+/// Which member of the Firefox product family the UA belongs to, as
+/// determined by [parse_firefox_family] -- lets downstream code treat
+/// privacy-focused variants (Focus/Klar) differently from mainline
+/// Firefox/Fennec/Fenix without having to re-parse the UA. `Other` covers
+/// non-Firefox traffic (e.g. bots).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum BrowserVariant {
+    Firefox,
+    Focus,
+    Klar,
+    Other,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DeviceInfo {
+    pub form_factor: FormFactor,
+    pub os_family: OsFamily,
+    // We only care about major versions.
+    pub ff_version: u32,
+    pub browser_variant: BrowserVariant,
+    /// OS major/minor version, e.g. `(10, 0)` for Windows 10, `(13, 0)` for
+    /// Android 13, or `(11, 2)` for macOS 11.2. `(0, 0)` when the UA doesn't
+    /// carry a version we recognize.
+    pub os_major: u32,
+    pub os_minor: u32,
+    /// Whether the User-Agent looks like a known bot/crawler rather than a
+    /// real browser -- see [is_bot]. Ad-serving on bot traffic is a
+    /// billing/fraud problem, so callers should use this to skip tile
+    /// lookups or at least tag the request rather than treating it like a
+    /// real impression.
+    pub bot: bool,
+}
+
+/// Curated, case-insensitive substrings identifying known bots and
+/// crawlers, checked against the raw User-Agent (not the woothee-parsed
+/// name, since bots frequently omit `Firefox/` and wouldn't survive that
+/// far otherwise). Not exhaustive -- see
+/// [crate::server::inbound_filter::CrawlerFilter] for an
+/// operator-configurable, regex-based complement.
+const BOT_TOKENS: &[&str] = &[
+    "bot",
+    "crawler",
+    "spider",
+    "slurp",
+    "headless",
+    "preview",
+    "monitor",
+    "googlebot",
+    "bingbot",
+    "ahrefsbot",
+    "facebookexternalhit",
+    "python-urllib",
+    "curl",
+    "wget",
+];
+
+/// Whether `ua` looks like a known bot or crawler, matched case-insensitively
+/// against [BOT_TOKENS].
+pub fn is_bot(ua: &str) -> bool {
+    let ua = ua.to_lowercase();
+    BOT_TOKENS.iter().any(|token| ua.contains(token))
+}
+
+impl DeviceInfo {
+    /// "Legacy" means that it can only display tiles that are available from
+    /// remote settings. Currently, that's limited to just desktop devices that
+    /// are before v. 91
+    pub fn legacy_only(&self) -> bool {
+        matches!(self.form_factor, FormFactor::Desktop | FormFactor::Other) && self.ff_version < 91
+    }
+
+    /// Determine if the device is a mobile phone based on either the form factor or OS.
+    pub fn is_mobile(&self) -> bool {
+        matches!(&self.form_factor, FormFactor::Phone | FormFactor::Tablet)
+            || matches!(&self.os_family, OsFamily::Android | OsFamily::IOs)
+    }
+}
+
+/// Parse the leading `major[.|_]minor` numbers out of a dotted or
+/// underscore-separated version string (e.g. `"10.15.4"` or `"10_15_4"`),
+/// falling back to `0` for either piece that's missing or non-numeric.
+fn parse_leading_two(version: &str) -> (u32, u32) {
+    let mut parts = version.split(|c| c == '.' || c == '_');
+    let major = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let minor = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    (major, minor)
+}
+
+/// Parse the leading integer out of a version string (e.g. `"13"` out of
+/// `"13"` or `"6.0.1"`), falling back to `0`.
+fn parse_leading_int(version: &str) -> u32 {
+    version
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Map woothee's raw Windows `os_version` (e.g. `"NT 6.1"`) to the marketing
+/// major/minor version operators actually care about, per the table used by
+/// uasurfer's `system.go`. Versions without a conventional major.minor
+/// (e.g. Vista) fall back to `(0, 0)`.
+fn windows_version(os_version: &str) -> (u32, u32) {
+    let nt_version = os_version
+        .to_lowercase()
+        .split("nt")
+        .nth(1)
+        .unwrap_or(&os_version.to_lowercase())
+        .trim()
+        .to_owned();
+    match parse_leading_two(&nt_version) {
+        (10, 0) => (10, 0),
+        (6, 3) => (8, 1),
+        (6, 2) => (8, 0),
+        (6, 1) => (7, 0),
+        (5, 1) => (5, 1),
+        _ => (0, 0),
+    }
+}
+
+/// Parse woothee's raw `os_version` into `(os_major, os_minor)` appropriate
+/// for `os_family`, falling back to `(0, 0)` when absent or not recognized.
+fn parse_os_version(os_family: OsFamily, raw_os_version: &str) -> (u32, u32) {
+    match os_family {
+        OsFamily::Windows => windows_version(raw_os_version),
+        OsFamily::MacOs => parse_leading_two(raw_os_version),
+        OsFamily::Android | OsFamily::IOs => (parse_leading_int(raw_os_version), 0),
+        _ => (0, 0),
+    }
+}
+
+/// The result of scanning a raw UA for a Firefox-family product token (see
+/// [parse_firefox_family]). Fields are `None`/unset when the token alone
+/// doesn't determine them, leaving woothee's result to fill the gap.
+struct FirefoxMatch {
+    variant: BrowserVariant,
+    os_family: Option<OsFamily>,
+    form_factor: Option<FormFactor>,
+    version: u32,
+}
+
+/// Extract the `u32` major version following the first occurrence of
+/// `token` in `ua`, e.g. `token_version(ua, "Firefox/")` on
+/// `"...Firefox/91.0..."` returns `Some(91)`.
+fn token_version(ua: &str, token: &str) -> Option<u32> {
+    let rest = &ua[ua.find(token)? + token.len()..];
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Scan the raw UA for Firefox-family product tokens, in priority order,
+/// rather than trusting woothee's general-purpose browser database --
+/// which doesn't reliably cover Fenix/FxiOS/Focus/Klar and would otherwise
+/// misclassify (or outright reject) them. Captures the version from the
+/// product token itself, not `rv:`, since the two can disagree (e.g. GeckoView
+/// embedders).
+fn parse_firefox_family(ua: &str) -> Option<FirefoxMatch> {
+    // Firefox for iOS always reports a Safari-style UA otherwise, so this
+    // has to be checked before anything else.
+    if let Some(version) = token_version(ua, "FxiOS/") {
+        let form_factor = if ua.contains("iPad") {
+            FormFactor::Tablet
+        } else {
+            FormFactor::Phone
+        };
+        return Some(FirefoxMatch {
+            variant: BrowserVariant::Firefox,
+            os_family: Some(OsFamily::IOs),
+            form_factor: Some(form_factor),
+            version,
+        });
+    }
+    // Privacy-focused browsers: still Firefox family (built on GeckoView),
+    // but flagged distinctly so callers can treat them differently.
+    if let Some(version) = token_version(ua, "Focus/") {
+        return Some(FirefoxMatch {
+            variant: BrowserVariant::Focus,
+            os_family: None,
+            form_factor: None,
+            version,
+        });
+    }
+    if let Some(version) = token_version(ua, "Klar/") {
+        return Some(FirefoxMatch {
+            variant: BrowserVariant::Klar,
+            os_family: None,
+            form_factor: None,
+            version,
+        });
+    }
+    // Fennec/Fenix (Firefox for Android) and mainline desktop Firefox.
+    let version = token_version(ua, "Fennec/").or_else(|| token_version(ua, "Firefox/"))?;
+    let is_android = ua.contains("Android");
+    Some(FirefoxMatch {
+        variant: BrowserVariant::Firefox,
+        os_family: is_android.then_some(OsFamily::Android),
+        // Firefox for Android drops the `Mobile;` platform token on
+        // tablets, so its presence/absence is what actually distinguishes
+        // them (woothee itself just reports "smartphone" for both).
+        form_factor: is_android.then(|| {
+            if ua.contains("Mobile") {
+                FormFactor::Phone
+            } else {
+                FormFactor::Tablet
+            }
+        }),
+        version,
+    })
+}
+
+/// Parse a User-Agent header into a simplified `DeviceInfo`
+pub fn get_device_info(ua: &str) -> HandlerResult<DeviceInfo> {
+    // Check for bots/crawlers against the raw UA first: they often don't
+    // claim to be Firefox at all, so they'd otherwise just fail the
+    // `InvalidUA` check below before we ever got a chance to tag them.
+    let bot = is_bot(ua);
+
+    // Recognize Firefox-family variants directly off the raw UA before
+    // falling back to woothee, which doesn't reliably classify them.
+    let firefox_match = parse_firefox_family(ua);
+
+    let mut wresult = Parser::new().parse(ua).unwrap_or_default();
+
+    // NOTE: Firefox on iPads report back the Safari "desktop" UA
+    // (e.g. `Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_4) AppleWebKit/605.1.15
+    //        (KHTML, like Gecko) Version/13.1 Safari/605.1.15)`
+    // therefore we have to accept that one. This does mean that we may presume
+    // that a mac safari UA is an iPad. `parse_firefox_family` supersedes this
+    // for actual FxiOS UAs; this hack remains for whatever woothee still
+    // misreports as Safari without an `FxiOS/` token.
+    if wresult.name.to_lowercase() == "safari" && !ua.to_lowercase().contains("firefox/") {
+        wresult.name = "firefox";
+        wresult.category = "smartphone";
+        wresult.os = "ipad";
+    }
+    // If it's not firefox, it doesn't belong here... unless it's a
+    // recognized Firefox-family variant woothee just didn't know about, or
+    // a known bot. For a bot, we still hand back a (mostly default)
+    // `DeviceInfo` with `bot` set, so the handler can decide whether to
+    // skip tile lookups rather than just rejecting the request outright.
+    if firefox_match.is_none() && !["firefox"].contains(&wresult.name.to_lowercase().as_str()) {
+        if bot {
+            return Ok(DeviceInfo {
+                form_factor: FormFactor::Other,
+                os_family: OsFamily::Other,
+                ff_version: 0,
+                browser_variant: BrowserVariant::Other,
+                os_major: 0,
+                os_minor: 0,
+                bot: true,
+            });
+        }
+        let mut err: HandlerError = HandlerErrorKind::InvalidUA.into();
+        // XXX: Tags::from_head already adds this
+        err.tags.add_extra("ua", ua);
+        err.tags
+            .add_extra("name", wresult.name.to_lowercase().as_str());
+        return Err(err);
+    }
+
+    let os = wresult.os.to_lowercase();
+    let mut os_family = match os.as_str() {
+        _ if os.starts_with("windows") => OsFamily::Windows,
+        "mac osx" => OsFamily::MacOs,
+        "linux" => OsFamily::Linux,
+        "iphone" | "ipad" => OsFamily::IOs,
+        "android" => OsFamily::Android,
+        "chromeos" => OsFamily::ChromeOs,
+        "blackberry" => OsFamily::BlackBerry,
+        _ => OsFamily::Other,
+    };
+    let mut form_factor = match wresult.category {
+        "pc" => FormFactor::Desktop,
+        "smartphone" if os.as_str() == "ipad" => FormFactor::Tablet,
+        // woothee reports "smartphone" for Android regardless of device
+        // size; Firefox for Android drops the `Mobile;` platform token on
+        // tablets, so use its presence/absence to tell them apart.
+        "smartphone" if os.as_str() == "android" => {
+            if ua.contains("Mobile") {
+                FormFactor::Phone
+            } else {
+                FormFactor::Tablet
+            }
+        }
+        "smartphone" => FormFactor::Phone,
+        _ => FormFactor::Other,
+    };
+
+    let mut ff_version =
+        u32::from_str(wresult.version.split('.').collect::<Vec<&str>>()[0]).unwrap_or_default();
+    let mut browser_variant = BrowserVariant::Firefox;
+    if let Some(m) = firefox_match {
+        browser_variant = m.variant;
+        ff_version = m.version;
+        if let Some(of) = m.os_family {
+            os_family = of;
+        }
+        if let Some(ff) = m.form_factor {
+            form_factor = ff;
+        }
+    }
+
+    let (os_major, os_minor) = parse_os_version(os_family, wresult.os_version);
+    Ok(DeviceInfo {
+        form_factor,
+        os_family,
+        ff_version,
+        browser_variant,
+        os_major,
+        os_minor,
+        bot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::HandlerErrorKind;
+
+    use super::{get_device_info, is_bot, BrowserVariant, DeviceInfo, FormFactor, OsFamily};
+
+    macro_rules! assert_get_device_info {
+        ($value:expr, $os_family:expr, $form_factor:expr, $ff_version:expr, $os_major:expr, $os_minor:expr) => {
+            assert_eq!(
+                get_device_info($value).expect("Error"),
+                DeviceInfo {
+                    os_family: $os_family,
+                    form_factor: $form_factor,
+                    ff_version: $ff_version,
+                    browser_variant: BrowserVariant::Firefox,
+                    os_major: $os_major,
+                    os_minor: $os_minor,
+                    bot: false,
+                }
+            );
+        };
+    }
+
+    #[test]
+    fn macos() {
+        assert_get_device_info!(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 11.2; rv:85.0) Gecko/20100101 Firefox/85.0",
+            OsFamily::MacOs,
+            FormFactor::Desktop,
+            85,
+            11,
+            2
+        );
+    }
+
+    #[test]
+    fn windows() {
+        assert_get_device_info!(
+            "Mozilla/5.0 (Windows NT 6.1; Win64; x64; rv:61.0) Gecko/20100101 Firefox/61.0",
+            OsFamily::Windows,
+            FormFactor::Desktop,
+            61,
+            7,
+            0
+        );
+    }
+
+    #[test]
+    fn linux() {
+        assert_get_device_info!(
+            "Mozilla/5.0 (X11; Fedora; Linux x86_64; rv:82.0.1) Gecko/20100101 Firefox/82.0.1",
+            OsFamily::Linux,
+            FormFactor::Desktop,
+            82,
+            0,
+            0
+        );
+    }
+
+    #[test]
+    fn android() {
+        assert_get_device_info!(
+            "Mozilla/5.0 (Android 11; Mobile; rv:68.0) Gecko/68.0 Firefox/85.0",
+            OsFamily::Android,
+            FormFactor::Phone,
+            85,
+            11,
+            0
+        );
+    }
+
+    #[test]
+    fn android_tablet() {
+        // Same as the phone UA above, but Firefox for Android drops
+        // `Mobile;` on tablets.
+        assert_get_device_info!(
+            "Mozilla/5.0 (Android 11; rv:68.0) Gecko/68.0 Firefox/85.0",
+            OsFamily::Android,
+            FormFactor::Tablet,
+            85,
+            11,
+            0
+        );
+    }
+
+    #[test]
+    fn ios() {
+        let ipad_ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/13.1 Safari/605.1.15";
+        let macos_ua =
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:95.0) Gecko/20100101 Firefox/95.0";
+        let iphone_ua = "Mozilla/5.0 (iPhone; CPU iPhone OS 14_8_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) FxiOS/40.2 Mobile/15E148 Safari/605.1.15";
+        /*
+        // for test debugging
+        dbg!(woothee::parser::Parser::new().parse(ipad_ua).unwrap());
+        dbg!(woothee::parser::Parser::new().parse(macos_ua).unwrap());
+        dbg!(woothee::parser::Parser::new().parse(iphone_ua).unwrap());
+        */
+
+        assert_get_device_info!(ipad_ua, OsFamily::IOs, FormFactor::Tablet, 13, 10, 0);
+        assert_get_device_info!(iphone_ua, OsFamily::IOs, FormFactor::Phone, 40, 14, 0);
+        assert_get_device_info!(macos_ua, OsFamily::MacOs, FormFactor::Desktop, 95, 10, 15);
+    }
+
+    #[test]
+    fn chromeos() {
+        let ua_str = "Mozilla/5.0 (X11; CrOS x86_64 13816.64.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/90.0.4430.100 Safari/537.36";
+        let result = get_device_info(ua_str);
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        match err.kind() {
+            HandlerErrorKind::InvalidUA => {}
+            _ => panic!("Incorrect error returned for test"),
+        }
+        assert!(err.tags.extra.get("ua") == Some(&ua_str.to_owned()));
+        assert!(err.tags.extra.get("name") == Some(&"chrome".to_owned()));
+        dbg!(err.tags);
+    }
+
+    #[test]
+    fn other_ua() {
+        assert!(get_device_info(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 11_2) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/88.0.4324.150 Safari/537.36")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn bots() {
+        let bot_uas = [
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+            "Mozilla/5.0 (compatible; bingbot/2.0; +http://www.bing.com/bingbot.htm)",
+            "Mozilla/5.0 (compatible; AhrefsBot/7.0; +http://ahrefs.com/robot/)",
+            "facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)",
+            "Mozilla/5.0 (compatible; spidertron/1.0)",
+            "Mozilla/5.0 (compatible; Slurp/3.0; http://help.yahoo.com/help/us/ysearch/slurp)",
+            "Mozilla/5.0 (X11; Linux x86_64) HeadlessChrome/90.0.4430.212 Safari/537.36",
+            "Mozilla/5.0 (compatible; Some-Preview/1.0)",
+            "Mozilla/5.0 (compatible; UptimeMonitor/1.0)",
+            "Python-urllib/3.9",
+            "curl/7.68.0",
+            "Wget/1.20.3 (linux-gnu)",
+        ];
+        for ua in bot_uas {
+            assert!(is_bot(ua), "expected {:?} to be detected as a bot", ua);
+            let info = get_device_info(ua).expect("bots get a DeviceInfo, not an error");
+            assert!(info.bot, "expected {:?} to produce DeviceInfo::bot", ua);
+        }
+
+        let non_bot_uas = [
+            "Mozilla/5.0 (Windows NT 6.1; Win64; x64; rv:61.0) Gecko/20100101 Firefox/61.0",
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 11_2) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/88.0.4324.150 Safari/537.36",
+        ];
+        for ua in non_bot_uas {
+            assert!(!is_bot(ua), "expected {:?} not to be detected as a bot", ua);
+        }
+    }
+
+    #[test]
+    fn windows_versions() {
+        use super::windows_version;
+
+        for (os_version, expected) in [
+            ("NT 10.0", (10, 0)),
+            ("NT 6.3", (8, 1)),
+            ("NT 6.2", (8, 0)),
+            ("NT 6.1", (7, 0)),
+            ("NT 5.1", (5, 1)),
+            ("NT 6.0", (0, 0)),
+            ("", (0, 0)),
+        ] {
+            assert_eq!(
+                windows_version(os_version),
+                expected,
+                "unexpected mapping for {:?}",
+                os_version
+            );
+        }
+    }
+
+    #[test]
+    fn firefox_family_variants() {
+        // Firefox for Android (Fenix), reporting as a phone.
+        let info =
+            get_device_info("Mozilla/5.0 (Android 13; Mobile; rv:115.0) Gecko/115.0 Firefox/115.0")
+                .expect("Fenix UA should parse");
+        assert_eq!(info.browser_variant, BrowserVariant::Firefox);
+        assert_eq!(info.os_family, OsFamily::Android);
+        assert_eq!(info.form_factor, FormFactor::Phone);
+        assert_eq!(info.ff_version, 115);
+
+        // Firefox for Android on a tablet -- note the missing `Mobile;`.
+        let info = get_device_info("Mozilla/5.0 (Android 13; rv:115.0) Gecko/115.0 Firefox/115.0")
+            .expect("Fenix tablet UA should parse");
+        assert_eq!(info.form_factor, FormFactor::Tablet);
+
+        // Firefox for iOS on an iPhone.
+        let info = get_device_info(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) FxiOS/116.0 Mobile/15E148 Safari/605.1.15",
+        )
+        .expect("FxiOS iPhone UA should parse");
+        assert_eq!(info.browser_variant, BrowserVariant::Firefox);
+        assert_eq!(info.os_family, OsFamily::IOs);
+        assert_eq!(info.form_factor, FormFactor::Phone);
+        assert_eq!(info.ff_version, 116);
+
+        // Firefox for iOS on an iPad.
+        let info = get_device_info(
+            "Mozilla/5.0 (iPad; CPU OS 16_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) FxiOS/116.0 Mobile/15E148 Safari/605.1.15",
+        )
+        .expect("FxiOS iPad UA should parse");
+        assert_eq!(info.form_factor, FormFactor::Tablet);
+
+        // Firefox Focus.
+        let info = get_device_info(
+            "Mozilla/5.0 (Android 13; Mobile; rv:115.0) Gecko/115.0 Firefox/115.0 Focus/115.0",
+        )
+        .expect("Focus UA should parse");
+        assert_eq!(info.browser_variant, BrowserVariant::Focus);
+        assert_eq!(info.ff_version, 115);
+
+        // Klar (Focus's German sibling).
+        let info = get_device_info(
+            "Mozilla/5.0 (Android 13; Mobile; rv:115.0) Gecko/115.0 Firefox/115.0 Klar/115.0",
+        )
+        .expect("Klar UA should parse");
+        assert_eq!(info.browser_variant, BrowserVariant::Klar);
+    }
+}